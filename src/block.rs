@@ -0,0 +1,175 @@
+use crate::*;
+
+/// Builds a function body line by line, with indentation derived from `open`/`close` nesting instead of
+/// counting levels by hand.
+///
+/// Unbalanced nesting is recorded and reported by `into_lines`, rather than rendering misindented code.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Block {
+    lines: Vec<CodeLine>,
+    /// The lines that opened the blocks that are not closed yet.
+    open: Vec<String>,
+    errors: Vec<String>,
+}
+
+impl Block {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a line at the current depth.
+    pub fn line(mut self, text: &str) -> Self {
+        self.lines.push(CodeLine::new(self.depth(), text));
+        self
+    }
+
+    /// Adds a line that opens a block, such as `match x {`, and indents the following lines.
+    pub fn open(mut self, text: &str) -> Self {
+        self = self.line(text);
+        self.open.push(text.to_string());
+        self
+    }
+
+    /// Closes the innermost block and adds `text`, such as `}` or `});`, at the outer depth.
+    pub fn close(mut self, text: &str) -> Self {
+        if self.open.pop().is_none() {
+            self.errors.push(format!("`{}` closes a block that was never opened", text));
+        }
+
+        self.line(text)
+    }
+
+    /// Closes the innermost block and opens another at the same depth, such as `} else {`.
+    pub fn reopen(self, text: &str) -> Self {
+        self.close(text).open_without_line(text)
+    }
+
+    fn open_without_line(mut self, text: &str) -> Self {
+        self.open.push(text.to_string());
+        self
+    }
+
+    /// Adds `header {`, the lines added by `body` and `}`. A `{` is only appended if `header` lacks one.
+    pub fn block<F: FnOnce(Block) -> Block>(self, header: &str, body: F) -> Self {
+        let header = match header.trim_end().ends_with('{') {
+            true => header.to_string(),
+            false => format!("{} {{", header),
+        };
+
+        let depth = self.open.len();
+        let mut block = body(self.open(&header));
+
+        if block.open.len() != depth + 1 {
+            block.errors.push(format!("the body of `{}` does not close every block it opens", header));
+            block.open.truncate(depth + 1);
+        }
+
+        block.close("}")
+    }
+
+    fn depth(&self) -> u8 {
+        self.open.len() as u8
+    }
+
+    /// The lines of the block, or a description of how the nesting is unbalanced.
+    pub fn into_lines(self) -> Result<Vec<CodeLine>, CodeGenError> {
+        let mut errors = self.errors;
+
+        if !self.open.is_empty() {
+            let unclosed: Vec<String> = self.open.iter().map(|o| format!("`{}`", o)).collect();
+            errors.push(format!("unclosed blocks: {}", unclosed.join(", ")));
+        }
+
+        match errors.is_empty() {
+            true => Ok(self.lines),
+            false => Err(CodeGenError::UnbalancedBlock(errors)),
+        }
+    }
+}
+
+impl Function {
+    /// Appends the lines of `block`, failing if its nesting is unbalanced.
+    pub fn with_block(mut self, block: Block) -> Result<Self, CodeGenError> {
+        self.lines.extend(block.into_lines()?);
+        Ok(self)
+    }
+}
+
+impl TraitFunction {
+    /// Appends the lines of `block`, failing if its nesting is unbalanced.
+    pub fn with_block(mut self, block: Block) -> Result<Self, CodeGenError> {
+        self.lines.extend(block.into_lines()?);
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_blocks() {
+        let f = Function::new("count")
+            .with_parameters("items: &[Item]")
+            .with_return("u32".to_string())
+            .with_block(Block::new()
+                .line("let mut total = 0;")
+                .block("for item in items", |b| b
+                    .open("match item {")
+                    .line("Item::A(n) => total += n,")
+                    .line("Item::B => {}")
+                    .close("}"))
+                .line("total"))
+            .unwrap();
+
+        assert_eq!(
+            "    pub fn count(items: &[Item]) -> u32 {\n        let mut total = 0;\n        for item in items {\n            match item {\n                Item::A(n) => total += n,\n                Item::B => {}\n            }\n        }\n        total\n    }\n",
+            f.to_string()
+        );
+    }
+
+    #[test]
+    fn reopen() {
+        let lines = Block::new()
+            .open("if x {")
+            .line("a();")
+            .reopen("} else {")
+            .line("b();")
+            .close("}")
+            .into_lines()
+            .unwrap();
+
+        let rendered: Vec<String> = lines.iter().map(ToString::to_string).collect();
+        assert_eq!(vec!["    if x {", "        a();", "    } else {", "        b();", "    }"], rendered);
+    }
+
+    #[test]
+    fn unclosed_block_is_an_error() {
+        let result = Block::new().open("match x {").line("_ => {}").into_lines();
+
+        assert_eq!(Err(CodeGenError::UnbalancedBlock(vec!["unclosed blocks: `match x {`".to_string()])), result);
+    }
+
+    #[test]
+    fn extra_close_is_an_error() {
+        assert!(Block::new().line("x").close("}").into_lines().is_err());
+    }
+
+    #[test]
+    fn unbalanced_block_body_is_an_error() {
+        let result = Block::new()
+            .block("if x", |b| b.open("loop {"))
+            .into_lines();
+
+        assert_eq!("Unbalanced Block: the body of `if x {` does not close every block it opens", result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn trait_function_with_block() {
+        let f = TraitFunction::new("run")
+            .with_block(Block::new().block("if ready", |b| b.line("go();")))
+            .unwrap();
+
+        assert_eq!("    fn run() {\n        if ready {\n            go();\n        }\n    }\n", f.to_string());
+    }
+}
@@ -0,0 +1,98 @@
+use std::env;
+use std::fmt::Display;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::Mod;
+use crate::emitter::write_if_changed;
+
+/// Glue for generating code from a `build.rs`: writes `$OUT_DIR/<name>.rs`, tells cargo which input files to
+/// watch and produces the `include!` that pulls the generated file into the crate.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BuildOutput {
+    pub name: String,
+    pub out_dir: Option<PathBuf>,
+    pub inputs: Vec<PathBuf>,
+}
+
+impl BuildOutput {
+    /// Output to `$OUT_DIR/<name>.rs`, with `OUT_DIR` as set by cargo for build scripts.
+    pub fn new(name: &str) -> Self {
+        BuildOutput {
+            name: name.trim_end_matches(".rs").to_string(),
+            out_dir: env::var_os("OUT_DIR").map(PathBuf::from),
+            inputs: vec![],
+        }
+    }
+
+    pub fn with_out_dir<P: AsRef<Path>>(mut self, out_dir: P) -> Self {
+        self.out_dir = Some(out_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Adds a file the generated code is derived from, so cargo reruns the build script when it changes.
+    pub fn add_input<P: AsRef<Path>>(mut self, input: P) -> Self {
+        self.inputs.push(input.as_ref().to_path_buf());
+        self
+    }
+
+    /// The file the code is written to.
+    pub fn path(&self) -> io::Result<PathBuf> {
+        self.out_dir.as_ref()
+            .map(|dir| dir.join(format!("{}.rs", self.name)))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set, BuildOutput must be used from a build script"))
+    }
+
+    /// Writes `code` to the output file, unless it already has that content. Returns whether the file was written.
+    pub fn write<D: Display>(&self, code: D) -> io::Result<bool> {
+        write_if_changed(&self.path()?, &code.to_string())
+    }
+
+    /// Writes the items of `module`, without the surrounding `mod`, to the output file.
+    pub fn write_mod(&self, module: &Mod) -> io::Result<bool> {
+        self.write(module.contents())
+    }
+
+    /// The `cargo:rerun-if-changed` lines for the inputs.
+    pub fn rerun_if_changed(&self) -> String {
+        self.inputs.iter()
+            .map(|input| format!("cargo:rerun-if-changed={}\n", input.display()))
+            .collect()
+    }
+
+    pub fn print_rerun_if_changed(&self) {
+        print!("{}", self.rerun_if_changed());
+    }
+
+    /// The line that includes the generated file in the crate's source.
+    pub fn include_snippet(&self) -> String {
+        format!("include!(concat!(env!(\"OUT_DIR\"), \"/{}.rs\"));\n", self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn include_snippet() {
+        assert_eq!("include!(concat!(env!(\"OUT_DIR\"), \"/units.rs\"));\n", BuildOutput::new("units.rs").include_snippet());
+    }
+
+    #[test]
+    fn rerun_if_changed() {
+        let output = BuildOutput::new("units")
+            .add_input("units.txt")
+            .add_input("build.rs");
+
+        assert_eq!("cargo:rerun-if-changed=units.txt\ncargo:rerun-if-changed=build.rs\n", output.rerun_if_changed());
+    }
+
+    #[test]
+    fn path_requires_out_dir() {
+        let mut output = BuildOutput::new("units");
+        output.out_dir = None;
+
+        assert!(output.path().is_err());
+        assert_eq!(Path::new("out/units.rs"), output.with_out_dir("out").path().unwrap());
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt::{Display, Formatter, Error};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use crate::{Emitter, Mod, RenderedFile, unified_diff};
+use crate::emitter::manifest_path;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum FileStatus {
+    /// The file would be created.
+    Added,
+    /// The file was generated before and would be removed.
+    Removed,
+    /// The file exists with different content.
+    Changed,
+}
+
+/// A file that is out of date, with a unified diff from its content on disk to the generated content.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FileDiff {
+    pub path: PathBuf,
+    pub status: FileStatus,
+    pub diff: String,
+}
+
+impl Display for FileDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.diff)
+    }
+}
+
+/// The result of comparing generated files against the files on disk.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct CheckReport {
+    pub files: Vec<FileDiff>,
+}
+
+impl CheckReport {
+    pub fn is_up_to_date(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+impl Display for CheckReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        for file in self.files.iter() {
+            write!(f, "{}", file)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Emitter {
+    /// Compares the files `emit` would write for `root` with the files on disk, without writing anything.
+    pub fn check(&self, root: &Mod) -> io::Result<CheckReport> {
+        self.check_files(&self.render(root))
+    }
+
+    /// Like `check`, for files rendered from arbitrary items.
+    pub fn check_files(&self, files: &[RenderedFile]) -> io::Result<CheckReport> {
+        let mut report = CheckReport::default();
+
+        for file in files.iter() {
+            let name = manifest_path(&file.path);
+
+            let (status, diff) = match fs::read_to_string(self.dir.join(&file.path)) {
+                Ok(existing) if existing == file.content => continue,
+                Ok(existing) => (FileStatus::Changed, unified_diff(&existing, &file.content, &format!("a/{}", name), &format!("b/{}", name))),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => (FileStatus::Added, unified_diff("", &file.content, "/dev/null", &format!("b/{}", name))),
+                Err(e) => return Err(e),
+            };
+
+            report.files.push(FileDiff { path: file.path.clone(), status, diff });
+        }
+
+        for stale in self.stale_files(files)? {
+            let existing = fs::read_to_string(self.dir.join(&stale))?;
+            let diff = unified_diff(&existing, "", &format!("a/{}", manifest_path(&stale)), "/dev/null");
+
+            report.files.push(FileDiff { path: stale, status: FileStatus::Removed, diff });
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Struct;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("code_gen_check_{}_{}", std::process::id(), name));
+        fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    #[test]
+    fn up_to_date() {
+        let dir = temp_dir("up_to_date");
+        let emitter = Emitter::new(&dir);
+        let root = Mod::new("root").add_item(Struct::new("A"));
+
+        emitter.emit(&root).unwrap();
+
+        assert!(emitter.check(&root).unwrap().is_up_to_date());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn added_changed_and_removed() {
+        let dir = temp_dir("changes");
+        let emitter = Emitter::new(&dir);
+
+        emitter.emit(&Mod::new("root")
+            .add_item(Mod::new("old").add_item(Struct::new("Old")))
+            .add_item(Struct::new("A"))).unwrap();
+
+        let report = emitter.check(&Mod::new("root")
+            .add_item(Mod::new("new").add_item(Struct::new("New")))
+            .add_item(Struct::new("A"))).unwrap();
+
+        let statuses: Vec<_> = report.files.iter().map(|f| (manifest_path(&f.path), f.status)).collect();
+        assert_eq!(vec![
+            ("lib.rs".to_string(), FileStatus::Changed),
+            ("new.rs".to_string(), FileStatus::Added),
+            ("old.rs".to_string(), FileStatus::Removed),
+        ], statuses);
+
+        assert_eq!("--- a/lib.rs\n+++ b/lib.rs\n@@ -1,3 +1,3 @@\n-pub mod old;\n+pub mod new;\n \n pub struct A;\n", report.files[0].diff);
+        assert_eq!("--- /dev/null\n+++ b/new.rs\n@@ -0,0 +1 @@\n+pub struct New;\n", report.files[1].diff);
+        assert_eq!("--- a/old.rs\n+++ /dev/null\n@@ -1 +0,0 @@\n-pub struct Old;\n", report.files[2].diff);
+
+        // nothing was written
+        assert!(!dir.join("new.rs").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn arbitrary_files() {
+        let dir = temp_dir("arbitrary");
+        let emitter = Emitter::new(&dir);
+
+        let report = emitter.check_files(&[RenderedFile::new("types.rs", Struct::new("A"))]).unwrap();
+
+        assert_eq!(1, report.files.len());
+        assert_eq!(FileStatus::Added, report.files[0].status);
+    }
+}
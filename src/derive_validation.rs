@@ -0,0 +1,600 @@
+use crate::*;
+use crate::generics::split_top_level;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Whether a type implements a derivable trait.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Capability {
+    /// Implemented regardless of the type arguments, e.g. `Rc<T>: Clone`.
+    Always,
+    /// Implemented when every type argument implements it, e.g. `Vec<T>: Clone`.
+    IfArgs,
+    /// Implemented when every type argument implements it and another trait, e.g. `Cell<T>: Clone` needs `T: Copy`.
+    IfArgsAnd(Derive),
+}
+
+/// A table of which derivable traits each type implements, used to check `Struct` and `Enum` derives.
+///
+/// Types that are not in the table are assumed to implement everything, unless the table is strict.
+/// Custom derives are never checked.
+#[derive(Debug, Default, Clone)]
+pub struct TypeCapabilities {
+    types: HashMap<String, HashMap<Derive, Capability>>,
+    pub strict: bool,
+}
+
+impl TypeCapabilities {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The capabilities of primitives and common standard library types.
+    pub fn std() -> Self {
+        use Derive::*;
+
+        let all = [Debug, Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash];
+        let no_copy = [Debug, Default, Clone, Eq, PartialEq, Ord, PartialOrd, Hash];
+        let float = [Debug, Default, Copy, Clone, PartialEq, PartialOrd];
+        let hashed = [Debug, Default, Clone, Eq, PartialEq];
+        let no_default = [Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash];
+
+        let mut capabilities = Self::new();
+
+        for primitive in ["bool", "char", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize", "()"].iter() {
+            capabilities = capabilities.add_type(primitive, &all);
+        }
+
+        for float_type in ["f32", "f64"].iter() {
+            capabilities = capabilities.add_type(float_type, &float);
+        }
+
+        capabilities = capabilities
+            .add_type("str", &[Debug, Eq, PartialEq, Ord, PartialOrd, Hash])
+            .add_type("String", &no_copy)
+            .add_type("Vec", &no_copy)
+            .add_type("VecDeque", &no_copy)
+            .add_type("LinkedList", &no_copy)
+            .add_type("BTreeMap", &no_copy)
+            .add_type("BTreeSet", &no_copy)
+            .add_type("Box", &no_copy)
+            .add_type("Option", &all)
+            .add_type("Result", &no_default)
+            .add_type("HashMap", &hashed)
+            .add_type("HashSet", &hashed)
+            .add_type("Cell", &[Debug, Default])
+            .add_type("RefCell", &[Debug, Default, Clone, Eq, PartialEq, Ord, PartialOrd])
+            .add_type("Mutex", &[Debug, Default])
+            .add_type("RwLock", &[Debug, Default])
+            .add_type("ManuallyDrop", &all)
+            .add_type("Duration", &all)
+            .add_type("Instant", &no_default)
+            .add_type("NonZeroU32", &no_default)
+            .add_type("NonZeroUsize", &no_default);
+
+        // `Cell` hands out copies of its value
+        for derive in [Clone, Eq, PartialEq, Ord, PartialOrd].iter() {
+            capabilities = capabilities.add_capability("Cell", derive.clone(), Capability::IfArgsAnd(Copy));
+        }
+
+        // empty collections need no default elements
+        for collection in ["Vec", "VecDeque", "LinkedList", "BTreeMap", "BTreeSet", "HashMap", "HashSet", "Option"].iter() {
+            capabilities = capabilities.add_capability(collection, Default, Capability::Always);
+        }
+
+        for rc in ["Rc", "Arc"].iter() {
+            capabilities = capabilities
+                .add_type(rc, &[Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash])
+                .add_capability(rc, Clone, Capability::Always);
+        }
+
+        for derive in all.iter() {
+            capabilities = capabilities.add_capability("PhantomData", derive.clone(), Capability::Always);
+        }
+
+        capabilities
+    }
+
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Registers a type whose listed derives hold whenever its type arguments implement them.
+    pub fn add_type(mut self, name: &str, derives: &[Derive]) -> Self {
+        let entry = self.types.entry(name.to_string()).or_default();
+
+        for derive in derives.iter() {
+            entry.insert(derive.clone(), Capability::IfArgs);
+        }
+
+        self
+    }
+
+    pub fn add_capability(mut self, name: &str, derive: Derive, capability: Capability) -> Self {
+        self.types.entry(name.to_string())
+            .or_default()
+            .insert(derive, capability);
+        self
+    }
+
+    /// Registers a generated struct with the traits it derives.
+    pub fn add_struct(self, strct: &Struct) -> Self {
+        let derives: Vec<Derive> = strct.derives.iter().collect();
+        self.add_type(strct.typ.name.as_str(), &derives)
+    }
+
+    /// Registers a generated enum with the traits it derives.
+    pub fn add_enum(self, enm: &Enum) -> Self {
+        let derives: Vec<Derive> = enm.derives.iter().collect();
+        self.add_type(enm.typ.name.as_str(), &derives)
+    }
+
+    /// Whether `typ` implements `derive`, treating the names in `params` as generic parameters
+    /// that are bounded by the derive.
+    pub fn supports(&self, typ: &str, derive: &Derive, params: &[String]) -> bool {
+        if let Derive::Custom(_) = derive {
+            return true;
+        }
+
+        let typ = typ.trim();
+
+        if let Some(inner) = typ.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+            return split_top_level(inner, ',').iter()
+                .filter(|t| !t.trim().is_empty())
+                .all(|t| self.supports(t, derive, params));
+        }
+
+        if let Some(inner) = typ.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            return match split_top_level(inner, ';').as_slice() {
+                [element, _len] => self.supports(element, derive, params),
+                _ => false,
+            };
+        }
+
+        // raw pointers implement everything but `Default` by address
+        if typ.starts_with('*') {
+            return *derive != Derive::Default;
+        }
+
+        if let Some(reference) = typ.strip_prefix('&') {
+            return self.reference_supports(reference, derive, params);
+        }
+
+        match Type::from_str(typ) {
+            Ok(t) => self.type_supports(&t, derive, params),
+            Err(_) => !self.strict,
+        }
+    }
+
+    fn reference_supports(&self, reference: &str, derive: &Derive, params: &[String]) -> bool {
+        let reference = match reference.strip_prefix('\'') {
+            Some(lifetime) => lifetime.trim_start_matches(|c: char| c.is_alphanumeric() || c == '_').trim_start(),
+            None => reference,
+        };
+
+        if let Some(target) = reference.strip_prefix("mut ") {
+            return match derive {
+                Derive::Copy | Derive::Clone | Derive::Default => false,
+                _ => self.supports(target, derive, params),
+            };
+        }
+
+        match derive {
+            Derive::Copy | Derive::Clone => true,
+            Derive::Default => reference.trim() == "str",
+            _ => self.supports(reference, derive, params),
+        }
+    }
+
+    fn type_supports(&self, typ: &Type, derive: &Derive, params: &[String]) -> bool {
+        let name = typ.name.as_str();
+
+        if params.iter().any(|p| p == name) {
+            return true;
+        }
+
+        let short_name = name.rsplit("::").next().unwrap_or(name);
+
+        let capabilities = match self.types.get(name).or_else(|| self.types.get(short_name)) {
+            Some(capabilities) => capabilities,
+            None => return !self.strict,
+        };
+
+        match capabilities.get(derive) {
+            None => false,
+            Some(Capability::Always) => true,
+            Some(Capability::IfArgs) => self.args_support(typ, derive, params),
+            Some(Capability::IfArgsAnd(other)) => self.args_support(typ, derive, params) && self.args_support(typ, other, params),
+        }
+    }
+
+    fn args_support(&self, typ: &Type, derive: &Derive, params: &[String]) -> bool {
+        typ.types.iter().all(|g| match g {
+            Generic::Type(t) => self.type_supports(t, derive, params),
+            _ => true,
+        })
+    }
+
+    pub fn check_struct(&self, strct: &Struct) -> Vec<DeriveIssue> {
+        let params = type_params(&strct.typ);
+        let mut issues = vec![];
+
+        for derive in strct.derives.iter() {
+            for field in strct.fields.iter() {
+                if !self.supports(&field.field_type.to_string(), &derive, &params) {
+                    issues.push(DeriveIssue::field(&strct.typ, derive.clone(), field.name.as_str(), &field.field_type.to_string()));
+                }
+            }
+        }
+
+        issues
+    }
+
+    pub fn check_enum(&self, enm: &Enum) -> Vec<DeriveIssue> {
+        let params = type_params(&enm.typ);
+        let mut issues = vec![];
+
+        for derive in enm.derives.iter() {
+            if derive == Derive::Default {
+                let defaults: Vec<&EnumOption> = enm.options.iter().filter(|o| o.default).collect();
+
+                match defaults.as_slice() {
+                    [] => issues.push(DeriveIssue::new(&enm.typ, derive, "enum has no #[default] variant")),
+                    [option] if !option.option_types.is_empty() => {
+                        issues.push(DeriveIssue::new(&enm.typ, derive, &format!("#[default] variant {} is not a unit variant", option.name)))
+                    },
+                    [_] => {},
+                    _ => issues.push(DeriveIssue::new(&enm.typ, derive, "enum has more than one #[default] variant")),
+                }
+
+                continue;
+            }
+
+            for option in enm.options.iter() {
+                for (i, ty) in option.option_types.iter().enumerate() {
+                    if !self.supports(ty, &derive, &params) {
+                        issues.push(DeriveIssue::field(&enm.typ, derive.clone(), &format!("{}.{}", option.name, i), ty));
+                    }
+                }
+            }
+        }
+
+        issues
+    }
+
+    /// Checks that every field of `unn` is `Copy` or wrapped in `ManuallyDrop`, as unions require, and that
+    /// it only derives `Copy` and `Clone`, the only standard derives that support unions.
+    pub fn check_union(&self, unn: &Union) -> Vec<DeriveIssue> {
+        let params = type_params(&unn.typ);
+        let mut issues = vec![];
+
+        for field in unn.fields.iter() {
+            let field_type = field.field_type.to_string();
+
+            if self.supports(&field_type, &Derive::Copy, &params) {
+                continue;
+            }
+
+            if !is_manually_drop(&field.field_type) {
+                issues.push(DeriveIssue {
+                    typ: unn.typ.clone(),
+                    derive: None,
+                    field: Some(field.name.to_string()),
+                    message: format!("union field `{}` of type `{}` is not Copy, wrap it in ManuallyDrop", field.name, field_type),
+                });
+            }
+
+            if unn.derives.contains(&Derive::Copy) {
+                issues.push(DeriveIssue::field(&unn.typ, Derive::Copy, field.name.as_str(), &field_type));
+            }
+        }
+
+        // a derived `Clone` for a union is a copy, so it needs `Copy` too
+        let copy = unn.derives.contains(&Derive::Copy) && !issues.iter().any(|i| i.derive == Some(Derive::Copy));
+
+        for derive in unn.derives.iter() {
+            match derive {
+                Derive::Copy | Derive::Custom(_) => {},
+                Derive::Clone if copy => {},
+                Derive::Clone => issues.push(DeriveIssue::new(&unn.typ, derive, "unions can only derive Clone together with Copy")),
+                _ => issues.push(DeriveIssue::new(&unn.typ, derive, "unions can only derive Copy and Clone")),
+            }
+        }
+
+        issues
+    }
+
+    /// Removes the derives that cannot be derived for `strct`, returning why each was removed.
+    pub fn fix_struct(&self, strct: &mut Struct) -> Vec<DeriveIssue> {
+        let issues = self.check_struct(strct);
+        remove_derives(&mut strct.derives, &issues);
+        issues
+    }
+
+    /// Removes the derives that cannot be derived for `enm`, returning why each was removed.
+    pub fn fix_enum(&self, enm: &mut Enum) -> Vec<DeriveIssue> {
+        let issues = self.check_enum(enm);
+        remove_derives(&mut enm.derives, &issues);
+        issues
+    }
+
+    /// Removes the derives that cannot be derived for `unn`, returning every issue. Fields that are not
+    /// `Copy` cannot be fixed by removing derives and still need to be changed.
+    pub fn fix_union(&self, unn: &mut Union) -> Vec<DeriveIssue> {
+        let issues = self.check_union(unn);
+        remove_derives(&mut unn.derives, &issues);
+
+        issues
+    }
+}
+
+fn type_params(typ: &Type) -> Vec<String> {
+    typ.types.iter()
+        .filter_map(|g| match g {
+            Generic::Type(t) => Some(t.name.to_string()),
+            Generic::Param(p) => Some(p.typ.name.to_string()),
+            Generic::Lifetime(_) => None,
+        })
+        .collect()
+}
+
+/// Whether `typ` is `ManuallyDrop`, by its last path segment so `std::mem::ManuallyDrop<T>` counts too.
+fn is_manually_drop(typ: &Type) -> bool {
+    typ.name.as_str().rsplit("::").next() == Some("ManuallyDrop")
+}
+
+fn remove_derives(derives: &mut Derives, issues: &[DeriveIssue]) {
+    for derive in issues.iter().filter_map(|i| i.derive.as_ref()) {
+        derives.remove(derive);
+    }
+}
+
+/// A derive that cannot be applied, and the field that prevents it.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct DeriveIssue {
+    pub typ: Type,
+    /// The derive that cannot be applied, or `None` for a field that is invalid whatever is derived, such as a
+    /// union field that is not `Copy`.
+    pub derive: Option<Derive>,
+    /// The struct field name or `Variant.index` of the offending field, if the issue is caused by a field.
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl DeriveIssue {
+    fn new(typ: &Type, derive: Derive, message: &str) -> Self {
+        DeriveIssue {
+            typ: typ.clone(),
+            derive: Some(derive),
+            field: None,
+            message: message.to_string(),
+        }
+    }
+
+    fn field(typ: &Type, derive: Derive, field: &str, field_type: &str) -> Self {
+        DeriveIssue {
+            typ: typ.clone(),
+            message: format!("field `{}` of type `{}` does not implement {}", field, field_type, derive),
+            derive: Some(derive),
+            field: Some(field.to_string()),
+        }
+    }
+}
+
+impl Display for DeriveIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match &self.derive {
+            Some(derive) => write!(f, "cannot derive {} for {}: {}", derive, self.typ, self.message),
+            None => write!(f, "invalid {}: {}", self.typ, self.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn derives(derives: &[Derive]) -> Derives {
+        derives.iter().cloned().collect()
+    }
+
+    #[test]
+    fn copy_with_string_field_is_reported() {
+        let s = Struct::new("Named")
+            .with_derives(derives(&[Derive::Debug, Derive::Copy]))
+            .add_field(Field::new("id", "u32"))
+            .add_field(Field::new("name", "String"));
+
+        let issues = TypeCapabilities::std().check_struct(&s);
+
+        assert_eq!(1, issues.len());
+        assert_eq!(Some(Derive::Copy), issues[0].derive);
+        assert_eq!(Some("name".to_string()), issues[0].field);
+        assert_eq!(
+            "cannot derive Copy for Named: field `name` of type `String` does not implement Copy",
+            issues[0].to_string()
+        );
+    }
+
+    #[test]
+    fn float_fields_prevent_eq_and_hash() {
+        let s = Struct::new("Point")
+            .with_derives(derives(&[Derive::PartialEq, Derive::Hash]))
+            .add_field(Field::new("x", "f64"));
+
+        let derives: Vec<Derive> = TypeCapabilities::std().check_struct(&s).into_iter().filter_map(|i| i.derive).collect();
+
+        assert_eq!(vec![Derive::Eq, Derive::Hash], derives);
+    }
+
+    #[test]
+    fn type_arguments_are_checked() {
+        let caps = TypeCapabilities::std();
+
+        assert!(caps.supports("Option<u32>", &Derive::Copy, &[]));
+        assert!(!caps.supports("Option<String>", &Derive::Copy, &[]));
+        assert!(caps.supports("Rc<Mutex<u32>>", &Derive::Clone, &[]));
+        assert!(!caps.supports("HashMap<u32, u32>", &Derive::Hash, &[]));
+        assert!(caps.supports("PhantomData<String>", &Derive::Copy, &[]));
+    }
+
+    #[test]
+    fn empty_collections_are_default() {
+        let caps = TypeCapabilities::std();
+        let mut s = Struct::new("Timings")
+            .with_derives(derives(&[Derive::Debug, Derive::Default]))
+            .add_field(Field::new("samples", "Vec<Instant>"))
+            .add_field(Field::new("by_name", "HashMap<String, Instant>"))
+            .add_field(Field::new("last", "Option<Instant>"));
+
+        assert!(caps.fix_struct(&mut s).is_empty());
+        assert_eq!("#[derive(Debug, Default)]\n", s.derives.to_string());
+        assert!(!caps.supports("Box<Instant>", &Derive::Default, &[]));
+    }
+
+    #[test]
+    fn cell_clone_needs_copy() {
+        let caps = TypeCapabilities::std();
+
+        assert!(caps.supports("Cell<u32>", &Derive::Clone, &[]));
+        assert!(!caps.supports("Cell<String>", &Derive::Clone, &[]));
+        assert!(!caps.supports("Cell<String>", &Derive::PartialEq, &[]));
+        assert!(caps.supports("RefCell<String>", &Derive::Clone, &[]));
+    }
+
+    #[test]
+    fn references_tuples_and_arrays() {
+        let caps = TypeCapabilities::std();
+
+        assert!(caps.supports("&'a String", &Derive::Copy, &[]));
+        assert!(!caps.supports("&mut u32", &Derive::Clone, &[]));
+        assert!(caps.supports("&'static str", &Derive::Default, &[]));
+        assert!(!caps.supports("(f32, f32)", &Derive::Eq, &[]));
+        assert!(caps.supports("(u8, char)", &Derive::Hash, &[]));
+        assert!(caps.supports("[u8; 4]", &Derive::Copy, &[]));
+        assert!(!caps.supports("*const u8", &Derive::Default, &[]));
+    }
+
+    #[test]
+    fn generic_parameters_are_assumed_capable() {
+        let s = Struct::new("Wrapper<T>")
+            .with_derives(derives(&[Derive::Copy]))
+            .add_field(Field::new("value", "Option<T>"));
+
+        assert!(TypeCapabilities::std().strict().check_struct(&s).is_empty());
+    }
+
+    #[test]
+    fn unknown_types_fail_only_when_strict() {
+        let s = Struct::new("Holder")
+            .with_derives(derives(&[Derive::Copy]))
+            .add_field(Field::new("position", "Position"));
+
+        assert!(TypeCapabilities::std().check_struct(&s).is_empty());
+        assert_eq!(2, TypeCapabilities::std().strict().check_struct(&s).len());
+    }
+
+    #[test]
+    fn user_types_are_looked_up() {
+        let position = Struct::new("Position")
+            .with_derives(Derives::with_debug_clone())
+            .add_field(Field::new("x", "f64"));
+
+        let holder = Struct::new("Holder")
+            .with_derives(derives(&[Derive::Debug, Derive::Copy]))
+            .add_field(Field::new("position", "Position"));
+
+        let issues = TypeCapabilities::std().add_struct(&position).check_struct(&holder);
+
+        assert_eq!(1, issues.len());
+        assert_eq!(Some(Derive::Copy), issues[0].derive);
+    }
+
+    #[test]
+    fn enum_default_requires_default_variant() {
+        let e = Enum::new("Mode")
+            .with_derives(Derives::with_debug_default())
+            .add_option(EnumOption::new("On", vec![]))
+            .add_option(EnumOption::new("Off", vec![]));
+
+        let caps = TypeCapabilities::std();
+        let issues = caps.check_enum(&e);
+
+        assert_eq!(1, issues.len());
+        assert_eq!("cannot derive Default for Mode: enum has no #[default] variant", issues[0].to_string());
+
+        let e = e.clone().add_option(EnumOption::new("Auto", vec![]).as_default());
+        assert!(caps.check_enum(&e).is_empty());
+    }
+
+    #[test]
+    fn enum_variant_fields_are_checked() {
+        let e = Enum::new("Value")
+            .with_derives(derives(&[Derive::Debug, Derive::Hash]))
+            .add_option(EnumOption::new("Number", vec!["u32", "f32"]));
+
+        let issues = TypeCapabilities::std().check_enum(&e);
+
+        assert!(issues.iter().all(|i| i.field == Some("Number.1".to_string())));
+        assert_eq!(2, issues.len());
+    }
+
+    #[test]
+    fn fix_struct_removes_invalid_derives() {
+        let mut s = Struct::new("Named")
+            .with_derives(derives(&[Derive::Debug, Derive::Copy, Derive::Ord]))
+            .add_field(Field::new("name", "String"))
+            .add_field(Field::new("weight", "f32"));
+
+        let issues = TypeCapabilities::std().fix_struct(&mut s);
+
+        assert!(!issues.is_empty());
+        assert_eq!("#[derive(Debug, Clone, PartialOrd)]\n", s.derives.to_string());
+    }
+
+    #[test]
+    fn union_fields_must_be_copy() {
+        let caps = TypeCapabilities::std();
+        let unn = Union::new("Value")
+            .add_field(Field::new("int", "i32"))
+            .add_field(Field::new("text", "String"))
+            .add_field(Field::new("boxed", "ManuallyDrop<String>"));
+
+        let issues = caps.check_union(&unn);
+
+        assert_eq!(1, issues.len());
+        assert_eq!(None, issues[0].derive);
+        assert_eq!(Some("text".to_string()), issues[0].field);
+        assert_eq!("invalid Value: union field `text` of type `String` is not Copy, wrap it in ManuallyDrop", issues[0].to_string());
+
+        let unn = Union::new("Value").add_field(Field::new("boxed", "std::mem::ManuallyDrop<String>"));
+        assert!(caps.check_union(&unn).is_empty());
+    }
+
+    #[test]
+    fn fix_union_removes_clone_with_copy() {
+        let mut unn = Union::new("Value")
+            .with_derives(derives(&[Derive::Copy, Derive::Clone]))
+            .add_field(Field::new("int", "i32"))
+            .add_field(Field::new("boxed", "ManuallyDrop<String>"));
+
+        let issues = TypeCapabilities::std().fix_union(&mut unn);
+
+        assert_eq!(vec![Some(Derive::Copy), Some(Derive::Clone)], issues.iter().map(|i| i.derive.clone()).collect::<Vec<_>>());
+        assert!(unn.derives.is_empty());
+    }
+
+    #[test]
+    fn union_derives_are_limited() {
+        let caps = TypeCapabilities::std();
+        let mut unn = Union::new("Value")
+            .with_derives(Derives::with_debug_clone())
+            .add_field(Field::new("int", "i32"));
+
+        let issues = caps.fix_union(&mut unn);
+
+        assert_eq!(2, issues.len());
+        assert!(unn.derives.is_empty());
+    }
+}
@@ -1,10 +1,19 @@
-use std::collections::HashSet;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::{Hash, Hasher};
 use std::fmt::{Display, Formatter, Error};
 use std::iter::FromIterator;
-use crate::{StrConcat, TraitName};
+use std::str::FromStr;
+use crate::{CamelCase, Render, Renderer, CodeGenError, InputKind};
+use crate::render::display_via_render;
 
 #[derive(Debug, Default, Clone, Eq, PartialEq)]
-pub struct Derives(HashSet<Derive>);
+pub struct Derives {
+    /// Kept sorted in the order the derives are rendered.
+    derives: BTreeSet<Derive>,
+    /// Helper attributes that belong to a derive, e.g. `serde(rename_all = "camelCase")` for `Serialize`.
+    helpers: BTreeMap<Derive, Vec<String>>,
+}
 
 impl Derives {
     pub fn new() -> Self {
@@ -56,25 +65,60 @@ impl Derives {
             _ => {},
         }
 
-        self.0.insert(derive);
+        // a path inserted again replaces the one already there, which may differ in whether it is imported
+        self.derives.replace(derive);
     }
 
-    fn get_sorted_derive_vec(&self) -> Vec<Derive> {
-        let mut derives: Vec<Derive> = self.0.iter().cloned().collect();
-        derives.sort();
-        derives
+    /// Inserts `derive` together with a helper attribute, given without the surrounding `#[...]`.
+    pub fn insert_with_helper(&mut self, derive: Derive, helper: &str) {
+        self.helpers.entry(derive.clone())
+            .or_default()
+            .push(helper.to_string());
+
+        self.insert(derive);
+    }
+
+    /// The paths that must be imported for the derives to resolve, e.g. `serde::Serialize`.
+    pub fn imports(&self) -> Vec<String> {
+        self.iter()
+            .filter_map(|d| match d {
+                Derive::Custom(path) if path.imported => Some(path.path.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn contains(&self, derive: &Derive) -> bool {
+        self.derives.contains(derive)
     }
 
-    fn get_str_concat<'a>(&self) -> impl Display + 'a {
-        StrConcat {
-            iter: self.get_sorted_derive_vec(),
-            left_bound: "#[derive(",
-            right_bound: ")]",
-            item_prepend: "",
-            item_append: "",
-            join: ", "
+    /// Removes `derive` along with any derives that require it, e.g. removing `Clone` also removes `Copy`.
+    pub fn remove(&mut self, derive: &Derive) {
+        self.derives.remove(derive);
+        self.helpers.remove(derive);
+
+        let dependents: Vec<Derive> = self.derives.iter()
+            .filter(|d| d.requires().contains(derive))
+            .cloned()
+            .collect();
+
+        for d in dependents.iter() {
+            self.remove(d);
         }
     }
+
+    pub fn len(&self) -> usize {
+        self.derives.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.derives.is_empty()
+    }
+
+    /// The derives in the order they are rendered.
+    pub fn iter(&self) -> impl Iterator<Item=Derive> + '_ {
+        self.derives.iter().cloned()
+    }
 }
 
 impl FromIterator<Derive> for Derives {
@@ -89,16 +133,25 @@ impl FromIterator<Derive> for Derives {
     }
 }
 
-impl Display for Derives {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        if self.0.is_empty() {
+impl Render for Derives {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        if self.derives.is_empty() {
             return Ok(());
         }
 
-        writeln!(f, "{}", self.get_str_concat())
+        r.list(0, "#[derive(", &self.derives, ")]")?;
+        writeln!(r)?;
+
+        for helper in self.helpers.values().flatten() {
+            writeln!(r, "{}#[{}]", r.indent(0), helper)?;
+        }
+
+        Ok(())
     }
 }
 
+display_via_render!(Derives);
+
 #[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub enum Derive {
     Debug,
@@ -108,7 +161,30 @@ pub enum Derive {
     Eq, PartialEq,
     Ord, PartialOrd,
     Hash,
-    Custom(TraitName),
+    Custom(DerivePath),
+}
+
+impl Derive {
+    /// A derive macro rendered by its full path, e.g. `#[derive(serde::Serialize)]`.
+    pub fn custom(path: &str) -> Self {
+        Derive::Custom(DerivePath::new(path))
+    }
+
+    /// A derive macro rendered by its name and imported with `use`, e.g. `#[derive(Serialize)]`.
+    pub fn imported(path: &str) -> Self {
+        Derive::Custom(DerivePath::new(path).imported())
+    }
+
+    /// The traits that must also be implemented for this one to be derived.
+    pub fn requires(&self) -> Vec<Derive> {
+        match self {
+            Derive::Copy => vec![Derive::Clone],
+            Derive::Eq => vec![Derive::PartialEq],
+            Derive::PartialOrd => vec![Derive::PartialEq],
+            Derive::Ord => vec![Derive::Eq, Derive::PartialOrd],
+            _ => vec![],
+        }
+    }
 }
 
 impl Display for Derive {
@@ -132,9 +208,112 @@ impl Display for Derive {
     }
 }
 
+impl FromStr for Derive {
+    type Err = CodeGenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Debug" => Ok(Derive::Debug),
+            "Default" => Ok(Derive::Default),
+            "Copy" => Ok(Derive::Copy),
+            "Clone" => Ok(Derive::Clone),
+            "Eq" => Ok(Derive::Eq),
+            "PartialEq" => Ok(Derive::PartialEq),
+            "Ord" => Ok(Derive::Ord),
+            "PartialOrd" => Ok(Derive::PartialOrd),
+            "Hash" => Ok(Derive::Hash),
+            _ => s.parse().map(Derive::Custom),
+        }
+    }
+}
+
+/// The path of a derive macro, such as `Serialize` or `num_derive::FromPrimitive`. Paths are compared, ordered
+/// and hashed by `path` alone, so a derive is the same whether it is imported or not.
+#[derive(Debug, Clone)]
+pub struct DerivePath {
+    pub path: String,
+    /// Whether the derive is rendered by name and needs a `use` of its path.
+    pub imported: bool,
+}
+
+impl DerivePath {
+    pub fn new(path: &str) -> Self {
+        path.parse().unwrap()
+    }
+
+    pub fn try_new(path: &str) -> Result<Self, CodeGenError> {
+        path.parse()
+    }
+
+    pub fn imported(mut self) -> Self {
+        self.imported = true;
+        self
+    }
+
+    /// The last segment of the path: `Serialize` for `serde::Serialize`.
+    pub fn name(&self) -> &str {
+        self.path.rsplit("::").next().unwrap_or(&self.path)
+    }
+
+    /// The text used inside `#[derive(...)]`.
+    pub fn as_str(&self) -> &str {
+        if self.imported {
+            self.name()
+        } else {
+            &self.path
+        }
+    }
+}
+
+impl PartialEq for DerivePath {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl Eq for DerivePath {}
+
+impl PartialOrd for DerivePath {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DerivePath {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.path.cmp(&other.path)
+    }
+}
+
+impl Hash for DerivePath {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.path.hash(state);
+    }
+}
+
+impl FromStr for DerivePath {
+    type Err = CodeGenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let segments: Vec<&str> = s.split("::").collect();
+
+        if segments.iter().any(|seg| seg.is_empty() || seg.contains(' ')) {
+            return Err(CodeGenError::syntax(InputKind::DerivePath, s, "segments cannot be empty or contain spaces"));
+        }
+
+        CamelCase::from_str(segments[segments.len() - 1])?;
+
+        Ok(DerivePath {
+            path: s.to_string(),
+            imported: false,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Style;
 
     #[test]
     fn display_test() {
@@ -155,8 +334,87 @@ mod tests {
         let mut hash = Derives::new();
         hash.insert(Derive::Hash);
 
-        assert!(hash.0.contains(&Derive::Hash));
-        assert!(hash.0.contains(&Derive::Eq));
-        assert!(hash.0.contains(&Derive::PartialEq));
+        assert!(hash.contains(&Derive::Hash));
+        assert!(hash.contains(&Derive::Eq));
+        assert!(hash.contains(&Derive::PartialEq));
+    }
+
+    #[test]
+    fn remove_cascades_to_dependents() {
+        let mut derives: Derives = vec![Derive::Copy, Derive::Ord, Derive::Debug].into_iter().collect();
+        derives.remove(&Derive::PartialEq);
+
+        assert_eq!("#[derive(Debug, Copy, Clone)]\n", derives.to_string());
+
+        derives.remove(&Derive::Clone);
+
+        assert_eq!("#[derive(Debug)]\n", derives.to_string());
+    }
+
+    #[test]
+    fn path_derives_follow_std_derives_in_path_order() {
+        let derives: Derives = vec![
+            Derive::custom("strum::EnumIter"),
+            Derive::Debug,
+            Derive::custom("num_derive::FromPrimitive"),
+            Derive::Clone,
+        ].into_iter().collect();
+
+        assert_eq!("#[derive(Debug, Clone, num_derive::FromPrimitive, strum::EnumIter)]\n", derives.to_string());
+    }
+
+    #[test]
+    fn imported_derive_uses_name_and_needs_import() {
+        let derives: Derives = vec![Derive::Debug, Derive::imported("serde::Serialize")].into_iter().collect();
+
+        assert_eq!("#[derive(Debug, Serialize)]\n", derives.to_string());
+        assert_eq!(vec!["serde::Serialize".to_string()], derives.imports());
+    }
+
+    #[test]
+    fn helper_attributes_follow_derive() {
+        let mut derives = Derives::with_debug();
+        derives.insert_with_helper(Derive::imported("serde::Serialize"), "serde(rename_all = \"camelCase\")");
+
+        assert_eq!("#[derive(Debug, Serialize)]\n#[serde(rename_all = \"camelCase\")]\n", derives.to_string());
+
+        derives.remove(&Derive::imported("serde::Serialize"));
+
+        assert_eq!("#[derive(Debug)]\n", derives.to_string());
+    }
+
+    #[test]
+    fn imported_and_path_derives_are_the_same_derive() {
+        let mut derives: Derives = vec![Derive::Debug, Derive::imported("serde::Serialize")].into_iter().collect();
+
+        assert!(derives.contains(&Derive::custom("serde::Serialize")));
+
+        derives.insert(Derive::custom("serde::Serialize"));
+
+        assert_eq!("#[derive(Debug, serde::Serialize)]\n", derives.to_string());
+        assert!(derives.imports().is_empty());
+
+        derives.remove(&Derive::imported("serde::Serialize"));
+
+        assert_eq!("#[derive(Debug)]\n", derives.to_string());
+    }
+
+    #[test]
+    fn derive_from_str() {
+        assert_eq!(Derive::Hash, "Hash".parse().unwrap());
+        assert_eq!(Derive::custom("serde::Deserialize"), "serde::Deserialize".parse().unwrap());
+        assert!("serde::".parse::<Derive>().is_err());
+        assert!("serde::deserialize".parse::<Derive>().is_err());
+    }
+
+    #[test]
+    fn long_derive_list_is_broken() {
+        let derives: Derives = vec![Derive::Debug, Derive::Clone, Derive::custom("serde::Serialize")].into_iter().collect();
+
+        assert_eq!("#[derive(Debug, Clone, serde::Serialize)]\n", derives.to_string());
+        assert_eq!(
+            "#[derive(\n    Debug,\n    Clone,\n    serde::Serialize,\n)]\n",
+            Style::new().with_max_width(30).render(&derives)
+        );
     }
 }
\ No newline at end of file
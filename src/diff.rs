@@ -0,0 +1,270 @@
+use std::fmt::Write;
+
+/// Lines of context around each change in a unified diff.
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// A unified diff of `old` against `new`, or an empty string if they are equal.
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let edits = edit_script(&old_lines, &new_lines);
+
+    if edits.iter().all(|e| *e == Edit::Equal) {
+        return String::new();
+    }
+
+    let mut out = String::new();
+    writeln!(out, "--- {}", old_label).ok();
+    writeln!(out, "+++ {}", new_label).ok();
+
+    for (start, end) in hunks(&edits) {
+        // line positions before the hunk
+        let old_start = edits[..start].iter().filter(|e| **e != Edit::Insert).count();
+        let new_start = edits[..start].iter().filter(|e| **e != Edit::Delete).count();
+        let old_len = edits[start..end].iter().filter(|e| **e != Edit::Insert).count();
+        let new_len = edits[start..end].iter().filter(|e| **e != Edit::Delete).count();
+
+        writeln!(out, "@@ -{} +{} @@", range(old_start, old_len), range(new_start, new_len)).ok();
+
+        let (mut o, mut n) = (old_start, new_start);
+        for edit in edits[start..end].iter() {
+            let (prefix, line) = match edit {
+                Edit::Equal => { o += 1; n += 1; (' ', old_lines[o - 1]) },
+                Edit::Delete => { o += 1; ('-', old_lines[o - 1]) },
+                Edit::Insert => { n += 1; ('+', new_lines[n - 1]) },
+            };
+
+            out.push(prefix);
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+
+    out
+}
+
+/// A hunk range as `start,len`, where `start` is 1-based, or the line before the hunk if it is empty.
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+/// Groups the changes into hunks with `CONTEXT` lines around them, as ranges of `edits`.
+fn hunks(edits: &[Edit]) -> Vec<(usize, usize)> {
+    let mut hunks: Vec<(usize, usize)> = vec![];
+
+    for (i, _) in edits.iter().enumerate().filter(|(_, e)| **e != Edit::Equal) {
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + 1 + CONTEXT).min(edits.len());
+
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    hunks
+}
+
+/// The shortest edit script turning `old` into `new`, using the linear space variant of Myers' algorithm.
+fn edit_script(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let mut edits = Vec::with_capacity(old.len() + new.len());
+    diff_range(old, new, &mut edits);
+    edits
+}
+
+/// Appends the edits turning `old` into `new`, splitting the problem at the middle snake until one side is empty.
+fn diff_range(old: &[&str], new: &[&str], edits: &mut Vec<Edit>) {
+    let prefix = old.iter().zip(new.iter()).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = old.iter().rev().zip(new.iter().rev()).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+
+    edits.extend(std::iter::repeat(Edit::Equal).take(prefix));
+
+    if old.is_empty() {
+        edits.extend(std::iter::repeat(Edit::Insert).take(new.len()));
+    } else if new.is_empty() {
+        edits.extend(std::iter::repeat(Edit::Delete).take(old.len()));
+    } else {
+        // both sides start and end with a difference, so the script has at least two edits and both halves
+        // are smaller
+        let (x, y, u, v) = middle_snake(old, new);
+        diff_range(&old[..x], &new[..y], edits);
+        edits.extend(std::iter::repeat(Edit::Equal).take(u - x));
+        diff_range(&old[u..], &new[v..], edits);
+    }
+
+    edits.extend(std::iter::repeat(Edit::Equal).take(suffix));
+}
+
+/// The snake where a forward search from the start and a backward search from the end first overlap, as
+/// `(x, y, u, v)`: it runs from `old[x]`, `new[y]` to `old[u]`, `new[v]` and lies on a shortest edit script.
+/// Only the furthest reaching `x` of each diagonal is kept, so memory is linear in the input.
+fn middle_snake(old: &[&str], new: &[&str]) -> (usize, usize, usize, usize) {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2;
+    let offset = max + 1;
+    let at = |k: isize| (k + offset) as usize;
+
+    // the backward search runs over the reversed sequences, with `x` counted from the end of `old`
+    let mut forward = vec![0isize; 2 * max as usize + 3];
+    let mut backward = vec![0isize; 2 * max as usize + 3];
+
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                forward[at(k + 1)]
+            } else {
+                forward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let (start_x, start_y) = (x, y);
+
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+
+            forward[at(k)] = x;
+
+            if odd && (delta - k).abs() < d && x + backward[at(delta - k)] >= n {
+                return (start_x as usize, start_y as usize, x as usize, y as usize);
+            }
+        }
+
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && backward[at(k - 1)] < backward[at(k + 1)]) {
+                backward[at(k + 1)]
+            } else {
+                backward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let (start_x, start_y) = (x, y);
+
+            while x < n && y < m && old[(n - x - 1) as usize] == new[(m - y - 1) as usize] {
+                x += 1;
+                y += 1;
+            }
+
+            backward[at(k)] = x;
+
+            if !odd && (delta - k).abs() <= d && x + forward[at(delta - k)] >= n {
+                return ((n - x) as usize, (m - y) as usize, (n - start_x) as usize, (m - start_y) as usize);
+            }
+        }
+    }
+
+    unreachable!("the searches meet within (n + m + 1) / 2 steps")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal() {
+        assert_eq!("", unified_diff("a\nb\n", "a\nb\n", "a", "b"));
+    }
+
+    #[test]
+    fn changed_line() {
+        assert_eq!(
+            "--- a/lib.rs\n+++ b/lib.rs\n@@ -1,3 +1,3 @@\n a\n-b\n+c\n d\n",
+            unified_diff("a\nb\nd\n", "a\nc\nd\n", "a/lib.rs", "b/lib.rs")
+        );
+    }
+
+    #[test]
+    fn separate_hunks() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let new = "0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n";
+
+        assert_eq!(
+            "--- old\n+++ new\n@@ -1,3 +1,4 @@\n+0\n 1\n 2\n 3\n@@ -9,4 +10,3 @@\n 9\n 10\n 11\n-12\n",
+            unified_diff(old, new, "old", "new")
+        );
+    }
+
+    #[test]
+    fn added_file() {
+        assert_eq!("--- /dev/null\n+++ b/a.rs\n@@ -0,0 +1,2 @@\n+a\n+b\n", unified_diff("", "a\nb\n", "/dev/null", "b/a.rs"));
+    }
+
+    #[test]
+    fn missing_newline() {
+        assert_eq!(
+            "--- a\n+++ b\n@@ -1 +1 @@\n-a\n\\ No newline at end of file\n+a\n",
+            unified_diff("a", "a\n", "a", "b")
+        );
+    }
+
+    /// The length of the longest common subsequence, by dynamic programming.
+    fn lcs(old: &[&str], new: &[&str]) -> usize {
+        let mut table = vec![vec![0; new.len() + 1]; old.len() + 1];
+
+        for i in 1..=old.len() {
+            for j in 1..=new.len() {
+                table[i][j] = match old[i - 1] == new[j - 1] {
+                    true => table[i - 1][j - 1] + 1,
+                    false => table[i - 1][j].max(table[i][j - 1]),
+                };
+            }
+        }
+
+        table[old.len()][new.len()]
+    }
+
+    /// Every sequence of up to `len` lines drawn from `a` and `b`.
+    fn sequences(len: usize) -> Vec<Vec<&'static str>> {
+        let mut all = vec![vec![]];
+        let mut last = vec![vec![]];
+
+        for _ in 0..len {
+            last = last.iter()
+                .flat_map(|s: &Vec<&str>| ["a", "b"].iter().map(move |l| [s.as_slice(), &[*l]].concat()))
+                .collect();
+            all.extend(last.iter().cloned());
+        }
+
+        all
+    }
+
+    #[test]
+    fn edit_scripts_are_shortest() {
+        let sequences = sequences(6);
+
+        for old in sequences.iter() {
+            for new in sequences.iter() {
+                let edits = edit_script(old, new);
+
+                let (mut o, mut n) = (0, 0);
+                for edit in edits.iter() {
+                    match edit {
+                        Edit::Equal => { assert_eq!(old[o], new[n]); o += 1; n += 1; },
+                        Edit::Delete => o += 1,
+                        Edit::Insert => n += 1,
+                    }
+                }
+                assert_eq!((old.len(), new.len()), (o, n));
+
+                let changes = edits.iter().filter(|e| **e != Edit::Equal).count();
+                assert_eq!(old.len() + new.len() - 2 * lcs(old, new), changes, "{:?} -> {:?}", old, new);
+            }
+        }
+    }
+}
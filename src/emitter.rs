@@ -0,0 +1,474 @@
+use crate::{Mod, Item, SourceFile, Header, Render, Style};
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Where the files of nested modules are placed.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ModLayout {
+    /// `foo.rs` with its submodules in `foo/`
+    #[default]
+    ModName,
+    /// `foo/mod.rs` with its submodules next to it
+    ModRs,
+}
+
+/// A rendered source file, with its path relative to the emitter's directory.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RenderedFile {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+impl RenderedFile {
+    pub fn new<P: AsRef<Path>, D: Display>(path: P, content: D) -> Self {
+        RenderedFile {
+            path: path.as_ref().to_path_buf(),
+            content: content.to_string(),
+        }
+    }
+}
+
+/// What `Emitter::emit` did to the files on disk. All paths are relative to the emitter's directory.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct EmitReport {
+    pub written: Vec<PathBuf>,
+    pub unchanged: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+}
+
+/// Writes a `Mod` tree to a directory, one file per module.
+///
+/// The root module is written to `root_file` (`lib.rs` by default) and nested modules to their own files, with
+/// `mod` declarations in their parents. Files whose content did not change are not touched, so cargo does not
+/// rebuild needlessly. The generated paths are recorded in a manifest, and files generated by an earlier run
+/// that are no longer produced are removed.
+///
+/// Existing files that were not generated are treated as hand-written: they are not overwritten or removed unless
+/// `force` is set. With a `SourceFile` set, every file gets its header and inner attributes, and a file counts as
+/// generated if it starts with the header. Without one, it counts as generated if the manifest lists it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Emitter {
+    pub dir: PathBuf,
+    pub layout: ModLayout,
+    pub root_file: String,
+    pub manifest: String,
+    pub source_file: Option<SourceFile>,
+    pub style: Style,
+    pub force: bool,
+}
+
+impl Emitter {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Emitter {
+            dir: dir.as_ref().to_path_buf(),
+            layout: Default::default(),
+            root_file: "lib.rs".to_string(),
+            manifest: ".code_gen_manifest".to_string(),
+            source_file: None,
+            style: Style::DEFAULT,
+            force: false,
+        }
+    }
+
+    pub fn with_layout(mut self, layout: ModLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_root_file(mut self, root_file: &str) -> Self {
+        self.root_file = root_file.to_string();
+        self
+    }
+
+    pub fn with_manifest(mut self, manifest: &str) -> Self {
+        self.manifest = manifest.to_string();
+        self
+    }
+
+    /// Wraps every file in the header and attributes of `source_file`. Its items are not used.
+    pub fn with_source_file(mut self, source_file: SourceFile) -> Self {
+        self.source_file = Some(source_file);
+        self
+    }
+
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Overwrite and remove files even if they lack the generated header.
+    pub fn force(mut self) -> Self {
+        self.force = true;
+        self
+    }
+
+    /// Renders the files for `root` without writing anything. The name of the root module is not used.
+    pub fn render(&self, root: &Mod) -> Vec<RenderedFile> {
+        let root_file = PathBuf::from(&self.root_file);
+
+        // lib.rs, main.rs and mod.rs own their directory, any other file has its submodules in a directory
+        // named after it
+        let children_dir = match root_file.file_stem().and_then(|s| s.to_str()) {
+            Some("lib") | Some("main") | Some("mod") | None => root_file.parent().map(Path::to_path_buf).unwrap_or_default(),
+            Some(stem) => root_file.with_file_name(stem),
+        };
+
+        let mut files = vec![];
+        self.render_mod(root, root_file, &children_dir, &mut files);
+        files
+    }
+
+    fn render_mod(&self, module: &Mod, path: PathBuf, children_dir: &Path, files: &mut Vec<RenderedFile>) {
+        let content = match &self.source_file {
+            Some(source_file) => source_file.wrap(module.file_contents().styled(&self.style)),
+            None => self.style.render(&module.file_contents()),
+        };
+        files.push(RenderedFile { path, content });
+
+        for item in module.items.iter() {
+            if let Item::Mod(m) = item {
+                // `mod r#type;` is read from `type.rs`
+                let dir = children_dir.join(m.name.unescaped());
+                let path = match self.layout {
+                    ModLayout::ModName => children_dir.join(format!("{}.rs", m.name.unescaped())),
+                    ModLayout::ModRs => dir.join("mod.rs"),
+                };
+                self.render_mod(m, path, &dir, files);
+            }
+        }
+    }
+
+    /// Writes the files for `root`, leaving unchanged files alone and removing stale files from a previous run.
+    /// If any file would overwrite or remove a hand-written one, nothing is changed.
+    pub fn emit(&self, root: &Mod) -> io::Result<EmitReport> {
+        self.emit_files(&self.render(root))
+    }
+
+    /// Like `emit`, for files rendered from arbitrary items.
+    pub fn emit_files(&self, files: &[RenderedFile]) -> io::Result<EmitReport> {
+        let mut report = EmitReport::default();
+        let manifest = self.read_manifest()?;
+        let stale_files = self.stale_files(files)?;
+
+        // check everything first, so a hand-written file leaves the directory untouched
+        for file in files.iter() {
+            self.check_overwrite(&file.path, &file.content, &manifest)?;
+        }
+        for stale in stale_files.iter() {
+            self.check_overwrite(stale, "", &manifest)?;
+        }
+
+        for file in files.iter() {
+            if write_if_changed(&self.dir.join(&file.path), &file.content)? {
+                report.written.push(file.path.clone());
+            } else {
+                report.unchanged.push(file.path.clone());
+            }
+        }
+
+        for stale in stale_files {
+            let path = self.dir.join(&stale);
+            fs::remove_file(&path)?;
+            remove_empty_parents(&self.dir, &path)?;
+            report.removed.push(stale);
+        }
+
+        let generated: BTreeSet<&PathBuf> = files.iter().map(|f| &f.path).collect();
+        let manifest: String = generated.iter()
+            .map(|p| format!("{}\n", manifest_path(p)))
+            .collect();
+        write_if_changed(&self.dir.join(&self.manifest), &manifest)?;
+
+        Ok(report)
+    }
+
+    /// Fails if the file at `path` would be changed but was not generated, so is probably hand-written: it lacks
+    /// the generated header or, without a `SourceFile`, is not in the `manifest` of the previous run.
+    fn check_overwrite(&self, path: &Path, content: &str, manifest: &[PathBuf]) -> io::Result<()> {
+        if self.force {
+            return Ok(());
+        }
+
+        let refuse = |reason: &str| Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("refusing to overwrite {}: {}", path.display(), reason)
+        ));
+
+        match fs::read_to_string(self.dir.join(path)) {
+            Ok(existing) if existing == content => Ok(()),
+            Ok(existing) if self.source_file.is_some() && !Header::is_generated(&existing) => refuse("it has no generated header"),
+            Ok(_) if self.source_file.is_none() && !manifest.iter().any(|p| p == path) => refuse("it is not in the manifest"),
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The files from the manifest that still exist but are not in `files`.
+    pub(crate) fn stale_files(&self, files: &[RenderedFile]) -> io::Result<Vec<PathBuf>> {
+        let generated: BTreeSet<&PathBuf> = files.iter().map(|f| &f.path).collect();
+
+        Ok(self.read_manifest()?
+            .into_iter()
+            .filter(|p| !generated.contains(p) && self.dir.join(p).is_file())
+            .collect())
+    }
+
+    /// The paths recorded by the previous `emit`, or none if there is no manifest. Fails if an entry is absolute
+    /// or leads out of the directory with `..`, as stale entries are removed.
+    pub fn read_manifest(&self) -> io::Result<Vec<PathBuf>> {
+        let manifest = match fs::read_to_string(self.dir.join(&self.manifest)) {
+            Ok(manifest) => manifest,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+
+        manifest.lines()
+            .filter(|l| !l.is_empty())
+            .map(|line| {
+                let path = PathBuf::from(line);
+
+                match path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+                    true => Ok(path),
+                    false => Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("manifest entry {} is outside {}", line, self.dir.display())
+                    )),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Paths in the manifest always use `/`, so it is the same on every platform.
+pub(crate) fn manifest_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Writes `content` to `path` unless it already contains exactly that. Returns whether the file was written.
+pub(crate) fn write_if_changed(path: &Path, content: &str) -> io::Result<bool> {
+    match fs::read(path) {
+        Ok(existing) if existing == content.as_bytes() => return Ok(false),
+        Ok(_) => {},
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+        Err(e) => return Err(e),
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(path, content)?;
+    Ok(true)
+}
+
+/// Removes the directories between `path` and `root` that are left empty.
+fn remove_empty_parents(root: &Path, path: &Path) -> io::Result<()> {
+    let mut dir = path.parent();
+
+    while let Some(d) = dir {
+        if d == root || !d.starts_with(root) || fs::read_dir(d)?.next().is_some() {
+            break;
+        }
+        fs::remove_dir(d)?;
+        dir = d.parent();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Struct;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("code_gen_emitter_{}_{}", std::process::id(), name));
+        fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    fn tree() -> Mod {
+        Mod::new("root")
+            .add_item(Mod::new("a")
+                .add_item(Mod::new("b").add_item(Struct::new("B"))))
+            .add_item(Struct::new("Root"))
+    }
+
+    fn paths(files: &[RenderedFile]) -> Vec<String> {
+        files.iter().map(|f| manifest_path(&f.path)).collect()
+    }
+
+    #[test]
+    fn mod_name_layout() {
+        let files = Emitter::new("out").render(&tree());
+
+        assert_eq!(vec!["lib.rs", "a.rs", "a/b.rs"], paths(&files));
+        assert_eq!("pub mod a;\n\npub struct Root;\n", files[0].content);
+        assert_eq!("pub mod b;\n", files[1].content);
+        assert_eq!("pub struct B;\n", files[2].content);
+    }
+
+    #[test]
+    fn mod_rs_layout() {
+        let files = Emitter::new("out").with_layout(ModLayout::ModRs).render(&tree());
+
+        assert_eq!(vec!["lib.rs", "a/mod.rs", "a/b/mod.rs"], paths(&files));
+    }
+
+    #[test]
+    fn named_root_file() {
+        let files = Emitter::new("out").with_root_file("generated.rs").render(&tree());
+
+        assert_eq!(vec!["generated.rs", "generated/a.rs", "generated/a/b.rs"], paths(&files));
+    }
+
+    #[test]
+    fn unchanged_files_are_not_rewritten() {
+        let dir = temp_dir("unchanged");
+        let emitter = Emitter::new(&dir);
+
+        let first = emitter.emit(&tree()).unwrap();
+        assert_eq!(3, first.written.len());
+
+        let mut changed = tree();
+        changed.push(Struct::new("Other"));
+
+        let second = emitter.emit(&changed).unwrap();
+        assert_eq!(vec![PathBuf::from("lib.rs")], second.written);
+        assert_eq!(2, second.unchanged.len());
+        assert_eq!("pub mod a;\n\npub struct Root;\n\npub struct Other;\n", fs::read_to_string(dir.join("lib.rs")).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn header_added_to_every_file() {
+        let files = Emitter::new("out")
+            .with_source_file(SourceFile::new()
+                .with_header(Header::new().without_version())
+                .add_attribute("allow(clippy::all)"))
+            .render(&tree());
+
+        assert_eq!("// @generated by code_gen — do not edit\n\n#![allow(clippy::all)]\n\npub mod a;\n\npub struct Root;\n", files[0].content);
+        assert_eq!("// @generated by code_gen — do not edit\n\n#![allow(clippy::all)]\n\npub struct B;\n", files[2].content);
+    }
+
+    #[test]
+    fn hand_written_files_are_not_overwritten() {
+        let dir = temp_dir("hand_written");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.rs"), "// hand-written\n").unwrap();
+
+        let emitter = Emitter::new(&dir).with_source_file(SourceFile::new());
+
+        assert_eq!(io::ErrorKind::AlreadyExists, emitter.emit(&tree()).unwrap_err().kind());
+        assert_eq!("// hand-written\n", fs::read_to_string(dir.join("a.rs")).unwrap());
+
+        emitter.clone().force().emit(&tree()).unwrap();
+        assert!(Header::is_generated(&fs::read_to_string(dir.join("a.rs")).unwrap()));
+
+        // files with the header are regenerated without forcing
+        emitter.emit(&tree().add_item(Struct::new("Other"))).unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn files_missing_from_the_manifest_are_not_overwritten_without_a_source_file() {
+        let dir = temp_dir("hand_written_no_header");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.rs"), "// hand-written\n").unwrap();
+
+        let emitter = Emitter::new(&dir);
+
+        assert_eq!(io::ErrorKind::AlreadyExists, emitter.emit(&tree()).unwrap_err().kind());
+        assert_eq!("// hand-written\n", fs::read_to_string(dir.join("a.rs")).unwrap());
+
+        emitter.clone().force().emit(&tree()).unwrap();
+
+        // files listed in the manifest are regenerated without forcing
+        emitter.emit(&tree().add_item(Struct::new("Other"))).unwrap();
+        assert!(fs::read_to_string(dir.join("lib.rs")).unwrap().contains("Other"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn nothing_is_written_if_any_file_is_hand_written() {
+        let dir = temp_dir("hand_written_second");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.rs"), "// hand-written\n").unwrap();
+
+        let emitter = Emitter::new(&dir).with_source_file(SourceFile::new());
+
+        assert_eq!(io::ErrorKind::AlreadyExists, emitter.emit(&tree()).unwrap_err().kind());
+        assert!(!dir.join("lib.rs").exists());
+        assert!(!dir.join("a").exists());
+        assert!(!dir.join(".code_gen_manifest").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stale_files_are_checked_before_writing() {
+        let dir = temp_dir("hand_written_stale");
+        let emitter = Emitter::new(&dir).with_source_file(SourceFile::new().with_header(Header::new().without_version()));
+
+        emitter.emit(&tree()).unwrap();
+        fs::write(dir.join("a/b.rs"), "// hand-written\n").unwrap();
+        let lib = fs::read_to_string(dir.join("lib.rs")).unwrap();
+
+        let root = Mod::new("root").add_item(Struct::new("Root")).add_item(Struct::new("Other"));
+        assert_eq!(io::ErrorKind::AlreadyExists, emitter.emit(&root).unwrap_err().kind());
+        assert_eq!(lib, fs::read_to_string(dir.join("lib.rs")).unwrap());
+        assert!(dir.join("a.rs").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn manifest_entries_outside_the_directory_are_rejected() {
+        let root = temp_dir("escape");
+        let dir = root.join("out");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(root.join("victim.rs"), "// victim\n").unwrap();
+        fs::write(dir.join(".code_gen_manifest"), "lib.rs\n../victim.rs\n").unwrap();
+
+        let emitter = Emitter::new(&dir);
+
+        assert_eq!(io::ErrorKind::InvalidData, emitter.emit(&tree()).unwrap_err().kind());
+        assert!(root.join("victim.rs").exists());
+        assert!(!dir.join("lib.rs").exists());
+
+        fs::write(dir.join(".code_gen_manifest"), format!("{}\n", root.join("victim.rs").display())).unwrap();
+        assert!(emitter.emit(&tree()).is_err());
+        assert!(root.join("victim.rs").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn stale_files_are_removed() {
+        let dir = temp_dir("stale");
+        let emitter = Emitter::new(&dir);
+
+        emitter.emit(&tree()).unwrap();
+        fs::write(dir.join("handwritten.rs"), "").unwrap();
+
+        let report = emitter.emit(&Mod::new("root").add_item(Struct::new("Root"))).unwrap();
+
+        assert_eq!(vec![PathBuf::from("a/b.rs"), PathBuf::from("a.rs")], report.removed);
+        assert!(!dir.join("a").exists());
+        assert!(dir.join("handwritten.rs").exists());
+        assert_eq!(vec![PathBuf::from("lib.rs")], emitter.read_manifest().unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
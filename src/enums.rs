@@ -1,5 +1,6 @@
 use crate::*;
-use std::fmt::{Display, Result};
+use crate::render::display_via_render;
+use std::fmt::Result;
 
 #[derive(Debug, Clone)]
 pub struct EnumType {
@@ -8,16 +9,19 @@ pub struct EnumType {
     pub enum_traits: Vec<TraitImpl>,
 }
 
-impl Display for EnumType {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        writeln!(f, "{}", self.base).ok();
+impl Render for EnumType {
+    fn render(&self, r: &mut Renderer<'_>) -> Result {
+        r.render(&self.base)?;
+        writeln!(r)?;
 
         if let Some(i) = &self.enum_impl {
-            writeln!(f, "{}", i).ok();
+            r.render(i)?;
+            writeln!(r)?;
         }
 
         for i in &self.enum_traits {
-            writeln!(f, "{}", i).ok();
+            r.render(i)?;
+            writeln!(r)?;
         }
 
         Ok(())
@@ -34,12 +38,16 @@ pub struct Enum {
 
 impl Enum {
     pub fn new(name: &str) -> Self {
-        Enum {
-            typ: Type::new(name),
+        Self::try_new(name).unwrap()
+    }
+
+    pub fn try_new(name: &str) -> std::result::Result<Self, CodeGenError> {
+        Ok(Enum {
+            typ: name.parse()?,
             visibility: Visibility::Pub,
             derives: Default::default(),
             options: vec![],
-        }
+        })
     }
 
     pub fn with_derives(mut self, derives: Derives) -> Self {
@@ -58,17 +66,22 @@ impl Enum {
     }
 }
 
-impl Display for Enum {
-    fn fmt(&self, f: &mut Formatter) -> Result {
+impl Render for Enum {
+    fn render(&self, r: &mut Renderer<'_>) -> Result {
+        r.render(&self.derives)?;
+        let broken = r.braced_header(0, format_args!("{}enum {}", self.visibility, self.typ.name), &self.typ.types, self.options.is_empty())?;
+
         if self.options.is_empty() {
-            writeln!(f, "{}{}enum {} {{}}", self.derives, self.visibility, self.typ)
-        } else {
-            writeln!(f, "{}{}enum {} {{", self.derives, self.visibility, self.typ).ok();
-            for opt in self.options.iter() {
-                write!(f, "    {}", opt).ok();
-            }
-            writeln!(f, "}}")
+            r.empty_body(0, broken)?;
+            return writeln!(r);
+        }
+
+        writeln!(r)?;
+        for (i, opt) in self.options.iter().enumerate() {
+            opt.render_in_list(r, i == self.options.len() - 1)?;
+            writeln!(r)?;
         }
+        writeln!(r, "{}}}", r.indent(0))
     }
 }
 
@@ -76,26 +89,66 @@ impl Display for Enum {
 pub struct EnumOption {
     pub name: CamelCase,
     pub option_types: Vec<String>,
+    /// Marks the variant with `#[default]` for `#[derive(Default)]`.
+    pub default: bool,
+    /// Attributes without the surrounding `#[...]`, such as derive helpers: `serde(rename = "x")`.
+    pub attributes: Vec<String>,
 }
 
 impl EnumOption {
     pub fn new(name: &str, option_types: Vec<&str>) -> Self {
+        Self::try_new(name, option_types).unwrap_or_else(|e| panic!("EnumOption: {}", e))
+    }
+
+    pub fn try_new(name: &str, option_types: Vec<&str>) -> std::result::Result<Self, CodeGenError> {
         let option_types = option_types
             .into_iter()
             .map(String::from)
             .collect();
 
-        EnumOption {
-            name: name.parse().expect(&format!("EnumOption: name must be CamelCase: {}", name)),
-            option_types
-        }
+        Ok(EnumOption {
+            name: name.parse()?,
+            option_types,
+            default: false,
+            attributes: vec![],
+        })
+    }
+
+    pub fn as_default(mut self) -> Self {
+        self.default = true;
+        self
+    }
+
+    pub fn add_attribute(mut self, attribute: &str) -> Self {
+        self.attributes.push(attribute.to_string());
+        self
     }
 }
 
-impl Display for EnumOption {
-    fn fmt(&self, f: &mut Formatter) -> Result {
+impl EnumOption {
+    /// Renders the variant with its attributes as an entry of an enum body, without the final newline.
+    pub(crate) fn render_in_list(&self, r: &mut Renderer<'_>, is_last: bool) -> Result {
+        let indent = r.indent(1);
+
+        if self.default {
+            writeln!(r, "{}#[default]", indent)?;
+        }
+        for attribute in self.attributes.iter() {
+            writeln!(r, "{}#[{}]", indent, attribute)?;
+        }
+
+        match self.option_types.is_empty() {
+            true => write!(r, "{}{}{}", indent, self.name, r.comma(is_last)),
+            false => {
+                let comma = r.comma(is_last);
+                r.tuple_list(1, format_args!("{}(", self.name), &self.option_types, format_args!("){}", comma)).map(|_| ())
+            },
+        }
+    }
+
+    fn render_variant(&self, r: &mut Renderer<'_>) -> Result {
         if self.option_types.is_empty() {
-            writeln!(f, "{},", self.name)
+            write!(r, "{}", self.name)
         } else {
             let str = StrConcat {
                 iter: self.option_types.iter(),
@@ -105,11 +158,20 @@ impl Display for EnumOption {
                 item_append: "",
                 join: ", "
             };
-            writeln!(f, "{}({}),", self.name, str)
+            write!(r, "{}({})", self.name, str)
         }
     }
 }
 
+impl Render for EnumOption {
+    fn render(&self, r: &mut Renderer<'_>) -> Result {
+        self.render_variant(r)?;
+        writeln!(r, ",")
+    }
+}
+
+display_via_render!(EnumType, Enum, EnumOption);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -177,16 +239,38 @@ r#"pub enum Option {
     }
 
     #[test]
-    fn enum_type() {
-        let default = Trait::new("Default")
-            .add_function_definition(TraitFunction::new("default")
-                .with_return("Self"));
+    fn option_marked_default() {
+        let option = Enum::new("Option")
+            .with_derives(Derives::with_debug_default())
+            .add_option(EnumOption::new("Some", vec!["u32"]))
+            .add_option(EnumOption::new("None", vec![]).as_default());
+
+        let expected =
+r#"#[derive(Debug, Default)]
+pub enum Option {
+    Some(u32),
+    #[default]
+    None,
+}
+"#;
+        assert_eq!(expected, option.to_string());
+    }
 
+    #[test]
+    fn option_with_attribute() {
+        let e = Enum::new("Kind")
+            .add_option(EnumOption::new("Big", vec![]).add_attribute("serde(rename = \"big\")"));
+
+        assert_eq!("pub enum Kind {\n    #[serde(rename = \"big\")]\n    Big,\n}\n", e.to_string());
+    }
+
+    #[test]
+    fn enum_type() {
         let base = Enum::new("Test")
             .with_derives(Derives::with_debug_default_clone())
             .add_option(EnumOption::new("Number", vec!["u32"]));
 
-        let default_impl = default.impl_for(&base.typ)
+        let default_impl = StdTrait::Default.impl_for(&base.typ)
             .add_function(TraitFunction::new("default")
                 .with_return("Self")
                 .add_line(CodeLine::new(0, "Test::Number(0)")));
@@ -213,4 +297,28 @@ impl Default for Test {
 
         assert_eq!(expected, enum_type.to_string());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn wide_tuple_variant_is_broken_like_rustfmt() {
+        let e = Enum::new("Shape")
+            .add_option(EnumOption::new("Triangle", vec!["Point<Coordinates3D>", "Point<Coordinates3D>", "Point<Coordinates3D>"]))
+            .add_option(EnumOption::new("Label", vec!["AVeryLongTypeNameThatFillsMostOfTheLineOnItsOwnAndThenSome"]));
+
+        assert_eq!(
+            "pub enum Shape {\n    Triangle(\n        Point<Coordinates3D>,\n        Point<Coordinates3D>,\n        Point<Coordinates3D>,\n    ),\n    Label(AVeryLongTypeNameThatFillsMostOfTheLineOnItsOwnAndThenSome),\n}\n",
+            e.to_string()
+        );
+    }
+
+    #[test]
+    fn long_tuple_variant_is_broken() {
+        let e = Enum::new("Shape")
+            .add_option(EnumOption::new("Triangle", vec!["Point", "Point", "Point"]))
+            .add_option(EnumOption::new("Dot", vec!["Point"]));
+
+        assert_eq!(
+            "pub enum Shape {\n    Triangle(\n        Point,\n        Point,\n        Point,\n    ),\n    Dot(Point),\n}\n",
+            Style::new().with_max_width(30).render(&e)
+        );
+    }
+}
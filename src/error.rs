@@ -0,0 +1,150 @@
+use std::fmt::{Display, Formatter, Error};
+
+/// What an input was being parsed as.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum InputKind {
+    CamelCase,
+    SnakeCase,
+    ScreamingSnakeCase,
+    TypeName,
+    Type,
+    Generics,
+    Generic,
+    TypeParam,
+    Bounds,
+    Lifetime,
+    DerivePath,
+    UseTree,
+    BinaryOp,
+    CrossOp,
+    /// A name that only has to be an identifier, without a case convention, such as a C parameter.
+    Identifier,
+}
+
+impl Display for InputKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", match self {
+            InputKind::CamelCase => "CamelCase",
+            InputKind::SnakeCase => "snake_case",
+            InputKind::ScreamingSnakeCase => "SCREAMING_SNAKE_CASE",
+            InputKind::TypeName => "TypeName",
+            InputKind::Type => "Type",
+            InputKind::Generics => "Generics",
+            InputKind::Generic => "Generic",
+            InputKind::TypeParam => "TypeParam",
+            InputKind::Bounds => "Bounds",
+            InputKind::Lifetime => "Lifetime",
+            InputKind::DerivePath => "DerivePath",
+            InputKind::UseTree => "UseTree",
+            InputKind::BinaryOp => "BinaryOp",
+            InputKind::CrossOp => "CrossOp",
+            InputKind::Identifier => "identifier",
+        })
+    }
+}
+
+/// Why an input cannot be turned into code, as returned by the `try_new` constructors and `FromStr`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum CodeGenError {
+    /// An input that must not be empty was.
+    Empty { kind: InputKind },
+    /// A name that breaks the naming convention of its kind, such as `snake_case` with upper case letters.
+    InvalidName { kind: InputKind, input: String, reason: &'static str },
+    /// Input that is not valid syntax for its kind, such as generics without `<>`.
+    InvalidSyntax { kind: InputKind, input: String, reason: &'static str },
+    /// A `Block` that does not close every block it opens, with a description of each problem.
+    UnbalancedBlock(Vec<String>),
+    /// A `TraitImpl` that does not match its trait, such as one missing a required function.
+    InvalidImpl(String),
+}
+
+impl CodeGenError {
+    pub(crate) fn name(kind: InputKind, input: &str, reason: &'static str) -> Self {
+        CodeGenError::InvalidName { kind, input: input.to_string(), reason }
+    }
+
+    pub(crate) fn syntax(kind: InputKind, input: &str, reason: &'static str) -> Self {
+        CodeGenError::InvalidSyntax { kind, input: input.to_string(), reason }
+    }
+
+    /// What the input was being parsed as, if the error came from parsing.
+    pub fn kind(&self) -> Option<InputKind> {
+        match self {
+            CodeGenError::Empty { kind }
+            | CodeGenError::InvalidName { kind, .. }
+            | CodeGenError::InvalidSyntax { kind, .. } => Some(*kind),
+            CodeGenError::UnbalancedBlock(_) | CodeGenError::InvalidImpl(_) => None,
+        }
+    }
+
+    /// The offending input, if there was one.
+    pub fn input(&self) -> Option<&str> {
+        match self {
+            CodeGenError::InvalidName { input, .. } | CodeGenError::InvalidSyntax { input, .. } => Some(input),
+            CodeGenError::Empty { .. } | CodeGenError::UnbalancedBlock(_) | CodeGenError::InvalidImpl(_) => None,
+        }
+    }
+}
+
+impl Display for CodeGenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            CodeGenError::Empty { kind } => write!(f, "{} cannot be empty", kind),
+            CodeGenError::InvalidName { kind, input, reason }
+            | CodeGenError::InvalidSyntax { kind, input, reason } => write!(f, "{} {}: {}", kind, reason, input),
+            CodeGenError::UnbalancedBlock(problems) => write!(f, "Unbalanced Block: {}", problems.join("; ")),
+            CodeGenError::InvalidImpl(problem) => write!(f, "{}", problem),
+        }
+    }
+}
+
+impl std::error::Error for CodeGenError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn try_new_reports_the_input_and_kind() {
+        let e = Field::try_new("Speed", "f32").unwrap_err();
+
+        assert_eq!(Some(InputKind::SnakeCase), e.kind());
+        assert_eq!(Some("Speed"), e.input());
+        assert_eq!("snake_case cannot contain upper case: Speed", e.to_string());
+    }
+
+    #[test]
+    fn try_new_fails_instead_of_panicking() {
+        assert_eq!(Err(CodeGenError::Empty { kind: InputKind::TypeName }), Struct::try_new("").map(|_| ()));
+        assert_eq!(Some(InputKind::CamelCase), Trait::try_new("display").unwrap_err().kind());
+        assert_eq!(Some(InputKind::Generics), Enum::try_new("Pair<A, B").unwrap_err().kind());
+        assert_eq!(Some(InputKind::SnakeCase), Function::try_new("Run").unwrap_err().kind());
+        assert_eq!(Some(InputKind::TypeName), Type::try_new("Bad Type").unwrap_err().kind());
+        assert!(Struct::try_new("Point<T>").is_ok());
+    }
+
+    #[test]
+    fn try_builders_fail_instead_of_panicking() {
+        assert_eq!(Some(InputKind::Identifier), ForeignFn::new("puts").try_add_parameter("text len", "*const u8").unwrap_err().kind());
+        assert_eq!(Some(InputKind::TypeName), ForeignFn::new("puts").try_with_return("c int").unwrap_err().kind());
+        assert_eq!(Some(InputKind::Generic), Trait::new("Shape").try_with_supertraits("Debug +").unwrap_err().kind());
+        assert_eq!(Some(InputKind::TypeName), Stmt::try_let_typed("x", "bad type", Expr::raw("1")).unwrap_err().kind());
+        assert_eq!(Some(InputKind::SnakeCase), StructLiteral::new("Point").try_add_field("X", "1").unwrap_err().kind());
+        assert!(Trait::new("Shape").try_with_supertraits("Debug + Clone").is_ok());
+    }
+
+    #[test]
+    fn invalid_impls() {
+        let e = StdTrait::Drop.impl_for(&Type::new("Test")).validate().unwrap_err();
+
+        assert_eq!(CodeGenError::InvalidImpl("impl of Drop for Test is missing function drop".to_string()), e);
+        assert_eq!(None, e.kind());
+    }
+
+    #[test]
+    fn is_a_std_error() {
+        let e: Box<dyn std::error::Error> = Box::new(CamelCase::try_new("lower").unwrap_err());
+
+        assert_eq!("CamelCase cannot start with lower case: lower", e.to_string());
+    }
+}
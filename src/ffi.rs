@@ -0,0 +1,364 @@
+use crate::*;
+use crate::render::display_via_render;
+use crate::impls::{render_signature, Parameters, Returns};
+
+/// A `union`. Its fields must be `Copy` or wrapped in `ManuallyDrop`, see `TypeCapabilities::check_union`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Union {
+    pub typ: Type,
+    pub visibility: Visibility,
+    pub derives: Derives,
+    /// The argument of `#[repr(...)]`, such as `C`.
+    pub repr: Option<String>,
+    pub fields: Vec<Field>,
+}
+
+impl Union {
+    pub fn new(ty: &str) -> Self {
+        Self::try_new(ty).unwrap()
+    }
+
+    pub fn try_new(ty: &str) -> Result<Self, CodeGenError> {
+        Ok(Union {
+            typ: ty.parse()?,
+            visibility: Visibility::Pub,
+            derives: Default::default(),
+            repr: None,
+            fields: vec![],
+        })
+    }
+
+    pub fn with_derives(mut self, derives: Derives) -> Self {
+        self.derives = derives;
+        self
+    }
+
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
+
+    pub fn with_repr(mut self, repr: &str) -> Self {
+        self.repr = Some(repr.to_string());
+        self
+    }
+
+    pub fn add_field(mut self, field: Field) -> Self {
+        self.fields.push(field);
+        self
+    }
+}
+
+impl Render for Union {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        r.render(&self.derives)?;
+
+        if let Some(repr) = &self.repr {
+            writeln!(r, "{}#[repr({})]", r.indent(0), repr)?;
+        }
+
+        r.braced_header(0, format_args!("{}union {}", self.visibility, self.typ.name), &self.typ.types, self.fields.is_empty())?;
+        writeln!(r)?;
+
+        for (i, field) in self.fields.iter().enumerate() {
+            field.render_in_list(r, i == self.fields.len() - 1)?;
+            writeln!(r)?;
+        }
+
+        writeln!(r, "{}}}", r.indent(0))
+    }
+}
+
+/// An `extern "ABI" { ... }` block of foreign functions and statics.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ExternBlock {
+    pub abi: String,
+    /// The library for `#[link(name = "...")]`.
+    pub link: Option<String>,
+    /// Whether to write `unsafe extern`, which is required from the 2024 edition.
+    pub unsafe_block: bool,
+    pub items: Vec<ForeignItem>,
+}
+
+impl ExternBlock {
+    pub fn new(abi: &str) -> Self {
+        ExternBlock {
+            abi: abi.to_string(),
+            link: None,
+            unsafe_block: false,
+            items: vec![],
+        }
+    }
+
+    pub fn with_link(mut self, library: &str) -> Self {
+        self.link = Some(library.to_string());
+        self
+    }
+
+    pub fn as_unsafe(mut self) -> Self {
+        self.unsafe_block = true;
+        self
+    }
+
+    pub fn add_function(mut self, function: ForeignFn) -> Self {
+        self.items.push(ForeignItem::Fn(function));
+        self
+    }
+
+    pub fn add_static(mut self, stat: ForeignStatic) -> Self {
+        self.items.push(ForeignItem::Static(stat));
+        self
+    }
+
+    /// The names of the functions and statics declared in the block.
+    pub fn names(&self) -> Vec<&str> {
+        self.items.iter()
+            .map(|item| match item {
+                ForeignItem::Fn(func) => func.name.as_str(),
+                ForeignItem::Static(stat) => stat.name.as_str(),
+            })
+            .collect()
+    }
+}
+
+impl Render for ExternBlock {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        if let Some(link) = &self.link {
+            writeln!(r, "{}#[link(name = \"{}\")]", r.indent(0), link)?;
+        }
+
+        let unsafety = match self.unsafe_block {
+            true => "unsafe ",
+            false => "",
+        };
+
+        write!(r, "{}{}extern \"{}\" {{", r.indent(0), unsafety, self.abi)?;
+
+        if self.items.is_empty() {
+            r.empty_body(0, false)?;
+            return writeln!(r);
+        }
+
+        writeln!(r)?;
+        let indent = r.indent(1);
+
+        for item in self.items.iter() {
+            match item {
+                ForeignItem::Fn(func) => func.render_at(r, 1)?,
+                ForeignItem::Static(stat) => write!(r, "{}{}", indent, stat)?,
+            };
+            writeln!(r)?;
+        }
+
+        writeln!(r, "{}}}", r.indent(0))
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum ForeignItem {
+    Fn(ForeignFn),
+    Static(ForeignStatic),
+}
+
+/// A function declared in an `extern` block. Its name and parameter names are kept as written in the foreign
+/// library, such as `bufLen`: they only have to be identifiers, without a case convention.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ForeignFn {
+    pub name: String,
+    pub visibility: Visibility,
+    pub parameters: Vec<(String, Type)>,
+    pub variadic: bool,
+    pub return_type: Option<Type>,
+}
+
+impl ForeignFn {
+    pub fn new(name: &str) -> Self {
+        Self::try_new(name).unwrap()
+    }
+
+    pub fn try_new(name: &str) -> Result<Self, CodeGenError> {
+        Ok(ForeignFn {
+            name: NameRules::DEFAULT.identifier(InputKind::Identifier, name)?,
+            visibility: Visibility::Pub,
+            parameters: vec![],
+            variadic: false,
+            return_type: None,
+        })
+    }
+
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
+
+    pub fn add_parameter(self, name: &str, typ: &str) -> Self {
+        self.try_add_parameter(name, typ).unwrap()
+    }
+
+    pub fn try_add_parameter(mut self, name: &str, typ: &str) -> Result<Self, CodeGenError> {
+        self.parameters.push((NameRules::DEFAULT.identifier(InputKind::Identifier, name)?, typ.parse()?));
+        Ok(self)
+    }
+
+    /// Adds a trailing `...` to the parameters, as for `printf`.
+    pub fn variadic(mut self) -> Self {
+        self.variadic = true;
+        self
+    }
+
+    pub fn with_return(self, return_type: &str) -> Self {
+        self.try_with_return(return_type).unwrap()
+    }
+
+    pub fn try_with_return(mut self, return_type: &str) -> Result<Self, CodeGenError> {
+        self.return_type = Some(return_type.parse()?);
+        Ok(self)
+    }
+}
+
+impl ForeignFn {
+    /// Renders the declaration on a line indented by `levels`, breaking the parameters if they do not fit.
+    pub(crate) fn render_at(&self, r: &mut Renderer<'_>, levels: u8) -> Result<(), Error> {
+        let parameters = Parameters {
+            items: self.parameters.iter().map(|(name, typ)| ForeignParameter(name, typ)),
+            variadic: self.variadic,
+        };
+
+        render_signature(
+            r,
+            levels,
+            format_args!("{}fn {}", self.visibility, self.name),
+            &Generics::none(),
+            parameters,
+            format_args!("{};", Returns(self.return_type.as_ref())),
+        )?;
+        Ok(())
+    }
+}
+
+struct ForeignParameter<'a>(&'a str, &'a Type);
+
+impl Display for ForeignParameter<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}: {}", self.0, self.1)
+    }
+}
+
+impl Render for ForeignFn {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        self.render_at(r, 0)
+    }
+}
+
+/// A static declared in an `extern` block.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ForeignStatic {
+    pub name: String,
+    pub visibility: Visibility,
+    pub mutable: bool,
+    pub typ: Type,
+}
+
+impl ForeignStatic {
+    pub fn new(name: &str, typ: &str) -> Self {
+        Self::try_new(name, typ).unwrap()
+    }
+
+    pub fn try_new(name: &str, typ: &str) -> Result<Self, CodeGenError> {
+        Ok(ForeignStatic {
+            name: NameRules::DEFAULT.identifier(InputKind::Identifier, name)?,
+            visibility: Visibility::Pub,
+            mutable: false,
+            typ: typ.parse()?,
+        })
+    }
+
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
+
+    pub fn as_mut(mut self) -> Self {
+        self.mutable = true;
+        self
+    }
+}
+
+impl Display for ForeignStatic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        let mutability = match self.mutable {
+            true => "mut ",
+            false => "",
+        };
+
+        write!(f, "{}static {}{}: {};", self.visibility, mutability, self.name, self.typ)
+    }
+}
+
+display_via_render!(Union, ExternBlock, ForeignFn);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repr_c_union() {
+        let mut derives = Derives::new();
+        derives.insert(Derive::Copy);
+
+        let u = Union::new("Value")
+            .with_derives(derives)
+            .with_repr("C")
+            .add_field(Field::new("int", "i32"))
+            .add_field(Field::new("float", "f32"));
+
+        assert_eq!(
+            "#[derive(Copy, Clone)]\n#[repr(C)]\npub union Value {\n    pub int: i32,\n    pub float: f32,\n}\n",
+            u.to_string()
+        );
+    }
+
+    #[test]
+    fn extern_block() {
+        let block = ExternBlock::new("C")
+            .with_link("m")
+            .add_function(ForeignFn::new("cos").add_parameter("x", "f64").with_return("f64"))
+            .add_function(ForeignFn::new("printf").add_parameter("format", "*const c_char").variadic().with_return("c_int"))
+            .add_static(ForeignStatic::new("errno", "c_int").as_mut().with_visibility(Visibility::Private));
+
+        assert_eq!(
+            "#[link(name = \"m\")]\nextern \"C\" {\n    pub fn cos(x: f64) -> f64;\n    pub fn printf(format: *const c_char, ...) -> c_int;\n    static mut errno: c_int;\n}\n",
+            block.to_string()
+        );
+        assert_eq!(vec!["cos", "printf", "errno"], block.names());
+    }
+
+    #[test]
+    fn foreign_names_keep_their_case() {
+        let f = ForeignFn::new("SDL_GetError").add_parameter("bufLen", "usize").add_parameter("type", "c_int");
+        assert_eq!("pub fn SDL_GetError(bufLen: usize, r#type: c_int);", f.to_string());
+
+        assert!(ForeignFn::try_new("get-error").is_err());
+        assert_eq!(
+            Err(CodeGenError::name(InputKind::Identifier, "1st", "cannot start with a digit")),
+            ForeignFn::new("get").try_add_parameter("1st", "u8")
+        );
+    }
+
+    #[test]
+    fn unsafe_extern_block() {
+        assert_eq!("unsafe extern \"C\" {}\n", ExternBlock::new("C").as_unsafe().to_string());
+    }
+
+    #[test]
+    fn ffi_items_in_mod() {
+        let m = Mod::new("ffi")
+            .add_item(Union::new("Value").with_repr("C").add_field(Field::new("int", "i32")))
+            .add_item(ExternBlock::new("C").add_function(ForeignFn::new("get").with_return("Value")));
+
+        assert_eq!(
+            "pub mod ffi {\n    #[repr(C)]\n    pub union Value {\n        pub int: i32,\n    }\n\n    extern \"C\" {\n        pub fn get() -> Value;\n    }\n}\n",
+            m.to_string()
+        );
+    }
+}
@@ -2,6 +2,7 @@ use std::fmt::{Display, Formatter, Error};
 use std::ops::{Range};
 use std::str::FromStr;
 use crate::*;
+use crate::identifiers::{unescaped, escape_keyword};
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct CamelCase(String);
@@ -11,6 +12,31 @@ impl CamelCase {
         s.parse().unwrap()
     }
 
+    pub fn try_new(s: &str) -> Result<Self, CodeGenError> {
+        s.parse()
+    }
+
+    /// Parses `value` as a name checked against `rules`, which decide how keywords are handled.
+    pub fn parse_with(value: &str, rules: &NameRules) -> Result<Self, CodeGenError> {
+        if value.contains(' ') {
+            return Err(CodeGenError::name(InputKind::CamelCase, value, "cannot contain spaces"));
+        }
+
+        if value.contains('_') {
+            return Err(CodeGenError::name(InputKind::CamelCase, value, "cannot contain underscores"));
+        }
+
+        if value.is_empty() {
+            return Err(CodeGenError::Empty { kind: InputKind::CamelCase });
+        }
+
+        if unescaped(value).starts_with(char::is_lowercase) {
+            return Err(CodeGenError::name(InputKind::CamelCase, value, "cannot start with lower case"));
+        }
+
+        rules.identifier(InputKind::CamelCase, value).map(CamelCase)
+    }
+
     pub fn as_str(&self) -> &str {
         self.0.as_str()
     }
@@ -27,39 +53,23 @@ impl Display for CamelCase {
 }
 
 impl FromStr for CamelCase {
-    type Err = String;
+    type Err = CodeGenError;
 
     fn from_str(value: &str) -> Result<Self, Self::Err> {
-        if value.contains(' ') {
-            return Err(format!("CamelCase cannot contain spaces: {}", value))
-        }
-
-        if value.contains('_') {
-            return Err(format!("CamelCase cannot contain underscores: {}", value))
-        }
-
-        if value.is_empty() {
-            return Err("CamelCase cannot be empty".to_string());
-        }
-
-        if value.chars().nth(0).unwrap().is_lowercase() {
-            return Err(format!("CamelCase cannot start with lower case: {}", value));
-        }
-
-        Ok(CamelCase(value.to_string()))
+        Self::parse_with(value, &NameRules::DEFAULT)
     }
 }
 
-impl Into<TypeName> for CamelCase {
-    fn into(self) -> TypeName {
-        TypeName(self.0)
+impl From<CamelCase> for TypeName {
+    fn from(value: CamelCase) -> Self {
+        TypeName(value.0)
     }
 }
 
-impl Into<Type> for CamelCase {
-    fn into(self) -> Type {
+impl From<CamelCase> for Type {
+    fn from(value: CamelCase) -> Self {
         Type {
-            name: self.into(),
+            name: value.into(),
             types: Default::default()
         }
     }
@@ -73,9 +83,40 @@ impl SnakeCase {
         s.parse().unwrap()
     }
 
+    pub fn try_new(s: &str) -> Result<Self, CodeGenError> {
+        s.parse()
+    }
+
+    /// Parses `value` as a name checked against `rules`, which decide how keywords are handled.
+    pub fn parse_with(value: &str, rules: &NameRules) -> Result<Self, CodeGenError> {
+        if value.is_empty() {
+            return Err(CodeGenError::Empty { kind: InputKind::SnakeCase });
+        }
+
+        if value.chars().any(char::is_uppercase) {
+            return Err(CodeGenError::name(InputKind::SnakeCase, value, "cannot contain upper case"));
+        }
+
+        if value.contains(' ') {
+            return Err(CodeGenError::name(InputKind::SnakeCase, value, "cannot contain spaces"));
+        }
+
+        if value.contains("__") {
+            return Err(CodeGenError::name(InputKind::SnakeCase, value, "cannot contain double underscores"));
+        }
+
+        rules.identifier(InputKind::SnakeCase, value).map(SnakeCase)
+    }
+
+    /// The name as it is written in code, which is a raw identifier such as `r#type` for keywords.
     pub fn as_str(&self) -> &str {
         self.0.as_str()
     }
+
+    /// The name without the `r#` of a raw identifier, as used in file names and string literals.
+    pub fn unescaped(&self) -> &str {
+        unescaped(&self.0)
+    }
 }
 
 impl Display for SnakeCase {
@@ -85,35 +126,44 @@ impl Display for SnakeCase {
 }
 
 impl FromStr for SnakeCase {
-    type Err = String;
+    type Err = CodeGenError;
 
     fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::parse_with(value, &NameRules::DEFAULT)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ScreamingSnakeCase(String);
+
+impl ScreamingSnakeCase {
+    pub fn new(s: &str) -> Self {
+        s.parse().unwrap()
+    }
+
+    pub fn try_new(s: &str) -> Result<Self, CodeGenError> {
+        s.parse()
+    }
+
+    /// Parses `value` as a name checked against `rules`, which decide how keywords are handled.
+    pub fn parse_with(value: &str, rules: &NameRules) -> Result<Self, CodeGenError> {
         if value.is_empty() {
-            return Err("snake_case cannot be empty".to_string());
+            return Err(CodeGenError::Empty { kind: InputKind::ScreamingSnakeCase });
         }
 
-        if value.chars().any(char::is_uppercase) {
-            return Err(format!("snake_case cannot contain upper case: {}", value));
+        if unescaped(value).chars().any(char::is_lowercase) {
+            return Err(CodeGenError::name(InputKind::ScreamingSnakeCase, value, "cannot contain lowercase"));
         }
 
         if value.contains(' ') {
-            return Err(format!("snake_case cannot contain spaces: {}", value));
+            return Err(CodeGenError::name(InputKind::ScreamingSnakeCase, value, "cannot contain spaces"));
         }
 
         if value.contains("__") {
-            return Err(format!("snake_case cannot contain double underscores: {}", value));
+            return Err(CodeGenError::name(InputKind::ScreamingSnakeCase, value, "cannot contain double underscores"));
         }
 
-        Ok(SnakeCase(value.to_string()))
-    }
-}
-
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
-pub struct ScreamingSnakeCase(String);
-
-impl ScreamingSnakeCase {
-    pub fn new(s: &str) -> Self {
-        s.parse().unwrap()
+        rules.identifier(InputKind::ScreamingSnakeCase, value).map(ScreamingSnakeCase)
     }
 
     pub fn as_str(&self) -> &str {
@@ -128,82 +178,151 @@ impl Display for ScreamingSnakeCase {
 }
 
 impl FromStr for ScreamingSnakeCase {
-    type Err = String;
+    type Err = CodeGenError;
 
     fn from_str(value: &str) -> Result<Self, Self::Err> {
-        if value.chars().any(char::is_lowercase) {
-            return Err(format!("SCREAMING_SNAKE_CASE cannot contain lowercase: {}", value));
+        Self::parse_with(value, &NameRules::DEFAULT)
+    }
+}
+
+/// Converts names between `CamelCase` and `snake_case`. Words start at a capital that follows a lower case letter,
+/// or that starts a capitalised word after a run of capitals or digits, so acronyms stay whole (`HTTPServer` is
+/// `http_server`) and digits stay with the word before them (`Vec3D` is `vec3d`). Single letter words and words
+/// starting with a digit do not survive a round trip: `a_b` becomes `AB`, which is one word, and `vec3d` becomes
+/// `Vec3d`. When acronyms are configured, a capital after a digit also starts a word, so `Vec3D` is `vec3_d` and
+/// converts back, and `UTF8IO` is `utf8_io` if `IO` is an acronym.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
+pub struct CaseConversion {
+    /// Words written in capitals in `CamelCase`, longest first.
+    acronyms: Vec<String>,
+}
+
+impl CaseConversion {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Writes `acronyms` in capitals in `CamelCase` (`http_server` is `HTTPServer`), and splits runs of them
+    /// (`IOHTTP` is `io_http`). Empty acronyms are ignored.
+    pub fn with_acronyms(mut self, acronyms: &[&str]) -> Self {
+        self.acronyms.extend(acronyms.iter().filter(|a| !a.is_empty()).map(|a| a.to_uppercase()));
+        self.acronyms.sort_by_key(|a| std::cmp::Reverse(a.len()));
+        self
+    }
+
+    /// The words of a `CamelCase` name.
+    pub fn words<'a>(&self, name: &'a str) -> Vec<&'a str> {
+        let chars: Vec<(usize, char)> = name.char_indices().collect();
+        let mut words = vec![];
+        let mut start = 0;
+
+        for i in 1..chars.len() {
+            let (at, c) = chars[i];
+            let prev = chars[i - 1].1;
+            let next_is_lower = chars.get(i + 1).is_some_and(|(_, next)| next.is_lowercase());
+
+            let after_digit = prev.is_numeric() && !self.acronyms.is_empty();
+
+            if c.is_uppercase() && (prev.is_lowercase() || next_is_lower || after_digit) {
+                words.push(&name[start..at]);
+                start = at;
+            }
         }
 
-        if value.contains(' ') {
-            return Err(format!("SCREAMING_SNAKE_CASE cannot contain spaces: {}", value));
+        if start < name.len() {
+            words.push(&name[start..]);
         }
 
-        if value.contains("__") {
-            return Err(format!("SCREAMING_SNAKE_CASE cannot contain double underscores: {}", value));
+        words.into_iter()
+            .flat_map(|word| self.split_acronyms(word))
+            .collect()
+    }
+
+    /// Splits known acronyms off the start of a run of capitals.
+    fn split_acronyms<'a>(&self, mut word: &'a str) -> Vec<&'a str> {
+        let mut words = vec![];
+
+        while let Some(acronym) = self.acronyms.iter()
+            .find(|a| word.starts_with(a.as_str()) && word[a.len()..].starts_with(char::is_uppercase)) {
+            words.push(&word[..acronym.len()]);
+            word = &word[acronym.len()..];
         }
 
-        Ok(ScreamingSnakeCase(value.to_string()))
+        words.push(word);
+        words
+    }
+
+    pub fn to_snake_case(&self, name: &CamelCase) -> SnakeCase {
+        let words: Vec<String> = self.words(unescaped(&name.0)).iter()
+            .map(|word| word.to_lowercase())
+            .collect();
+
+        SnakeCase(escape_keyword(words.join("_")))
+    }
+
+    /// Panics if the name has no `CamelCase` form, see `try_to_camel_case`.
+    pub fn to_camel_case(&self, name: &SnakeCase) -> CamelCase {
+        self.try_to_camel_case(name).unwrap()
     }
-}
 
-impl Into<SnakeCase> for CamelCase {
-    fn into(self) -> SnakeCase {
+    /// The `CamelCase` form of `name`, or an error if it is not a valid name, such as `1abc` for `_1abc`.
+    pub fn try_to_camel_case(&self, name: &SnakeCase) -> Result<CamelCase, CodeGenError> {
         let mut output = String::new();
 
-        for (i, c) in self.0.chars().enumerate() {
-            if c.is_ascii_uppercase() && i != 0 {
-                output.push('_');
+        for word in name.unescaped().split('_') {
+            let upper = word.to_uppercase();
+
+            if self.acronyms.contains(&upper) {
+                output.push_str(&upper);
+                continue;
             }
 
-            output.push(c.to_ascii_lowercase());
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                output.extend(first.to_uppercase());
+                output.push_str(chars.as_str());
+            }
         }
 
-        SnakeCase(output)
+        output.parse()
     }
 }
 
-impl Into<SnakeCase> for ScreamingSnakeCase {
-    fn into(self) -> SnakeCase {
-        SnakeCase::from_str(self.0.to_lowercase().as_str()).unwrap()
+impl From<CamelCase> for SnakeCase {
+    fn from(value: CamelCase) -> Self {
+        CaseConversion::new().to_snake_case(&value)
     }
 }
 
-impl Into<ScreamingSnakeCase> for SnakeCase {
-    fn into(self) -> ScreamingSnakeCase {
-        ScreamingSnakeCase::from_str(self.0.to_uppercase().as_str()).unwrap()
+impl From<ScreamingSnakeCase> for SnakeCase {
+    fn from(value: ScreamingSnakeCase) -> Self {
+        SnakeCase(escape_keyword(value.0.to_lowercase()))
     }
 }
 
-impl Into<CamelCase> for SnakeCase {
-    fn into(self) -> CamelCase {
-        let mut output = String::new();
-
-        self.0.split('_')
-            .for_each(|word| {
-                for (i, c) in word.chars().enumerate() {
-                    if i == 0 {
-                        output.push(c.to_uppercase().nth(0).unwrap());
-                    } else {
-                        output.push(c);
-                    }
-                }
-            });
+impl From<SnakeCase> for ScreamingSnakeCase {
+    fn from(value: SnakeCase) -> Self {
+        ScreamingSnakeCase(value.unescaped().to_uppercase())
+    }
+}
 
-        CamelCase::from_str(output.as_str()).unwrap()
+/// Panics if the name has no `CamelCase` form, see `CaseConversion::try_to_camel_case`.
+impl From<SnakeCase> for CamelCase {
+    fn from(value: SnakeCase) -> Self {
+        CaseConversion::new().to_camel_case(&value)
     }
 }
 
-impl Into<CamelCase> for ScreamingSnakeCase {
-    fn into(self) -> CamelCase {
-        let snake: SnakeCase = self.into();
+impl From<ScreamingSnakeCase> for CamelCase {
+    fn from(value: ScreamingSnakeCase) -> Self {
+        let snake: SnakeCase = value.into();
         snake.into()
     }
 }
 
-impl Into<ScreamingSnakeCase> for CamelCase {
-    fn into(self) -> ScreamingSnakeCase {
-        let snake: SnakeCase = self.into();
+impl From<CamelCase> for ScreamingSnakeCase {
+    fn from(value: CamelCase) -> Self {
+        let snake: SnakeCase = value.into();
         snake.into()
     }
 }
@@ -223,8 +342,8 @@ impl Indent {
 
 impl Display for Indent {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        for _ in self.get_range().into_iter() {
-            write!(f, "    ").ok();
+        for _ in self.get_range() {
+            write!(f, "    ")?;
         }
         Ok(())
     }
@@ -242,14 +361,14 @@ pub struct StrConcat<'a, I: IntoIterator<Item=T> + Clone + 'a, T: Display> {
 
 impl<'a, I: IntoIterator<Item=T> + Clone + 'a, T: Display> Display for StrConcat<'a, I, T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        write!(f, "{}", self.left_bound).ok();
+        write!(f, "{}", self.left_bound)?;
 
-        self.iter.clone().into_iter().enumerate().for_each(|(i, s)| {
+        for (i, s) in self.iter.clone().into_iter().enumerate() {
             if i != 0 {
-                write!(f, "{}", self.join).ok();
+                write!(f, "{}", self.join)?;
             }
-            write!(f, "{}{}{}", self.item_prepend, s, self.item_append).ok();
-        });
+            write!(f, "{}{}{}", self.item_prepend, s, self.item_append)?;
+        }
 
         write!(f, "{}", self.right_bound)
     }
@@ -384,4 +503,138 @@ mod tests {
 
         assert_eq!("(&mut A-1, &mut B-1, &mut C-1)", concat.to_string());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn keywords_become_raw_identifiers() {
+        assert_eq!("r#type", SnakeCase::new("type").to_string());
+        assert_eq!("type", SnakeCase::new("type").unescaped());
+        assert_eq!("r#type", CamelCase::new("Type").into_snake_case().to_string());
+        assert_eq!("Type", CamelCase::from(SnakeCase::new("r#type")).to_string());
+        assert!(SnakeCase::parse_with("type", &NameRules::new().rejecting_keywords()).is_err());
+        assert!(SnakeCase::from_str("self").is_err());
+        assert!(CamelCase::from_str("Self").is_err());
+    }
+
+    #[test]
+    fn names_must_be_identifiers() {
+        assert!(SnakeCase::from_str("1abc").is_err());
+        assert!(SnakeCase::from_str("foo-bar").is_err());
+        assert!(CamelCase::from_str("1Abc").is_err());
+        assert!(ScreamingSnakeCase::from_str("MAX-SPEED").is_err());
+        assert!(SnakeCase::from_str("vec3").is_ok());
+    }
+
+    #[test]
+    fn acronyms_and_digits() {
+        let snake = |camel: &str| CamelCase::new(camel).into_snake_case().to_string();
+
+        assert_eq!("http_server", snake("HTTPServer"));
+        assert_eq!("vec3d", snake("Vec3D"));
+        assert_eq!("vec3d_list", snake("Vec3DList"));
+        assert_eq!("http2_server", snake("HTTP2Server"));
+        assert_eq!("utf8_string", snake("Utf8String"));
+        assert_eq!("a_value", snake("AValue"));
+        assert_eq!("iohttp", snake("IOHTTP"));
+    }
+
+    #[test]
+    fn configured_acronyms() {
+        let conversion = CaseConversion::new().with_acronyms(&["http", "IO"]);
+
+        assert_eq!("io_http", conversion.to_snake_case(&CamelCase::new("IOHTTP")).to_string());
+        assert_eq!("utf8_io", conversion.to_snake_case(&CamelCase::new("UTF8IO")).to_string());
+        assert_eq!("HTTPServer", conversion.to_camel_case(&SnakeCase::new("http_server")).to_string());
+        assert_eq!("HttpServer", CamelCase::from(SnakeCase::new("http_server")).to_string());
+        assert_eq!("vec3_d_list", conversion.to_snake_case(&CamelCase::new("Vec3DList")).to_string());
+        assert_eq!("Vec3DList", conversion.to_camel_case(&SnakeCase::new("vec3_d_list")).to_string());
+    }
+
+    #[test]
+    fn names_without_a_camel_case_form() {
+        let conversion = CaseConversion::new();
+
+        assert_eq!(Some(InputKind::CamelCase), conversion.try_to_camel_case(&SnakeCase::new("_1abc")).unwrap_err().kind());
+        assert_eq!("Abc", conversion.try_to_camel_case(&SnakeCase::new("_abc")).unwrap().to_string());
+    }
+
+    #[test]
+    fn empty_acronyms_are_ignored() {
+        let conversion = CaseConversion::new().with_acronyms(&["", "IO"]);
+
+        assert_eq!("io_port", conversion.to_snake_case(&CamelCase::new("IOPort")).to_string());
+        assert_eq!("IOPort", conversion.to_camel_case(&SnakeCase::new("io_port")).to_string());
+    }
+
+    /// A xorshift generator, so the property tests are reproducible without dependencies.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+
+        /// A lower case word of two or more letters, sometimes ending in digits.
+        fn word(&mut self) -> String {
+            let len = 2 + self.below(6);
+            let mut word: String = (0..len).map(|_| (b'a' + self.below(26) as u8) as char).collect();
+
+            if self.below(4) == 0 {
+                word.push_str(&(1 + self.below(99)).to_string());
+            }
+
+            word
+        }
+
+        /// The words of a name, or `None` if they would make a keyword such as `self` that cannot be a name.
+        fn words(&mut self, acronyms: &[&str]) -> Option<Vec<String>> {
+            let words: Vec<String> = (0..1 + self.below(4))
+                .map(|_| match self.below(5) {
+                    0 if !acronyms.is_empty() => acronyms[self.below(acronyms.len())].to_lowercase(),
+                    _ => self.word(),
+                })
+                .collect();
+
+            SnakeCase::try_new(&words.join("_")).ok().map(|_| words)
+        }
+    }
+
+    fn capitalised(word: &str, acronyms: &[&str]) -> String {
+        match acronyms.iter().any(|a| a.eq_ignore_ascii_case(word)) {
+            true => word.to_uppercase(),
+            false => word[..1].to_uppercase() + &word[1..],
+        }
+    }
+
+    #[test]
+    fn case_conversions_round_trip() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+
+        for acronyms in [&[][..], &["HTTP", "ID", "IO", "UTF8"][..]] {
+            let conversion = CaseConversion::new().with_acronyms(acronyms);
+
+            for _ in 0..1000 {
+                let words = match rng.words(acronyms) {
+                    Some(words) => words,
+                    None => continue,
+                };
+
+                let snake = SnakeCase::new(&words.join("_"));
+                let camel = CamelCase::new(&words.iter().map(|w| capitalised(w, acronyms)).collect::<String>());
+                let screaming = ScreamingSnakeCase::new(&words.join("_").to_uppercase());
+
+                assert_eq!(snake, conversion.to_snake_case(&conversion.to_camel_case(&snake)), "{:?}", words);
+                assert_eq!(camel, conversion.to_camel_case(&conversion.to_snake_case(&camel)), "{:?}", words);
+                assert_eq!(camel, conversion.to_camel_case(&snake), "{:?}", words);
+                assert_eq!(screaming, ScreamingSnakeCase::from(SnakeCase::from(screaming.clone())), "{:?}", words);
+                assert_eq!(snake, SnakeCase::from(ScreamingSnakeCase::from(snake.clone())), "{:?}", words);
+
+                if acronyms.is_empty() {
+                    assert_eq!(screaming, ScreamingSnakeCase::from(CamelCase::from(screaming.clone())), "{:?}", words);
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt::{Display, Formatter, Error, Debug};
 use std::iter::FromIterator;
-use crate::{StrConcat, Type};
+use crate::{StrConcat, Type, Render, Renderer, CodeGenError, InputKind};
+use crate::render::display_via_render;
 use std::str::FromStr;
 
 #[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
@@ -32,9 +33,28 @@ impl Generics {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Generic> {
+        self.0.iter()
+    }
+
+    /// Turns type arguments into impl parameters bounded by `bound`: `<'a, T>` becomes `<'a, T: Debug>`.
+    pub fn with_bound(&self, bound: &str) -> Generics {
+        self.0.iter()
+            .map(|g| match g {
+                Generic::Type(t) => Generic::new(&format!("{}: {}", t, bound)),
+                g => g.clone(),
+            })
+            .collect()
+    }
+
+    /// The number of generics that must be supplied, i.e. those without a default.
+    pub fn required_len(&self) -> usize {
+        self.0.iter().filter(|g| !g.has_default()).count()
+    }
 }
 
-impl<'a> FromIterator<Generic> for Generics {
+impl FromIterator<Generic> for Generics {
     fn from_iter<T: IntoIterator<Item=Generic>>(iter: T) -> Self {
         Generics(iter.into_iter().collect())
     }
@@ -60,25 +80,26 @@ impl Display for Generics {
 }
 
 impl FromStr for Generics {
-    type Err = String;
+    type Err = CodeGenError;
 
     fn from_str(input: &str) -> Result<Self, Self::Err> {
-        if input == "" {
+        if input.is_empty() {
             return Ok(Generics::none())
         }
 
         if !input.starts_with('<') || !input.ends_with('>') {
-            return Err("Generics must be wrapped by '<>'".to_string());
+            return Err(CodeGenError::syntax(InputKind::Generics, input, "must be wrapped by '<>'"));
         }
 
         let input = &input[1..input.len()-1];
 
-        input.split(',')
+        split_top_level(input, ',')
+            .into_iter()
             .map(|s| s.trim().parse::<Generic>())
             .collect::<Result<Vec<_>,_>>()
             .and_then(|types| {
                 if types.is_empty() {
-                    Err("Input cannot be empty".to_string())
+                    Err(CodeGenError::Empty { kind: InputKind::Generics })
                 } else {
                     Ok(Generics::from_iter(types))
                 }
@@ -90,12 +111,21 @@ impl FromStr for Generics {
 pub enum Generic {
     Type(Type),
     Lifetime(Lifetime),
+    Param(TypeParam),
 }
 
 impl Generic {
     pub fn new(gen: &str) -> Self {
         Self::from_str(gen).unwrap()
     }
+
+    pub fn try_new(gen: &str) -> Result<Self, CodeGenError> {
+        Self::from_str(gen)
+    }
+
+    pub fn has_default(&self) -> bool {
+        matches!(self, Generic::Param(TypeParam { default: Some(_), .. }))
+    }
 }
 
 impl Display for Generic {
@@ -103,17 +133,22 @@ impl Display for Generic {
         match self {
             Generic::Type(t) => write!(f, "{}", t),
             Generic::Lifetime(l) => write!(f, "{}", l),
+            Generic::Param(p) => write!(f, "{}", p),
         }
     }
 }
 
 impl FromStr for Generic {
-    type Err = String;
+    type Err = CodeGenError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if find_bound_colon(s).is_some() || split_top_level(s, '=').len() > 1 {
+            return TypeParam::from_str(s).map(Generic::from);
+        }
+
         Type::from_str(s).map(Generic::from)
             .or_else(|_| Lifetime::from_str(s).map(Generic::from))
-            .map_err(|_| format!("Could not parse as Type or Lifetime: {}", s))
+            .map_err(|_| CodeGenError::syntax(InputKind::Generic, s, "must be a type or a lifetime"))
     }
 }
 
@@ -129,6 +164,252 @@ impl From<Lifetime> for Generic {
     }
 }
 
+impl From<TypeParam> for Generic {
+    fn from(p: TypeParam) -> Self {
+        Generic::Param(p)
+    }
+}
+
+/// A type parameter with optional trait bounds and default, such as `T: Display + Clone`
+/// or `Rhs: ?Sized = Self`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TypeParam {
+    pub typ: Type,
+    pub bounds: Bounds,
+    pub default: Option<Type>,
+}
+
+impl TypeParam {
+    pub fn new(param: &str) -> Self {
+        param.parse().unwrap()
+    }
+
+    pub fn try_new(param: &str) -> Result<Self, CodeGenError> {
+        param.parse()
+    }
+}
+
+impl Display for TypeParam {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.typ)?;
+
+        if !self.bounds.is_empty() {
+            write!(f, ": {}", self.bounds)?;
+        }
+
+        if let Some(default) = &self.default {
+            write!(f, " = {}", default)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for TypeParam {
+    type Err = CodeGenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (param, default) = match split_top_level(s, '=').as_slice() {
+            [param] => (*param, None),
+            [param, default] => (*param, Some(default.trim().parse()?)),
+            _ => return Err(CodeGenError::syntax(InputKind::TypeParam, s, "can only have one default")),
+        };
+
+        match find_bound_colon(param) {
+            Some(i) => Ok(TypeParam {
+                typ: param[..i].trim().parse()?,
+                bounds: param[i + 1..].trim().parse()?,
+                default,
+            }),
+            None => Ok(TypeParam {
+                typ: param.trim().parse()?,
+                bounds: Bounds::default(),
+                default,
+            }),
+        }
+    }
+}
+
+/// Trait and lifetime bounds joined by `+`, such as `Display + Clone + 'static`.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
+pub struct Bounds(Vec<Generic>);
+
+impl Bounds {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Display for Bounds {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}",
+            StrConcat {
+                iter: &self.0,
+                left_bound: "",
+                right_bound: "",
+                item_prepend: "",
+                item_append: "",
+                join: " + "
+            }
+        )
+    }
+}
+
+impl FromStr for Bounds {
+    type Err = CodeGenError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let bounds = split_top_level(input, '+')
+            .into_iter()
+            .map(|s| s.trim().parse::<Generic>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if bounds.is_empty() {
+            return Err(CodeGenError::Empty { kind: InputKind::Bounds });
+        }
+
+        Ok(Bounds(bounds))
+    }
+}
+
+/// The predicates of a `where` clause, such as `where T: Display, Vec<T>: Debug`.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
+pub struct WhereClause(Vec<TypeParam>);
+
+impl WhereClause {
+    pub fn none() -> Self { Default::default() }
+
+    pub fn new(predicates: &str) -> Self {
+        predicates.parse().unwrap()
+    }
+
+    pub fn try_new(predicates: &str) -> Result<Self, CodeGenError> {
+        predicates.parse()
+    }
+
+    pub fn push(&mut self, predicate: &str) {
+        self.0.push(TypeParam::new(predicate));
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, TypeParam> {
+        self.0.iter()
+    }
+}
+
+impl Render for WhereClause {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(r, "{}where", r.indent(0))?;
+
+        for (i, predicate) in self.0.iter().enumerate() {
+            writeln!(r, "{}{}{}", r.indent(1), predicate, r.comma(i == self.0.len() - 1))?;
+        }
+
+        Ok(())
+    }
+}
+
+display_via_render!(WhereClause);
+
+impl FromStr for WhereClause {
+    type Err = CodeGenError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        let input = input.strip_prefix("where ").unwrap_or(input);
+
+        split_top_level(input, ',')
+            .into_iter()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(TypeParam::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .map(WhereClause)
+    }
+}
+
+/// Splits `input` on `separator`, ignoring separators nested inside `<>`, `()`, `[]` or `{}`.
+pub(crate) fn split_top_level(input: &str, separator: char) -> Vec<&str> {
+    SplitTopLevel { rest: Some(input), separator }.collect()
+}
+
+/// Iterates over the parts `split_top_level` returns, without collecting them.
+pub(crate) fn top_level_parts(input: &str, separator: char) -> SplitTopLevel<'_> {
+    SplitTopLevel { rest: Some(input), separator }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SplitTopLevel<'a> {
+    rest: Option<&'a str>,
+    separator: char,
+}
+
+impl<'a> Iterator for SplitTopLevel<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let input = self.rest?;
+        let mut depth = 0i32;
+        let mut previous = None;
+
+        for (i, c) in input.char_indices() {
+            match c {
+                '<' | '(' | '[' | '{' => depth += 1,
+                '>' if previous != Some('-') => depth -= 1,
+                ')' | ']' | '}' => depth -= 1,
+                c if c == self.separator && depth == 0 => {
+                    self.rest = Some(&input[i + c.len_utf8()..]);
+                    return Some(&input[..i]);
+                },
+                _ => {},
+            }
+            previous = Some(c);
+        }
+
+        self.rest = None;
+        Some(input)
+    }
+}
+
+/// Finds the top-level `:` that separates a type from its bounds, skipping `::` path separators.
+fn find_bound_colon(input: &str) -> Option<usize> {
+    let bytes = input.as_bytes();
+    let mut depth = 0i32;
+
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            b'<' | b'(' | b'[' => depth += 1,
+            b'>' if i == 0 || bytes[i - 1] != b'-' => depth -= 1,
+            b')' | b']' => depth -= 1,
+            b':' if depth == 0 => {
+                let prev_colon = i > 0 && bytes[i - 1] == b':';
+                let next_colon = bytes.get(i + 1) == Some(&b':');
+                if !prev_colon && !next_colon {
+                    return Some(i);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    None
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Lifetime(String);
 
@@ -145,13 +426,13 @@ impl Display for Lifetime {
 }
 
 impl FromStr for Lifetime {
-    type Err = String;
+    type Err = CodeGenError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Some('\'') = s.chars().nth(0) {
             Ok(Lifetime(s.chars().skip(1).collect()))
         } else {
-            Err(format!("Lifetime values must begin with an apostrophe: {}", s))
+            Err(CodeGenError::syntax(InputKind::Lifetime, s, "must begin with an apostrophe"))
         }
     }
 }
@@ -219,4 +500,74 @@ mod tests {
     fn with_lifetime() {
         assert_eq!("<'a, u32>".parse::<Generics>().unwrap(), Generics::two("'a", "u32"));
     }
+
+    #[test]
+    fn nested_generic_arguments() {
+        let g = "<ID, Component<ID, T>>".parse::<Generics>().unwrap();
+
+        assert_eq!(2, g.len());
+        assert_eq!("<ID, Component<ID, T>>", g.to_string());
+    }
+
+    #[test]
+    fn bounded_type_param() {
+        let g = Generic::new("T: Display + Clone + 'static");
+
+        assert!(matches!(g, Generic::Param(_)));
+        assert_eq!("T: Display + Clone + 'static", g.to_string());
+    }
+
+    #[test]
+    fn path_is_not_a_bound() {
+        assert_eq!(Generic::Type(Type::new("std::fmt::Display")), Generic::new("std::fmt::Display"));
+        assert_eq!("T: std::fmt::Display", Generic::new("T: std::fmt::Display").to_string());
+    }
+
+    #[test]
+    fn generics_with_bounds() {
+        let g = "<T: Into<Vec<u8>>, U: Clone>".parse::<Generics>().unwrap();
+
+        assert_eq!("<T: Into<Vec<u8>>, U: Clone>", g.to_string());
+    }
+
+    #[test]
+    fn type_param_with_default() {
+        let g = Generic::new("Rhs: ?Sized = Self");
+
+        assert!(g.has_default());
+        assert_eq!("Rhs: ?Sized = Self", g.to_string());
+        assert_eq!("Rhs = Self", Generic::new("Rhs = Self").to_string());
+    }
+
+    #[test]
+    fn required_len_skips_defaults() {
+        let g = "<T, Rhs = Self>".parse::<Generics>().unwrap();
+
+        assert_eq!(2, g.len());
+        assert_eq!(1, g.required_len());
+    }
+
+    #[test]
+    fn with_bound() {
+        let g = "<'a, T>".parse::<Generics>().unwrap();
+
+        assert_eq!("<'a, T: Debug>", g.with_bound("Debug").to_string());
+    }
+
+    #[test]
+    fn bounds_cannot_be_empty() {
+        assert!("T:".parse::<TypeParam>().is_err());
+    }
+
+    #[test]
+    fn where_clause_display() {
+        let w = WhereClause::new("T: Display, Vec<T>: Debug");
+
+        assert_eq!("where\n    T: Display,\n    Vec<T>: Debug,\n", w.to_string());
+    }
+
+    #[test]
+    fn empty_where_clause_displays_nothing() {
+        assert_eq!("", WhereClause::none().to_string());
+    }
 }
\ No newline at end of file
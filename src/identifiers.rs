@@ -0,0 +1,212 @@
+use crate::{CodeGenError, InputKind};
+
+/// A Rust edition, which decides the words that are reserved as keywords.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Edition {
+    E2015,
+    E2018,
+    E2021,
+    /// The latest edition, whose keywords are a superset of the earlier ones.
+    #[default]
+    E2024,
+}
+
+/// What to do with a name that is a reserved keyword, such as a field called `type`.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum KeywordHandling {
+    /// Write it as a raw identifier: `r#type`. Keywords that cannot be raw, such as `self`, are still errors.
+    #[default]
+    Escape,
+    /// Fail with `CodeGenError::InvalidName`.
+    Reject,
+}
+
+/// The rules names are checked against when they are parsed. `FromStr` and `new` use `NameRules::DEFAULT`.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct NameRules {
+    pub edition: Edition,
+    pub keywords: KeywordHandling,
+}
+
+impl NameRules {
+    pub const DEFAULT: NameRules = NameRules {
+        edition: Edition::E2024,
+        keywords: KeywordHandling::Escape,
+    };
+
+    pub fn new() -> Self {
+        Self::DEFAULT
+    }
+
+    pub fn with_edition(mut self, edition: Edition) -> Self {
+        self.edition = edition;
+        self
+    }
+
+    pub fn rejecting_keywords(mut self) -> Self {
+        self.keywords = KeywordHandling::Reject;
+        self
+    }
+
+    /// Checks that `input` is a Rust identifier, returning it as it should be written: escaped as a raw identifier
+    /// if it is a keyword. Input that is already raw, such as `r#type`, is kept.
+    pub(crate) fn identifier(&self, kind: InputKind, input: &str) -> Result<String, CodeGenError> {
+        let (raw, name) = match input.strip_prefix("r#") {
+            Some(name) => (true, name),
+            None => (false, input),
+        };
+
+        check_characters(kind, input, name)?;
+
+        if !raw && !is_keyword(name, self.edition) {
+            return Ok(input.to_string());
+        }
+
+        if !can_be_raw(name) {
+            return Err(CodeGenError::name(kind, input, "is a keyword that cannot be a raw identifier"));
+        }
+
+        match (raw, self.keywords) {
+            (true, _) => Ok(input.to_string()),
+            (false, KeywordHandling::Escape) => Ok(format!("r#{}", name)),
+            (false, KeywordHandling::Reject) => Err(CodeGenError::name(kind, input, "is a reserved keyword")),
+        }
+    }
+}
+
+/// Checks the characters of an identifier. XID_Start is approximated with the Unicode alphabetic property, and
+/// XID_Continue with alphabetic characters and decimal digits (category Nd). Other numbers, such as the `²` in
+/// `a²`, are rejected as rustc does. So are combining marks, which XID_Continue would accept.
+fn check_characters(kind: InputKind, input: &str, name: &str) -> Result<(), CodeGenError> {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        None => return Err(CodeGenError::Empty { kind }),
+        Some(c) if c.is_numeric() && !c.is_alphabetic() => return Err(CodeGenError::name(kind, input, "cannot start with a digit")),
+        Some(c) if !is_ident_start(c) => return Err(CodeGenError::name(kind, input, "must start with a letter or an underscore")),
+        Some(_) => {},
+    }
+
+    if !chars.all(is_ident_continue) {
+        return Err(CodeGenError::name(kind, input, "can only contain letters, digits and underscores"));
+    }
+
+    if name == "_" {
+        return Err(CodeGenError::name(kind, input, "cannot be only an underscore"));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn is_ident_start(c: char) -> bool {
+    c == '_' || c.is_alphabetic()
+}
+
+pub(crate) fn is_ident_continue(c: char) -> bool {
+    c == '_' || c.is_alphabetic() || is_decimal_digit(c)
+}
+
+fn is_decimal_digit(c: char) -> bool {
+    DECIMAL_ZEROS.iter().any(|zero| (*zero..zero + 10).contains(&(c as u32)))
+}
+
+/// The zero of each run of ten decimal digits (category Nd) in Unicode 14.0. Digits added later are rejected.
+const DECIMAL_ZEROS: &[u32] = &[
+    0x30, 0x660, 0x6F0, 0x7C0, 0x966, 0x9E6, 0xA66, 0xAE6, 0xB66, 0xBE6, 0xC66, 0xCE6, 0xD66, 0xDE6, 0xE50, 0xED0,
+    0xF20, 0x1040, 0x1090, 0x17E0, 0x1810, 0x1946, 0x19D0, 0x1A80, 0x1A90, 0x1B50, 0x1BB0, 0x1C40, 0x1C50, 0xA620,
+    0xA8D0, 0xA900, 0xA9D0, 0xA9F0, 0xAA50, 0xABF0, 0xFF10, 0x104A0, 0x10D30, 0x11066, 0x110F0, 0x11136, 0x111D0,
+    0x112F0, 0x11450, 0x114D0, 0x11650, 0x116C0, 0x11730, 0x118E0, 0x11950, 0x11C50, 0x11D50, 0x11DA0, 0x16A60,
+    0x16AC0, 0x16B50, 0x1D7CE, 0x1D7D8, 0x1D7E2, 0x1D7EC, 0x1D7F6, 0x1E140, 0x1E2F0, 0x1E950, 0x1FBF0,
+];
+
+/// Keywords reserved in every edition.
+const KEYWORDS: &[&str] = &[
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for", "if", "impl", "in",
+    "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+    "trait", "true", "type", "unsafe", "use", "where", "while",
+    // reserved for future use
+    "abstract", "become", "box", "do", "final", "macro", "override", "priv", "typeof", "unsized", "virtual", "yield",
+];
+
+/// Keywords added by the 2018 edition.
+const KEYWORDS_2018: &[&str] = &["async", "await", "dyn", "try"];
+
+/// Keywords added by the 2024 edition.
+const KEYWORDS_2024: &[&str] = &["gen"];
+
+/// Whether `name` is a reserved keyword in `edition`.
+pub fn is_keyword(name: &str, edition: Edition) -> bool {
+    KEYWORDS.contains(&name)
+        || (edition >= Edition::E2018 && KEYWORDS_2018.contains(&name))
+        || (edition >= Edition::E2024 && KEYWORDS_2024.contains(&name))
+}
+
+/// Whether `name` can be written as a raw identifier. Path keywords cannot.
+fn can_be_raw(name: &str) -> bool {
+    !matches!(name, "crate" | "self" | "Self" | "super")
+}
+
+/// `name` without the `r#` of a raw identifier: the name as it appears in file names and string literals.
+pub(crate) fn unescaped(name: &str) -> &str {
+    name.strip_prefix("r#").unwrap_or(name)
+}
+
+/// Makes a name produced by a case conversion usable: a keyword becomes a raw identifier, or gets a trailing `_`
+/// if it cannot be raw, as rustc suggests.
+pub(crate) fn escape_keyword(mut name: String) -> String {
+    if !is_keyword(&name, Edition::E2024) {
+        return name;
+    }
+
+    match can_be_raw(&name) {
+        true => format!("r#{}", name),
+        false => {
+            name.push('_');
+            name
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keywords_per_edition() {
+        assert!(is_keyword("type", Edition::E2015));
+        assert!(!is_keyword("async", Edition::E2015));
+        assert!(is_keyword("async", Edition::E2018));
+        assert!(!is_keyword("gen", Edition::E2021));
+        assert!(is_keyword("gen", Edition::E2024));
+    }
+
+    #[test]
+    fn keywords_are_escaped_or_rejected() {
+        let rules = NameRules::new();
+
+        assert_eq!(Ok("r#type".to_string()), rules.identifier(InputKind::SnakeCase, "type"));
+        assert_eq!(Ok("r#match".to_string()), rules.identifier(InputKind::SnakeCase, "r#match"));
+        assert_eq!(Ok("gen".to_string()), rules.with_edition(Edition::E2021).identifier(InputKind::SnakeCase, "gen"));
+        assert_eq!(
+            "snake_case is a reserved keyword: type",
+            rules.rejecting_keywords().identifier(InputKind::SnakeCase, "type").unwrap_err().to_string()
+        );
+        assert!(rules.identifier(InputKind::SnakeCase, "self").is_err());
+        assert!(rules.identifier(InputKind::CamelCase, "r#Self").is_err());
+    }
+
+    #[test]
+    fn characters() {
+        let rules = NameRules::new();
+
+        assert_eq!(Ok("größe".to_string()), rules.identifier(InputKind::SnakeCase, "größe"));
+        assert!(rules.identifier(InputKind::SnakeCase, "1abc").is_err());
+        assert!(rules.identifier(InputKind::SnakeCase, "foo-bar").is_err());
+        assert!(rules.identifier(InputKind::SnakeCase, "_").is_err());
+        assert!(rules.identifier(InputKind::SnakeCase, "r#").is_err());
+        assert!(rules.identifier(InputKind::SnakeCase, "a²").is_err());
+        assert!(rules.identifier(InputKind::SnakeCase, "a½").is_err());
+        assert!(rules.identifier(InputKind::SnakeCase, "x٣").is_ok());
+        assert!(rules.identifier(InputKind::CamelCase, "Ⅻ").is_ok());
+    }
+}
@@ -1,5 +1,8 @@
 use crate::*;
+use crate::items::{Preamble, with_preamble};
+use crate::render::display_via_render;
 use std::str::FromStr;
+use crate::generics::top_level_parts;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Impl {
@@ -18,10 +21,14 @@ impl From<&Type> for Impl {
 
 impl Impl {
     pub fn new(target: &str) -> Self {
-        Self {
-            strct: Type::from_str(target).unwrap(),
+        Self::try_new(target).unwrap()
+    }
+
+    pub fn try_new(target: &str) -> Result<Self, CodeGenError> {
+        Ok(Self {
+            strct: Type::from_str(target)?,
             functions: vec![],
-        }
+        })
     }
 
     pub fn add_function(mut self, function: Function) -> Self {
@@ -30,30 +37,26 @@ impl Impl {
     }
 }
 
-impl Display for Impl {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        write!(f, "impl {} {{", self.strct).ok();
+impl Render for Impl {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        write!(r, "{}impl {} {{", r.indent(0), self.strct)?;
 
-        if !self.functions.is_empty() {
-            writeln!(f).ok();
+        if self.functions.is_empty() {
+            r.empty_body(0, false)?;
+            return writeln!(r);
         }
 
-        let functions: Vec<String> = self.functions.iter()
-            .map(ToString::to_string)
-            .collect();
+        writeln!(r)?;
 
-        let functions = StrConcat {
-            iter: functions,
-            left_bound: "",
-            right_bound: "",
-            item_prepend: "",
-            item_append: "",
-            join: "\n"
-        };
+        for (i, function) in self.functions.iter().enumerate() {
+            if i > 0 {
+                r.item_separator()?;
+            }
 
-        write!(f, "{}", functions).ok();
+            function.render_at(r, 1)?;
+        }
 
-        writeln!(f, "}}")
+        writeln!(r, "{}}}", r.indent(0))
     }
 }
 
@@ -65,18 +68,26 @@ pub struct Function {
     pub parameters: String,
     pub return_type: Option<String>,
     pub lines: Vec<CodeLine>,
+    pub docs: Vec<String>,
+    pub attributes: Vec<String>,
 }
 
 impl Function {
     pub fn new(name: &str) -> Self {
-        Self {
-            name: name.parse().unwrap(),
+        Self::try_new(name).unwrap()
+    }
+
+    pub fn try_new(name: &str) -> Result<Self, CodeGenError> {
+        Ok(Self {
+            name: name.parse()?,
             generics: Generics::none(),
             visibility: Visibility::Pub,
             parameters: String::new(),
             return_type: None,
             lines: vec![],
-        }
+            docs: vec![],
+            attributes: vec![],
+        })
     }
 
     pub fn with_generics(mut self, generics: Generics) -> Self {
@@ -103,48 +114,135 @@ impl Function {
         self.lines.push(line);
         self
     }
+
+    /// Renders the function `levels` deep: 1 for methods, 0 for free functions.
+    pub(crate) fn render_at(&self, r: &mut Renderer<'_>, levels: u8) -> Result<(), Error> {
+        let indent = r.indent(levels);
+        Preamble { levels, docs: &self.docs, attributes: &self.attributes }.render(r)?;
+
+        let broken = render_signature(
+            r,
+            levels,
+            format_args!("{}fn {}", self.visibility, self.name),
+            &self.generics,
+            Parameters::new(parameter_list(&self.parameters)),
+            format_args!("{} {{", Returns(self.return_type.as_ref())),
+        )?;
+
+        if self.lines.is_empty() {
+            r.empty_body(levels, broken)?;
+            return writeln!(r);
+        }
+
+        writeln!(r)?;
+        for line in self.lines.iter() {
+            line.render_at(r, levels)?;
+            writeln!(r)?;
+        }
+        writeln!(r, "{}}}", indent)
+    }
 }
 
-impl Display for Function {
+impl Render for Function {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        self.render_at(r, 1)
+    }
+}
+
+/// Splits a parameter list such as `a: u32, f: impl Fn(u32, u32)` into its parameters.
+pub(crate) fn parameter_list(parameters: &str) -> impl Iterator<Item = &str> + Clone {
+    top_level_parts(parameters, ',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+}
+
+/// The parameters of a function signature, optionally followed by the `...` of a variadic function.
+#[derive(Debug, Clone)]
+pub(crate) struct Parameters<I> {
+    pub(crate) items: I,
+    pub(crate) variadic: bool,
+}
+
+impl<I> Parameters<I> {
+    pub(crate) fn new(items: I) -> Self {
+        Parameters { items, variadic: false }
+    }
+}
+
+impl<I: IntoIterator<Item = T> + Clone, T: Display> Parameters<I> {
+    fn is_empty(&self) -> bool {
+        !self.variadic && self.items.clone().into_iter().next().is_none()
+    }
+}
+
+impl<I: IntoIterator<Item = T> + Clone, T: Display> Display for Parameters<I> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        match &self.return_type {
-            Some(ret) => {
-                write!(
-                    f,
-                    "{}{}fn {}{}({}) -> {} {{",
-                    Indent(1),
-                    self.visibility,
-                    self.name,
-                    self.generics,
-                    self.parameters,
-                    ret,
-                ).ok();
-            },
-            None => {
-                write!(
-                    f,
-                    "{}{}fn {}{}({}) {{",
-                    Indent(1),
-                    self.visibility,
-                    self.name,
-                    self.generics,
-                    self.parameters,
-                ).ok();
-            },
+        let mut separator = "";
+
+        for item in self.items.clone() {
+            write!(f, "{}{}", separator, item)?;
+            separator = ", ";
         }
 
-        if self.lines.is_empty() {
-            writeln!(f, "}}")
-        } else {
-            writeln!(f).ok();
-            for line in self.lines.iter() {
-                writeln!(f, "{}{}", Indent(1), line).ok();
-            }
-            writeln!(f, "    }}")
+        match self.variadic {
+            true => write!(f, "{}...", separator),
+            false => Ok(()),
         }
     }
 }
 
+/// ` -> T` for a return type, or nothing.
+pub(crate) struct Returns<'a, T>(pub(crate) Option<&'a T>);
+
+impl<T: Display> Display for Returns<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self.0 {
+            Some(ret) => write!(f, " -> {}", ret),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Writes `head` (such as `pub fn name`), the generics, the parameters and `tail` on a line indented by `levels`.
+/// Like rustfmt, the parameters are put one per line if the signature does not fit, and the generics too if the
+/// line up to the parameters does not fit. Returns whether the signature was broken across lines.
+pub(crate) fn render_signature<H, I, T, R>(r: &mut Renderer<'_>, levels: u8, head: H, generics: &Generics, parameters: Parameters<I>, tail: R) -> Result<bool, Error>
+where
+    H: Display,
+    I: IntoIterator<Item = T> + Clone,
+    T: Display,
+    R: Display,
+{
+    let indent = r.indent(levels);
+    let generics_fit = r.fits(levels, format_args!("{}{}(", head, generics));
+
+    if r.fits(levels, format_args!("{}{}({}){}", head, generics, parameters, tail)) || (parameters.is_empty() && generics_fit) {
+        write!(r, "{}{}{}({}){}", indent, head, generics, parameters, tail)?;
+        return Ok(false);
+    }
+
+    if generics_fit {
+        write!(r, "{}{}{}(", indent, head, generics)?;
+    } else {
+        writeln!(r, "{}{}<", indent, head)?;
+        r.vertical(levels + 1, generics.iter())?;
+        write!(r, "{}>(", indent)?;
+    }
+
+    if parameters.is_empty() {
+        write!(r, "){}", tail)?;
+    } else {
+        writeln!(r)?;
+        match parameters.variadic {
+            true => r.vertical_variadic(levels + 1, parameters.items)?,
+            false => r.vertical(levels + 1, parameters.items)?,
+        }
+        write!(r, "{}){}", indent, tail)?;
+    }
+
+    Ok(true)
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct CodeLine {
     pub indent: Indent,
@@ -160,12 +258,23 @@ impl CodeLine {
     }
 }
 
-impl Display for CodeLine {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        write!(f, "{}{}", self.indent, self.text)
+impl CodeLine {
+    /// Renders the line inside a block that is `levels` deep. If the text spans several lines, lines inside string
+    /// literals are kept as they are.
+    pub(crate) fn render_at(&self, r: &mut Renderer<'_>, levels: u8) -> Result<(), Error> {
+        r.lines(levels + self.indent.0, &self.text)
+    }
+}
+
+impl Render for CodeLine {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        self.render_at(r, 0)
     }
 }
 
+display_via_render!(Impl, Function, CodeLine);
+with_preamble!(Function);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -245,4 +354,39 @@ mod tests {
 
         assert_eq!("    pub fn test<'a>() {}\n", generic_fn.to_string());
     }
+
+    #[test]
+    fn fn_with_docs_and_attributes() {
+        let i = Impl::new("Test")
+            .add_function(Function::new("test")
+                .with_docs("Does a test.\n\nReally.")
+                .add_attribute("inline"));
+
+        assert_eq!("impl Test {\n    /// Does a test.\n    ///\n    /// Really.\n    #[inline]\n    pub fn test() {}\n}\n", i.to_string());
+    }
+
+    #[test]
+    fn long_parameters_are_broken() {
+        let f = Function::new("area")
+            .with_parameters("width: f32, height: f32, f: impl Fn(f32, f32) -> f32")
+            .with_return("f32".to_string())
+            .add_line(CodeLine::new(0, "f(width, height)"));
+
+        assert_eq!(
+            "    pub fn area(\n        width: f32,\n        height: f32,\n        f: impl Fn(f32, f32) -> f32,\n    ) -> f32 {\n        f(width, height)\n    }\n",
+            Style::new().with_max_width(60).render(&f)
+        );
+    }
+
+    #[test]
+    fn long_generics_are_broken() {
+        let f = Function::new("convert")
+            .with_generics(Generics::two("TInput: Into<TOutput>", "TOutput"))
+            .with_parameters("input: TInput");
+
+        assert_eq!(
+            "    pub fn convert<\n        TInput: Into<TOutput>,\n        TOutput,\n    >(\n        input: TInput,\n    ) {\n    }\n",
+            Style::new().with_max_width(40).render(&f)
+        );
+    }
 }
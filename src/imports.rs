@@ -0,0 +1,635 @@
+use crate::*;
+use crate::generics::split_top_level;
+use crate::render::code_only;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+/// A `use` declaration, such as `use std::collections::{HashMap, HashSet};`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Use {
+    pub visibility: Visibility,
+    pub tree: UseTree,
+}
+
+impl Use {
+    pub fn new(tree: &str) -> Self {
+        Self::try_new(tree).unwrap()
+    }
+
+    pub fn try_new(tree: &str) -> Result<Self, CodeGenError> {
+        Ok(Use {
+            visibility: Visibility::Private,
+            tree: tree.parse()?,
+        })
+    }
+
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
+
+    /// The full paths brought into scope, e.g. `std::fmt::Display` for `use std::fmt::{self, Display};`.
+    /// Globs are not included.
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths = vec![];
+        self.tree.collect_paths("", &mut paths);
+        paths
+    }
+
+    /// The names brought into scope, taking renames into account.
+    pub fn names(&self) -> Vec<String> {
+        let mut names = vec![];
+        self.tree.collect_names(None, &mut names);
+        names
+    }
+}
+
+impl Display for Use {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        writeln!(f, "{}use {};", self.visibility, self.tree)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum UseTree {
+    /// `Name` or `self`
+    Name(String),
+    /// `Name as Alias`
+    Rename(String, String),
+    /// `*`
+    Glob,
+    /// `segment::tree`
+    Path(String, Box<UseTree>),
+    /// `{tree, tree}`
+    Group(Vec<UseTree>),
+}
+
+impl UseTree {
+    fn collect_paths(&self, prefix: &str, paths: &mut Vec<String>) {
+        let join = |name: &str| match prefix {
+            "" => name.to_string(),
+            _ if name == "self" => prefix.to_string(),
+            _ => format!("{}::{}", prefix, name),
+        };
+
+        match self {
+            UseTree::Name(name) | UseTree::Rename(name, _) => paths.push(join(name)),
+            UseTree::Glob => {},
+            UseTree::Path(segment, tree) => tree.collect_paths(&join(segment), paths),
+            UseTree::Group(trees) => trees.iter().for_each(|t| t.collect_paths(prefix, paths)),
+        }
+    }
+
+    fn collect_names(&self, parent: Option<&str>, names: &mut Vec<String>) {
+        match self {
+            UseTree::Name(name) if name == "self" => names.extend(parent.map(String::from)),
+            UseTree::Name(name) => names.push(name.clone()),
+            UseTree::Rename(_, alias) => names.push(alias.clone()),
+            UseTree::Glob => {},
+            UseTree::Path(segment, tree) => tree.collect_names(Some(segment), names),
+            UseTree::Group(trees) => trees.iter().for_each(|t| t.collect_names(parent, names)),
+        }
+    }
+}
+
+impl Display for UseTree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            UseTree::Name(name) => write!(f, "{}", name),
+            UseTree::Rename(name, alias) => write!(f, "{} as {}", name, alias),
+            UseTree::Glob => write!(f, "*"),
+            UseTree::Path(segment, tree) => write!(f, "{}::{}", segment, tree),
+            UseTree::Group(trees) => write!(f, "{}", StrConcat {
+                iter: trees,
+                left_bound: "{",
+                right_bound: "}",
+                item_prepend: "",
+                item_append: "",
+                join: ", "
+            }),
+        }
+    }
+}
+
+impl FromStr for UseTree {
+    type Err = CodeGenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if s.is_empty() {
+            return Err(CodeGenError::Empty { kind: InputKind::UseTree });
+        }
+
+        if let Some(inner) = s.strip_prefix('{') {
+            let inner = inner.strip_suffix('}')
+                .ok_or_else(|| CodeGenError::syntax(InputKind::UseTree, s, "groups must end with '}'"))?;
+
+            return split_top_level(inner, ',')
+                .into_iter()
+                .filter(|t| !t.trim().is_empty())
+                .map(UseTree::from_str)
+                .collect::<Result<Vec<_>, _>>()
+                .map(UseTree::Group);
+        }
+
+        let group_start = s.find('{').unwrap_or(s.len());
+
+        if let Some(i) = s[..group_start].find("::") {
+            return Ok(UseTree::Path(s[..i].to_string(), Box::new(s[i + 2..].parse()?)));
+        }
+
+        if s == "*" {
+            return Ok(UseTree::Glob);
+        }
+
+        let parts: Vec<&str> = s.split_whitespace().collect();
+
+        match parts.as_slice() {
+            [name] => Ok(UseTree::Name(name.to_string())),
+            [name, "as", alias] => Ok(UseTree::Rename(name.to_string(), alias.to_string())),
+            _ => Err(CodeGenError::syntax(InputKind::UseTree, s, "must be a path, a name, a rename, a glob or a group")),
+        }
+    }
+}
+
+/// Maps the names used in generated code to the paths they are imported from.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct SymbolTable(BTreeMap<String, String>);
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Standard library traits and types that are not in the prelude.
+    pub fn std() -> Self {
+        let mut table = Self::new();
+
+        for std_trait in StdTrait::ALL.iter().filter(|t| !t.in_prelude()) {
+            table = table.with_path(&std_trait.path());
+        }
+
+        for path in [
+            "std::collections::HashMap",
+            "std::collections::HashSet",
+            "std::collections::BTreeMap",
+            "std::collections::BTreeSet",
+            "std::collections::VecDeque",
+            "std::rc::Rc",
+            "std::sync::Arc",
+            "std::sync::Mutex",
+            "std::sync::RwLock",
+            "std::cell::Cell",
+            "std::cell::RefCell",
+            "std::marker::PhantomData",
+            "std::time::Duration",
+            "std::time::Instant",
+            "std::fmt::Formatter",
+            "std::hash::Hasher",
+            "std::cmp::Ordering",
+        ].iter() {
+            table = table.with_path(path);
+        }
+
+        table
+    }
+
+    /// Adds a path, which is looked up by its last segment.
+    pub fn with_path(mut self, path: &str) -> Self {
+        let name = path.rsplit("::").next().unwrap_or(path);
+        self.0.insert(name.to_string(), path.to_string());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+}
+
+/// How collected imports are merged into `use` declarations.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ImportGranularity {
+    /// One `use` per path: `use std::fmt::Debug;`
+    Item,
+    /// One `use` per module: `use std::fmt::{Debug, Display};`
+    #[default]
+    Module,
+    /// One `use` per crate: `use std::{collections::HashMap, fmt::Debug};`
+    Crate,
+}
+
+/// A deduplicated set of imports, rendered as sorted `use` declarations in std, external and crate groups.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Imports {
+    paths: BTreeSet<String>,
+    pub granularity: ImportGranularity,
+}
+
+impl Imports {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_granularity(mut self, granularity: ImportGranularity) -> Self {
+        self.granularity = granularity;
+        self
+    }
+
+    pub fn insert(&mut self, path: &str) {
+        self.paths.insert(path.to_string());
+    }
+
+    pub fn extend(&mut self, other: &Imports) {
+        self.paths.extend(other.paths.iter().cloned());
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item=&str> {
+        self.paths.iter().map(String::as_str)
+    }
+
+    /// Finds the imports needed by the items of `module`, looking names up in `table`.
+    ///
+    /// Names defined or already imported in the module are skipped. Nested modules are separate
+    /// scopes and are not included.
+    pub fn collect(module: &Mod, table: &SymbolTable) -> Self {
+        let mut names = BTreeSet::new();
+        let mut imports = Imports::new();
+
+        for item in module.items.iter() {
+            item_references(item, &mut names, &mut imports);
+        }
+
+        let defined = module.defined_names();
+
+        for name in names.iter().filter(|n| !defined.contains(*n)) {
+            if let Some(path) = table.get(name) {
+                imports.insert(path);
+            }
+        }
+
+        imports.paths.retain(|path| {
+            let name = path.rsplit("::").next().unwrap_or(path);
+            !defined.contains(name)
+        });
+
+        imports
+    }
+
+    /// The `use` declarations, in groups: std, external crates, then the current crate.
+    pub fn groups(&self) -> Vec<Vec<Use>> {
+        let mut groups: [Vec<&str>; 3] = Default::default();
+
+        for path in self.paths.iter() {
+            let root = path.split("::").next().unwrap_or(path);
+            let group = match root {
+                "std" | "core" | "alloc" => 0,
+                "crate" | "self" | "super" => 2,
+                _ => 1,
+            };
+            groups[group].push(path);
+        }
+
+        groups.iter()
+            .filter(|g| !g.is_empty())
+            .map(|paths| self.merge(paths))
+            .collect()
+    }
+
+    fn merge(&self, paths: &[&str]) -> Vec<Use> {
+        let mut uses: Vec<UseTree> = match self.granularity {
+            ImportGranularity::Item => paths.iter()
+                .map(|p| UseTree::from_str(p).unwrap())
+                .collect(),
+            ImportGranularity::Module => {
+                let mut modules: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+                for path in paths.iter() {
+                    let parent = path.rsplit_once("::").map_or("", |(parent, _)| parent);
+                    modules.entry(parent).or_default().push(path);
+                }
+                modules.values().flat_map(|paths| UseNode::from_paths(paths).trees()).collect()
+            },
+            ImportGranularity::Crate => UseNode::from_paths(paths).trees(),
+        };
+
+        uses.sort_by(compare_trees);
+
+        uses.into_iter()
+            .map(|tree| Use { visibility: Visibility::Private, tree })
+            .collect()
+    }
+}
+
+impl Display for Imports {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        for (i, group) in self.groups().iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            for u in group.iter() {
+                write!(f, "{}", u)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A trie of path segments, used to merge paths into `UseTree`s.
+#[derive(Debug, Default)]
+struct UseNode {
+    imported: bool,
+    children: BTreeMap<String, UseNode>,
+}
+
+impl UseNode {
+    fn from_paths(paths: &[&str]) -> Self {
+        let mut root = UseNode::default();
+
+        for path in paths.iter() {
+            let mut node = &mut root;
+            for segment in path.split("::") {
+                node = node.children.entry(segment.to_string()).or_default();
+            }
+            node.imported = true;
+        }
+
+        root
+    }
+
+    /// The trees of the root's children, which are the individual `use` declarations.
+    fn trees(&self) -> Vec<UseTree> {
+        self.children.iter().map(|(name, node)| node.tree(name)).collect()
+    }
+
+    fn tree(&self, name: &str) -> UseTree {
+        let mut children: Vec<UseTree> = self.children.iter()
+            .map(|(name, node)| node.tree(name))
+            .collect();
+
+        if children.is_empty() {
+            return UseTree::Name(name.to_string());
+        }
+
+        if self.imported {
+            children.push(UseTree::Name("self".to_string()));
+        }
+
+        children.sort_by(compare_trees);
+
+        let tree = match children.len() {
+            1 => children.remove(0),
+            _ => UseTree::Group(children),
+        };
+
+        UseTree::Path(name.to_string(), Box::new(tree))
+    }
+}
+
+/// Orders trees the way rustfmt does: `self`, `super` and `crate` first, then snake_case,
+/// CamelCase and SCREAMING_SNAKE_CASE names, with globs and groups last.
+fn compare_trees(a: &UseTree, b: &UseTree) -> Ordering {
+    fn key(tree: &UseTree) -> (u8, u8, &str) {
+        let name = match tree {
+            UseTree::Name(name) | UseTree::Rename(name, _) | UseTree::Path(name, _) => name.as_str(),
+            UseTree::Glob => return (5, 0, ""),
+            UseTree::Group(_) => return (6, 0, ""),
+        };
+
+        let kind = match name {
+            "self" => 0,
+            "super" => 1,
+            "crate" => 2,
+            _ if name.starts_with(char::is_lowercase) => 3,
+            _ if name.chars().all(|c| !c.is_lowercase()) && name.len() > 1 => 4,
+            _ => 3,
+        };
+
+        let case = match name.starts_with(char::is_uppercase) {
+            true => 1,
+            false => 0,
+        };
+
+        (kind, case, name)
+    }
+
+    key(a).cmp(&key(b)).then_with(|| a.to_string().cmp(&b.to_string()))
+}
+
+/// Records the names referenced by `item` and the imports its derives need.
+fn item_references(item: &Item, names: &mut BTreeSet<String>, imports: &mut Imports) {
+    match item {
+        Item::Struct(s) => {
+            scan_names(&s.typ.to_string(), names);
+            for field in s.fields.iter() {
+                scan_names(&field.field_type.to_string(), names);
+            }
+            s.derives.imports().iter().for_each(|p| imports.insert(p));
+        },
+        Item::Enum(e) => {
+            scan_names(&e.typ.to_string(), names);
+            for option in e.options.iter() {
+                option.option_types.iter().for_each(|t| scan_names(t, names));
+            }
+            e.derives.imports().iter().for_each(|p| imports.insert(p));
+        },
+        Item::Impl(i) => scan_names(&i.to_string(), names),
+        Item::TraitImpl(i) => scan_names(&i.to_string(), names),
+        Item::Trait(t) => scan_names(&t.to_string(), names),
+        Item::Raw(code) => scan_names(code, names),
+        Item::Union(u) => {
+            for field in u.fields.iter() {
+                scan_names(&field.field_type.to_string(), names);
+            }
+            u.derives.imports().iter().for_each(|p| imports.insert(p));
+        },
+        Item::Function(_) | Item::Const(_) | Item::Static(_) | Item::TypeAlias(_) | Item::MacroRules(_) | Item::MacroCall(_) | Item::ExternBlock(_) => {
+            scan_names(&item.to_string(), names)
+        },
+        Item::Mod(_) | Item::Use(_) | Item::Imports(_) => {},
+    }
+}
+
+/// Collects the identifiers in `code` that begin a path, i.e. are not preceded by `::` or `.`,
+/// skipping string and char literals, lifetimes and comments.
+pub(crate) fn scan_names(code: &str, names: &mut BTreeSet<String>) {
+    let chars: Vec<char> = code_only(code).chars().collect();
+    let mut i = 0;
+    let mut previous: Option<char> = None;
+    let mut before_previous: Option<char> = None;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '\'' {
+            // a char literal that is not ASCII, such as 'é', otherwise a lifetime
+            let end = if chars.get(i + 1) == Some(&'\\') { i + 3 } else { i + 2 };
+            if chars.get(end) == Some(&'\'') {
+                i = end + 1;
+            } else {
+                i += 1;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+            }
+            previous = Some('\'');
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' || c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+
+            let is_path_head = !(previous == Some(':') && before_previous == Some(':')) && previous != Some('.');
+
+            if is_path_head && !c.is_ascii_digit() {
+                names.insert(chars[start..i].iter().collect());
+            }
+
+            before_previous = previous;
+            previous = Some('a');
+            continue;
+        }
+
+        if !c.is_whitespace() {
+            before_previous = previous;
+            previous = Some(c);
+        }
+        i += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn use_tree_round_trip() {
+        for tree in ["std::fmt::Display", "std::collections::{HashMap, HashSet}", "std::io::{self, Write as _}", "crate::prelude::*", "a::{b::{C, D}, e::F}"].iter() {
+            assert_eq!(*tree, UseTree::from_str(tree).unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn use_display() {
+        assert_eq!("use std::fmt::Display;\n", Use::new("std::fmt::Display").to_string());
+        assert_eq!("pub use crate::units::*;\n", Use::new("crate::units::*").with_visibility(Visibility::Pub).to_string());
+    }
+
+    #[test]
+    fn use_paths_and_names() {
+        let u = Use::new("std::io::{self, Write as W, Read}");
+
+        assert_eq!(vec!["std::io", "std::io::Write", "std::io::Read"], u.paths());
+        assert_eq!(vec!["io", "W", "Read"], u.names());
+    }
+
+    #[test]
+    fn invalid_use_tree() {
+        assert!(UseTree::from_str("").is_err());
+        assert!(UseTree::from_str("a::{b").is_err());
+        assert!(UseTree::from_str("a b c").is_err());
+    }
+
+    #[test]
+    fn scan_names_skips_literals_and_paths() {
+        let mut names = BTreeSet::new();
+        scan_names("let m: HashMap<&'a str, Vec<u8>> = std::collections::HashMap::new(); x.Ordering; \"Rc\" // Arc", &mut names);
+        scan_names("r#\"Cell \"RefCell\"\"#; /* Mutex /* Weak */ Box */ 'é'; Pin", &mut names);
+
+        assert!(names.contains("HashMap"));
+        assert!(names.contains("Vec"));
+        assert!(names.contains("std"));
+        assert!(!names.contains("collections"));
+        assert!(!names.contains("new"));
+        assert!(!names.contains("Ordering"));
+        assert!(!names.contains("Rc"));
+        assert!(!names.contains("Arc"));
+        assert!(!names.contains("a"));
+        assert!(names.contains("Pin"));
+
+        for name in ["Cell", "RefCell", "Mutex", "Weak", "Box"].iter() {
+            assert!(!names.contains(*name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn imports_grouped_and_merged_by_module() {
+        let mut imports = Imports::new();
+        for path in ["serde::Serialize", "std::fmt::Display", "crate::units::Meters", "std::collections::HashMap", "std::fmt::Debug", "std::collections::HashMap"].iter() {
+            imports.insert(path);
+        }
+
+        assert_eq!(
+            "use std::collections::HashMap;\nuse std::fmt::{Debug, Display};\n\nuse serde::Serialize;\n\nuse crate::units::Meters;\n",
+            imports.to_string()
+        );
+    }
+
+    #[test]
+    fn imports_merged_by_crate() {
+        let mut imports = Imports::new().with_granularity(ImportGranularity::Crate);
+        for path in ["std::fmt", "std::fmt::Display", "std::collections::HashMap"].iter() {
+            imports.insert(path);
+        }
+
+        assert_eq!("use std::{collections::HashMap, fmt::{self, Display}};\n", imports.to_string());
+    }
+
+    #[test]
+    fn snake_case_sorts_before_camel_case() {
+        let mut imports = Imports::new().with_granularity(ImportGranularity::Crate);
+        for path in ["a::Zeta", "a::MAX", "a::beta"].iter() {
+            imports.insert(path);
+        }
+
+        assert_eq!("use a::{beta, Zeta, MAX};\n", imports.to_string());
+    }
+
+    #[test]
+    fn collect_from_module() {
+        let mut derives = Derives::with_debug();
+        derives.insert(Derive::imported("serde::Serialize"));
+
+        let m = Mod::new("test")
+            .add_item(Struct::new("Cache")
+                .with_derives(derives)
+                .add_field(Field::new("values", "HashMap<u32, Meters>"))
+                .add_field(Field::new("local", "Local")))
+            .add_item(Struct::new("Local"))
+            .add_item(StdTrait::Display.impl_for(&Type::new("Local"))
+                .add_function(TraitFunction::new("fmt")
+                    .with_parameters("&self, f: &mut Formatter<'_>")
+                    .with_return("std::fmt::Result")
+                    .add_line(CodeLine::new(0, "write!(f, \"Local\")"))));
+
+        let table = SymbolTable::std()
+            .with_path("crate::units::Meters")
+            .with_path("crate::other::Local");
+
+        assert_eq!(
+            "use std::collections::HashMap;\nuse std::fmt::{Display, Formatter};\n\nuse serde::Serialize;\n\nuse crate::units::Meters;\n",
+            Imports::collect(&m, &table).to_string()
+        );
+    }
+
+    #[test]
+    fn already_imported_names_are_skipped() {
+        let m = Mod::new("test")
+            .add_item(Use::new("std::collections::HashMap"))
+            .add_item(Struct::new("Cache").add_field(Field::new("values", "HashMap<u32, u32>")));
+
+        assert!(Imports::collect(&m, &SymbolTable::std()).is_empty());
+    }
+}
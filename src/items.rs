@@ -0,0 +1,237 @@
+use crate::*;
+use crate::render::display_via_render;
+
+/// The doc comments and outer attributes rendered before an item.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Preamble<'a> {
+    pub(crate) levels: u8,
+    pub(crate) docs: &'a [String],
+    pub(crate) attributes: &'a [String],
+}
+
+impl Render for Preamble<'_> {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        let indent = r.indent(self.levels);
+
+        for doc in self.docs.iter() {
+            match doc.is_empty() {
+                true => writeln!(r, "{}///", indent)?,
+                false => writeln!(r, "{}/// {}", indent, doc)?,
+            };
+        }
+
+        for attribute in self.attributes.iter() {
+            writeln!(r, "{}#[{}]", indent, attribute)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Adds the inherent `with_docs` and `add_attribute` builders to types with `docs` and `attributes` fields.
+macro_rules! with_preamble {
+    ($($typ:ty),* $(,)?) => {
+        $(
+            impl $typ {
+                /// Adds doc comment lines, one `///` line per line of `docs`.
+                pub fn with_docs(mut self, docs: &str) -> Self {
+                    self.docs.extend(docs.lines().map(String::from));
+                    self
+                }
+
+                /// Adds an outer attribute, given without the surrounding `#[...]`.
+                pub fn add_attribute(mut self, attribute: &str) -> Self {
+                    self.attributes.push(attribute.to_string());
+                    self
+                }
+            }
+        )*
+    };
+}
+
+pub(crate) use with_preamble;
+
+/// A `const NAME: Type = value;` item.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Const {
+    pub name: ScreamingSnakeCase,
+    pub visibility: Visibility,
+    pub typ: Type,
+    pub value: String,
+    pub docs: Vec<String>,
+    pub attributes: Vec<String>,
+}
+
+impl Const {
+    pub fn new(name: &str, typ: &str, value: &str) -> Self {
+        Self::try_new(name, typ, value).unwrap()
+    }
+
+    pub fn try_new(name: &str, typ: &str, value: &str) -> Result<Self, CodeGenError> {
+        Ok(Const {
+            name: name.parse()?,
+            visibility: Visibility::Pub,
+            typ: typ.parse()?,
+            value: value.to_string(),
+            docs: vec![],
+            attributes: vec![],
+        })
+    }
+
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
+}
+
+impl Render for Const {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        Preamble { levels: 0, docs: &self.docs, attributes: &self.attributes }.render(r)?;
+        writeln!(r, "{}{}const {}: {} = {};", r.indent(0), self.visibility, self.name, self.typ, self.value)
+    }
+}
+
+/// A `static NAME: Type = value;` or `static mut` item.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Static {
+    pub name: ScreamingSnakeCase,
+    pub visibility: Visibility,
+    pub mutable: bool,
+    pub typ: Type,
+    pub value: String,
+    pub docs: Vec<String>,
+    pub attributes: Vec<String>,
+}
+
+impl Static {
+    pub fn new(name: &str, typ: &str, value: &str) -> Self {
+        Self::try_new(name, typ, value).unwrap()
+    }
+
+    pub fn try_new(name: &str, typ: &str, value: &str) -> Result<Self, CodeGenError> {
+        Ok(Static {
+            name: name.parse()?,
+            visibility: Visibility::Pub,
+            mutable: false,
+            typ: typ.parse()?,
+            value: value.to_string(),
+            docs: vec![],
+            attributes: vec![],
+        })
+    }
+
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
+
+    pub fn as_mut(mut self) -> Self {
+        self.mutable = true;
+        self
+    }
+}
+
+impl Render for Static {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        let mutability = match self.mutable {
+            true => "mut ",
+            false => "",
+        };
+
+        Preamble { levels: 0, docs: &self.docs, attributes: &self.attributes }.render(r)?;
+        writeln!(r, "{}{}static {}{}: {} = {};", r.indent(0), self.visibility, mutability, self.name, self.typ, self.value)
+    }
+}
+
+/// A `type Alias<T> = Target<T>;` item.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TypeAlias {
+    pub alias: Type,
+    pub visibility: Visibility,
+    pub target: Type,
+    pub docs: Vec<String>,
+    pub attributes: Vec<String>,
+}
+
+impl TypeAlias {
+    pub fn new(alias: &str, target: &str) -> Self {
+        Self::try_new(alias, target).unwrap()
+    }
+
+    pub fn try_new(alias: &str, target: &str) -> Result<Self, CodeGenError> {
+        Ok(TypeAlias {
+            alias: alias.parse()?,
+            visibility: Visibility::Pub,
+            target: target.parse()?,
+            docs: vec![],
+            attributes: vec![],
+        })
+    }
+
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
+}
+
+impl Render for TypeAlias {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        Preamble { levels: 0, docs: &self.docs, attributes: &self.attributes }.render(r)?;
+        writeln!(r, "{}{}type {} = {};", r.indent(0), self.visibility, self.alias, self.target)
+    }
+}
+
+display_via_render!(Const, Static, TypeAlias);
+with_preamble!(Const, Static, TypeAlias);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn const_item() {
+        let c = Const::new("MAX_SPEED", "f64", "299_792_458.0")
+            .with_docs("The speed of light, in m/s.");
+
+        assert_eq!("/// The speed of light, in m/s.\npub const MAX_SPEED: f64 = 299_792_458.0;\n", c.to_string());
+    }
+
+    #[test]
+    fn const_name_must_be_screaming_snake_case() {
+        assert!(std::panic::catch_unwind(|| Const::new("max_speed", "f64", "1.0")).is_err());
+    }
+
+    #[test]
+    fn static_items() {
+        let s = Static::new("NAMES", "&[&str]", "&[\"a\", \"b\"]").with_visibility(Visibility::Private);
+        let m = Static::new("COUNTER", "u32", "0").as_mut().add_attribute("no_mangle");
+
+        assert_eq!("static NAMES: &[&str] = &[\"a\", \"b\"];\n", s.to_string());
+        assert_eq!("#[no_mangle]\npub static mut COUNTER: u32 = 0;\n", m.to_string());
+    }
+
+    #[test]
+    fn type_alias() {
+        let t = TypeAlias::new("Map<V>", "std::collections::HashMap<String, V>")
+            .with_visibility(Visibility::PubCrate)
+            .add_attribute("allow(dead_code)");
+
+        assert_eq!("#[allow(dead_code)]\npub (crate) type Map<V> = std::collections::HashMap<String, V>;\n", t.to_string());
+    }
+
+    #[test]
+    fn items_in_mod() {
+        let m = Mod::new("consts")
+            .add_item(Const::new("A", "u32", "1"))
+            .add_item(Function::new("double")
+                .with_docs("Doubles `x`.")
+                .with_parameters("x: u32")
+                .with_return("u32".to_string())
+                .add_line(CodeLine::new(0, "x * A * 2")));
+
+        assert_eq!(
+            "pub mod consts {\n    pub const A: u32 = 1;\n\n    /// Doubles `x`.\n    pub fn double(x: u32) -> u32 {\n        x * A * 2\n    }\n}\n",
+            m.to_string()
+        );
+    }
+}
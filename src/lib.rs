@@ -1,5 +1,8 @@
 use std::fmt::{Display, Formatter, Error};
 
+mod render;
+mod error;
+mod identifiers;
 mod formatting;
 mod visibility;
 mod types;
@@ -10,6 +13,21 @@ mod derives;
 mod impls;
 mod traits;
 mod mods;
+mod std_traits;
+mod operators;
+mod manual_impls;
+mod derive_validation;
+mod imports;
+mod emitter;
+mod diff;
+mod check;
+mod build_script;
+mod source_file;
+mod items;
+mod macros;
+mod ffi;
+mod statements;
+mod block;
 
 pub use structs::*;
 pub use enums::*;
@@ -20,4 +38,22 @@ pub use derives::*;
 pub use generics::*;
 pub use types::*;
 pub use traits::*;
-pub use mods::*;
\ No newline at end of file
+pub use mods::*;
+pub use std_traits::*;
+pub use operators::*;
+pub use manual_impls::*;
+pub use derive_validation::*;
+pub use imports::*;
+pub use emitter::*;
+pub use diff::*;
+pub use check::*;
+pub use build_script::*;
+pub use source_file::*;
+pub use items::*;
+pub use macros::*;
+pub use ffi::*;
+pub use statements::*;
+pub use block::*;
+pub use render::*;
+pub use error::*;
+pub use identifiers::*;
\ No newline at end of file
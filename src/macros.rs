@@ -0,0 +1,354 @@
+use crate::*;
+use crate::items::Preamble;
+use crate::render::{display_via_render, literal_aware_lines};
+
+/// A `macro_rules!` definition.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct MacroRules {
+    pub name: SnakeCase,
+    pub exported: bool,
+    pub arms: Vec<MacroArm>,
+    pub docs: Vec<String>,
+    pub attributes: Vec<String>,
+}
+
+impl MacroRules {
+    pub fn new(name: &str) -> Self {
+        Self::try_new(name).unwrap()
+    }
+
+    pub fn try_new(name: &str) -> Result<Self, CodeGenError> {
+        Ok(MacroRules {
+            name: name.parse()?,
+            exported: false,
+            arms: vec![],
+            docs: vec![],
+            attributes: vec![],
+        })
+    }
+
+    /// Adds `#[macro_export]`.
+    pub fn exported(mut self) -> Self {
+        self.exported = true;
+        self
+    }
+
+    pub fn add_arm(mut self, arm: MacroArm) -> Self {
+        self.arms.push(arm);
+        self
+    }
+
+    /// Adds doc comment lines, one `///` line per line of `docs`.
+    pub fn with_docs(mut self, docs: &str) -> Self {
+        self.docs.extend(docs.lines().map(String::from));
+        self
+    }
+
+    pub fn add_attribute(mut self, attribute: &str) -> Self {
+        self.attributes.push(attribute.to_string());
+        self
+    }
+}
+
+impl Render for MacroRules {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        Preamble { levels: 0, docs: &self.docs, attributes: &self.attributes }.render(r)?;
+
+        if self.exported {
+            writeln!(r, "{}#[macro_export]", r.indent(0))?;
+        }
+
+        write!(r, "{}macro_rules! {} {{", r.indent(0), self.name)?;
+
+        if self.arms.is_empty() {
+            r.empty_body(0, false)?;
+            return writeln!(r);
+        }
+
+        writeln!(r)?;
+
+        for arm in self.arms.iter() {
+            r.render(arm)?;
+        }
+
+        writeln!(r, "{}}}", r.indent(0))
+    }
+}
+
+/// A `(matcher) => { transcriber };` arm of a `macro_rules!`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct MacroArm {
+    /// The matcher without its outer parentheses, e.g. `$name:ident, $value:expr`.
+    pub matcher: String,
+    pub lines: Vec<CodeLine>,
+}
+
+impl MacroArm {
+    pub fn new(matcher: &str) -> Self {
+        MacroArm {
+            matcher: matcher.to_string(),
+            lines: vec![],
+        }
+    }
+
+    pub fn add_line(mut self, line: CodeLine) -> Self {
+        self.lines.push(line);
+        self
+    }
+
+    /// Adds the lines of `transcriber`, keeping their relative indentation. Common leading whitespace is
+    /// removed, so the transcriber can be written as an indented raw string.
+    pub fn with_transcriber(mut self, transcriber: &str) -> Self {
+        self.lines.extend(dedent(transcriber));
+        self
+    }
+}
+
+impl Render for MacroArm {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        let indent = r.indent(1);
+
+        if self.lines.is_empty() {
+            return writeln!(r, "{}({}) => {{}};", indent, self.matcher);
+        }
+
+        writeln!(r, "{}({}) => {{", indent, self.matcher)?;
+
+        for line in self.lines.iter() {
+            if !line.text.is_empty() {
+                line.render_at(r, 1)?;
+            }
+            writeln!(r)?;
+        }
+
+        writeln!(r, "{}}};", indent)
+    }
+}
+
+/// Splits `text` into `CodeLine`s, converting leading whitespace beyond the common indentation into
+/// indentation levels. A level is as wide as the smallest indentation beyond the common one, so text indented
+/// by two spaces keeps its nesting, and whitespace left over is kept as spaces. A tab counts as four spaces.
+/// Leading and trailing blank lines are dropped. Lines that continue a multi-line string literal are kept as
+/// they are, as part of the line the literal starts in.
+pub(crate) fn dedent(text: &str) -> Vec<CodeLine> {
+    let lines: Vec<(&str, bool)> = literal_aware_lines(text)
+        .map(|(line, in_literal)| (line.strip_suffix('\n').unwrap_or(line), in_literal))
+        .collect();
+    let blank = |(line, in_literal): &(&str, bool)| !in_literal && line.trim().is_empty();
+    let start = lines.iter().position(|l| !blank(l)).unwrap_or(lines.len());
+    let end = lines.iter().rposition(|l| !blank(l)).map_or(start, |i| i + 1);
+    let lines = &lines[start..end];
+
+    let width = |line: &str| -> usize {
+        line.chars()
+            .take_while(|c| c.is_whitespace())
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum()
+    };
+
+    let common = lines.iter()
+        .filter(|l| !blank(l) && !l.1)
+        .map(|(l, _)| width(l))
+        .min()
+        .unwrap_or(0);
+    let unit = lines.iter()
+        .filter(|l| !blank(l) && !l.1)
+        .map(|(l, _)| width(l) - common)
+        .filter(|w| *w > 0)
+        .min()
+        .unwrap_or(4);
+
+    let mut code_lines: Vec<CodeLine> = vec![];
+
+    for (line, in_literal) in lines.iter() {
+        match (in_literal, code_lines.last_mut()) {
+            (true, Some(last)) => {
+                last.text.push('\n');
+                last.text.push_str(line);
+            },
+            _ if line.trim().is_empty() => code_lines.push(CodeLine { indent: Indent(0), text: String::new() }),
+            _ => {
+                let relative = width(line) - common;
+                let text = format!("{:width$}{}", "", line.trim_start(), width = relative % unit);
+                code_lines.push(CodeLine::new((relative / unit) as u8, &text));
+            },
+        }
+    }
+
+    code_lines
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum MacroDelimiter {
+    Paren,
+    Bracket,
+    Brace,
+}
+
+/// A macro invoked at item position, such as `lazy_static! { ... }` or `thread_local!(...);`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct MacroCall {
+    /// The path of the macro, without the `!`.
+    pub path: String,
+    pub delimiter: MacroDelimiter,
+    /// Tokens on the same line as the macro name, used when there are no lines.
+    pub args: String,
+    pub lines: Vec<CodeLine>,
+}
+
+impl MacroCall {
+    pub fn new(path: &str) -> Self {
+        MacroCall {
+            path: path.trim_end_matches('!').to_string(),
+            delimiter: MacroDelimiter::Brace,
+            args: String::new(),
+            lines: vec![],
+        }
+    }
+
+    pub fn with_delimiter(mut self, delimiter: MacroDelimiter) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn with_args(mut self, args: &str) -> Self {
+        self.args = args.to_string();
+        self
+    }
+
+    pub fn add_line(mut self, line: CodeLine) -> Self {
+        self.lines.push(line);
+        self
+    }
+
+    /// Adds the lines of `body`, keeping their relative indentation, as for `MacroArm::with_transcriber`.
+    pub fn with_body(mut self, body: &str) -> Self {
+        self.lines.extend(dedent(body));
+        self
+    }
+}
+
+impl Render for MacroCall {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        let (open, close) = match self.delimiter {
+            MacroDelimiter::Paren => ("(", ");"),
+            MacroDelimiter::Bracket => ("[", "];"),
+            MacroDelimiter::Brace => (" {", "}"),
+        };
+
+        if self.lines.is_empty() {
+            return match (self.delimiter, self.args.is_empty()) {
+                (MacroDelimiter::Brace, false) => writeln!(r, "{}{}! {{ {} }}", r.indent(0), self.path, self.args),
+                _ => writeln!(r, "{}{}!{}{}{}", r.indent(0), self.path, open, self.args, close),
+            };
+        }
+
+        writeln!(r, "{}{}!{}", r.indent(0), self.path, open)?;
+
+        for line in self.lines.iter() {
+            if !line.text.is_empty() {
+                r.render(line)?;
+            }
+            writeln!(r)?;
+        }
+
+        writeln!(r, "{}{}", r.indent(0), close)
+    }
+}
+
+display_via_render!(MacroRules, MacroArm, MacroCall);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn macro_rules() {
+        let m = MacroRules::new("component")
+            .exported()
+            .with_docs("Declares a component.")
+            .add_arm(MacroArm::new("$name:ident")
+                .add_line(CodeLine::new(0, "pub struct $name;")))
+            .add_arm(MacroArm::new(""));
+
+        assert_eq!(
+            "/// Declares a component.\n#[macro_export]\nmacro_rules! component {\n    ($name:ident) => {\n        pub struct $name;\n    };\n    () => {};\n}\n",
+            m.to_string()
+        );
+    }
+
+    #[test]
+    fn transcriber_is_reindented() {
+        let arm = MacroArm::new("$($name:ident),*").with_transcriber("
+                $(
+                    impl Component for $name {}
+
+                    impl $name {}
+                )*
+        ");
+
+        assert_eq!(
+            "    ($($name:ident),*) => {\n        $(\n            impl Component for $name {}\n\n            impl $name {}\n        )*\n    };\n",
+            arm.to_string()
+        );
+    }
+
+    #[test]
+    fn transcriber_indented_by_two_spaces() {
+        let arm = MacroArm::new("$name:ident").with_transcriber("
+          impl $name {
+            fn get(&self)
+               -> u8 {
+              0
+            }
+          }
+        ");
+
+        assert_eq!(
+            "    ($name:ident) => {\n        impl $name {\n            fn get(&self)\n                 -> u8 {\n                0\n            }\n        }\n    };\n",
+            arm.to_string()
+        );
+    }
+
+    #[test]
+    fn macro_call_with_body() {
+        let call = MacroCall::new("lazy_static!").with_body("
+            static ref NAMES: Vec<&'static str> = {
+                vec![\"a\"]
+            };
+        ");
+
+        assert_eq!(
+            "lazy_static! {\n    static ref NAMES: Vec<&'static str> = {\n        vec![\"a\"]\n    };\n}\n",
+            call.to_string()
+        );
+    }
+
+    #[test]
+    fn macro_call_on_one_line() {
+        assert_eq!("component!(Position);\n", MacroCall::new("component").with_delimiter(MacroDelimiter::Paren).with_args("Position").to_string());
+        assert_eq!("component! { Position }\n", MacroCall::new("component").with_args("Position").to_string());
+    }
+
+    #[test]
+    fn macros_in_mod() {
+        let m = Mod::new("components")
+            .add_item(MacroRules::new("component").add_arm(MacroArm::new("$name:ident").with_transcriber("pub struct $name;")))
+            .add_item(MacroCall::new("component").with_delimiter(MacroDelimiter::Paren).with_args("Position"));
+
+        assert_eq!(
+            "pub mod components {\n    macro_rules! component {\n        ($name:ident) => {\n            pub struct $name;\n        };\n    }\n\n    component!(Position);\n}\n",
+            m.to_string()
+        );
+    }
+
+    #[test]
+    fn transcriber_literals_are_kept() {
+        let arm = MacroArm::new("").with_transcriber("
+            concat!(r\"a
+b\", \"c\")
+        ");
+
+        assert_eq!("    () => {\n        concat!(r\"a\nb\", \"c\")\n    };\n", arm.to_string());
+    }
+}
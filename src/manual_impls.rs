@@ -0,0 +1,726 @@
+use crate::*;
+use crate::identifiers::is_ident_continue;
+use std::str::FromStr;
+
+/// Generates a trait impl by hand from a `Struct` or `Enum`, for when `#[derive(...)]` would
+/// look at fields that must be ignored, such as caches, `PhantomData` or raw pointers.
+pub trait ManualImpl {
+    fn for_struct(&self, strct: &Struct) -> TraitImpl;
+    fn for_enum(&self, enm: &Enum) -> TraitImpl;
+    /// The paths that must be imported for the impl and its bounds to resolve, e.g. `std::hash::Hash`.
+    fn imports(&self) -> Vec<String>;
+}
+
+/// Chooses the fields a manual impl looks at.
+///
+/// Struct fields are referred to by name, enum variant fields by `Variant.index`.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct FieldFilter {
+    /// If set, only these fields are used, in this order.
+    pub only: Option<Vec<String>>,
+    pub skip: Vec<String>,
+    pub skip_types: Vec<TypeName>,
+}
+
+impl FieldFilter {
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    pub fn only(fields: &[&str]) -> Self {
+        FieldFilter {
+            only: Some(fields.iter().map(|f| f.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    pub fn skip(mut self, field: &str) -> Self {
+        self.skip.push(field.to_string());
+        self
+    }
+
+    /// Skips every field of the given type, e.g. `"PhantomData"`.
+    pub fn skip_type(mut self, type_name: &str) -> Self {
+        self.skip_types.push(TypeName::new(type_name));
+        self
+    }
+
+    fn includes(&self, key: &str, type_name: Option<&TypeName>) -> bool {
+        let selected = match &self.only {
+            Some(only) => only.iter().any(|f| f == key),
+            None => true,
+        };
+
+        let skipped = self.skip.iter().any(|f| f == key)
+            || type_name.is_some_and(|t| self.skip_types.contains(t));
+
+        selected && !skipped
+    }
+
+    fn struct_fields<'a>(&self, fields: &'a [Field]) -> Vec<&'a Field> {
+        let mut selected: Vec<&Field> = fields.iter()
+            .filter(|f| self.includes(f.name.unescaped(), Some(&f.field_type.name)))
+            .collect();
+
+        if let Some(only) = &self.only {
+            selected.sort_by_key(|f| only.iter().position(|o| o == f.name.unescaped()));
+        }
+
+        selected
+    }
+
+    /// The types of the used fields of every variant of `enm`.
+    fn enum_field_types(&self, enm: &Enum) -> Vec<String> {
+        enm.options.iter()
+            .flat_map(|option| self.variant_fields(option).into_iter().map(move |i| option.option_types[i].clone()))
+            .collect()
+    }
+
+    /// The indices of the fields of `option` that are used, in order.
+    fn variant_fields(&self, option: &EnumOption) -> Vec<usize> {
+        let key = |i: usize| format!("{}.{}", option.name, i);
+
+        let mut selected: Vec<usize> = option.option_types.iter()
+            .enumerate()
+            .filter(|(i, ty)| {
+                let type_name = Type::from_str(ty).ok().map(|t| t.name);
+                self.includes(&key(*i), type_name.as_ref())
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        if let Some(only) = &self.only {
+            selected.sort_by_key(|i| only.iter().position(|o| *o == key(*i)));
+        }
+
+        selected
+    }
+}
+
+/// `Debug` via `debug_struct`/`debug_tuple`, with skipped and redacted fields.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct ManualDebug {
+    pub fields: FieldFilter,
+    /// Fields that are printed as `<redacted>` instead of their value.
+    pub redacted: Vec<String>,
+}
+
+impl ManualDebug {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_fields(mut self, fields: FieldFilter) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    pub fn redact(mut self, field: &str) -> Self {
+        self.redacted.push(field.to_string());
+        self
+    }
+
+    fn is_redacted(&self, key: &str) -> bool {
+        self.redacted.iter().any(|r| r == key)
+    }
+
+    fn function() -> TraitFunction {
+        TraitFunction::new("fmt")
+            .with_parameters("&self, f: &mut std::fmt::Formatter<'_>")
+            .with_return("std::fmt::Result")
+    }
+}
+
+impl ManualImpl for ManualDebug {
+    fn for_struct(&self, strct: &Struct) -> TraitImpl {
+        let name = &strct.typ.name;
+        let fields = self.fields.struct_fields(&strct.fields);
+        let mut function = Self::function();
+
+        if strct.fields.is_empty() {
+            function = function.add_line(CodeLine::new(0, &format!("f.write_str(\"{}\")", name)));
+        } else {
+            function = function.add_line(CodeLine::new(0, &format!("f.debug_struct(\"{}\")", name)));
+
+            for field in fields.iter() {
+                let value = if self.is_redacted(field.name.unescaped()) {
+                    "&\"<redacted>\"".to_string()
+                } else {
+                    format!("&self.{}", field.name)
+                };
+
+                function = function.add_line(CodeLine::new(1, &format!(".field(\"{}\", {})", field.name.unescaped(), value)));
+            }
+
+            let finish = if fields.len() < strct.fields.len() { ".finish_non_exhaustive()" } else { ".finish()" };
+            function = function.add_line(CodeLine::new(1, finish));
+        }
+
+        bounded_impl(StdTrait::Debug, &strct.typ, &field_types(&fields)).add_function(function)
+    }
+
+    fn for_enum(&self, enm: &Enum) -> TraitImpl {
+        let arms = enm.options.iter()
+            .map(|option| {
+                if option.option_types.is_empty() {
+                    return format!("Self::{0} => f.write_str(\"{0}\"),", option.name);
+                }
+
+                let used = self.fields.variant_fields(option);
+                let bindings = bindings(option, &used, "v");
+
+                let fields: String = used.iter()
+                    .map(|i| {
+                        if self.is_redacted(&format!("{}.{}", option.name, i)) {
+                            ".field(&\"<redacted>\")".to_string()
+                        } else {
+                            format!(".field(v{})", i)
+                        }
+                    })
+                    .collect();
+
+                let finish = if used.len() < option.option_types.len() { "finish_non_exhaustive" } else { "finish" };
+                format!("Self::{0}({1}) => f.debug_tuple(\"{0}\"){2}.{3}(),", option.name, bindings, fields, finish)
+            })
+            .collect();
+
+        bounded_impl(StdTrait::Debug, &enm.typ, &self.fields.enum_field_types(enm))
+            .add_function(match_lines(Self::function(), "self", arms))
+    }
+
+    fn imports(&self) -> Vec<String> {
+        trait_imports(StdTrait::Debug)
+    }
+}
+
+/// `PartialEq` over a subset of fields.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct ManualPartialEq {
+    pub fields: FieldFilter,
+}
+
+impl ManualPartialEq {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_fields(mut self, fields: FieldFilter) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    fn function() -> TraitFunction {
+        TraitFunction::new("eq")
+            .with_parameters("&self, other: &Self")
+            .with_return("bool")
+    }
+}
+
+impl ManualImpl for ManualPartialEq {
+    fn for_struct(&self, strct: &Struct) -> TraitImpl {
+        let fields = self.fields.struct_fields(&strct.fields);
+        let mut function = Self::function();
+
+        if fields.is_empty() {
+            function = function.add_line(CodeLine::new(0, "true"));
+        }
+
+        for (i, field) in fields.iter().enumerate() {
+            function = match i {
+                0 => function.add_line(CodeLine::new(0, &format!("self.{0} == other.{0}", field.name))),
+                _ => function.add_line(CodeLine::new(1, &format!("&& self.{0} == other.{0}", field.name))),
+            };
+        }
+
+        bounded_impl(StdTrait::PartialEq, &strct.typ, &field_types(&fields)).add_function(function)
+    }
+
+    fn for_enum(&self, enm: &Enum) -> TraitImpl {
+        let mut arms: Vec<String> = enm.options.iter()
+            .map(|option| {
+                let used = self.fields.variant_fields(option);
+
+                let comparison = if used.is_empty() {
+                    "true".to_string()
+                } else {
+                    used.iter()
+                        .map(|i| format!("a{0} == b{0}", i))
+                        .collect::<Vec<_>>()
+                        .join(" && ")
+                };
+
+                format!("({}, {}) => {},", pattern(option, &used, "a"), pattern(option, &used, "b"), comparison)
+            })
+            .collect();
+
+        if enm.options.len() > 1 {
+            arms.push("_ => false,".to_string());
+        }
+
+        bounded_impl(StdTrait::PartialEq, &enm.typ, &self.fields.enum_field_types(enm))
+            .add_function(match_lines(Self::function(), "(self, other)", arms))
+    }
+
+    fn imports(&self) -> Vec<String> {
+        trait_imports(StdTrait::PartialEq)
+    }
+}
+
+/// `Hash` over a subset of fields. Should be paired with a `ManualPartialEq` over the same fields.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct ManualHash {
+    pub fields: FieldFilter,
+}
+
+impl ManualHash {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_fields(mut self, fields: FieldFilter) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    fn function() -> TraitFunction {
+        TraitFunction::new("hash")
+            .with_generics(Generics::one("H: std::hash::Hasher"))
+            .with_parameters("&self, state: &mut H")
+    }
+}
+
+impl ManualImpl for ManualHash {
+    fn for_struct(&self, strct: &Struct) -> TraitImpl {
+        let fields = self.fields.struct_fields(&strct.fields);
+        let mut function = Self::function();
+
+        if fields.is_empty() {
+            function = function.add_line(CodeLine::new(0, "let _ = state;"));
+        }
+
+        for field in fields.iter() {
+            function = function.add_line(CodeLine::new(0, &format!("std::hash::Hash::hash(&self.{}, state);", field.name)));
+        }
+
+        bounded_impl(StdTrait::Hash, &strct.typ, &field_types(&fields)).add_function(function)
+    }
+
+    fn for_enum(&self, enm: &Enum) -> TraitImpl {
+        let mut function = Self::function();
+
+        if enm.options.is_empty() {
+            function = function.add_line(CodeLine::new(0, "match *self {}"));
+            return bounded_impl(StdTrait::Hash, &enm.typ, &[]).add_function(function);
+        }
+
+        function = function
+            .add_line(CodeLine::new(0, "std::hash::Hash::hash(&std::mem::discriminant(self), state);"))
+            .add_line(CodeLine::new(0, "match self {"));
+
+        for option in enm.options.iter() {
+            let used = self.fields.variant_fields(option);
+            let pattern = pattern(option, &used, "v");
+
+            function = match used.as_slice() {
+                [] => function.add_line(CodeLine::new(1, &format!("{} => {{}}", pattern))),
+                [i] => function.add_line(CodeLine::new(1, &format!("{} => std::hash::Hash::hash(v{}, state),", pattern, i))),
+                _ => {
+                    let mut function = function.add_line(CodeLine::new(1, &format!("{} => {{", pattern)));
+                    for i in used.iter() {
+                        function = function.add_line(CodeLine::new(2, &format!("std::hash::Hash::hash(v{}, state);", i)));
+                    }
+                    function.add_line(CodeLine::new(1, "}"))
+                },
+            };
+        }
+
+        function = function.add_line(CodeLine::new(0, "}"));
+
+        bounded_impl(StdTrait::Hash, &enm.typ, &self.fields.enum_field_types(enm)).add_function(function)
+    }
+
+    fn imports(&self) -> Vec<String> {
+        trait_imports(StdTrait::Hash)
+    }
+}
+
+/// `Ord` comparing a subset of fields in a chosen order; use `FieldFilter::only` to set the order.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct ManualOrd {
+    pub fields: FieldFilter,
+}
+
+impl ManualOrd {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_fields(mut self, fields: FieldFilter) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// A `PartialOrd` impl that defers to the `Ord` impl `ord`, with the same bounds.
+    pub fn partial_ord(ord: &TraitImpl) -> TraitImpl {
+        StdTrait::PartialOrd.impl_for(&ord.typ)
+            .with_impl_generics(ord.impl_generics.clone())
+            .add_function(TraitFunction::new("partial_cmp")
+                .with_parameters("&self, other: &Self")
+                .with_return("Option<std::cmp::Ordering>")
+                .add_line(CodeLine::new(0, "Some(std::cmp::Ord::cmp(self, other))")))
+    }
+
+    fn function() -> TraitFunction {
+        TraitFunction::new("cmp")
+            .with_parameters("&self, other: &Self")
+            .with_return("std::cmp::Ordering")
+    }
+}
+
+impl ManualImpl for ManualOrd {
+    fn for_struct(&self, strct: &Struct) -> TraitImpl {
+        let fields = self.fields.struct_fields(&strct.fields);
+        let mut function = Self::function();
+
+        if fields.is_empty() {
+            function = function.add_line(CodeLine::new(0, "std::cmp::Ordering::Equal"));
+        }
+
+        for (i, field) in fields.iter().enumerate() {
+            function = match i {
+                0 => function.add_line(CodeLine::new(0, &format!("self.{0}.cmp(&other.{0})", field.name))),
+                _ => function.add_line(CodeLine::new(1, &format!(".then_with(|| self.{0}.cmp(&other.{0}))", field.name))),
+            };
+        }
+
+        bounded_impl(StdTrait::Ord, &strct.typ, &field_types(&fields)).add_function(function)
+    }
+
+    fn for_enum(&self, enm: &Enum) -> TraitImpl {
+        let mut function = Self::function();
+
+        if enm.options.len() > 1 {
+            function = function.add_line(CodeLine::new(0, "let index = |value: &Self| match value {"));
+
+            for (i, option) in enm.options.iter().enumerate() {
+                let pattern = match option.option_types.is_empty() {
+                    true => format!("Self::{}", option.name),
+                    false => format!("Self::{}(..)", option.name),
+                };
+                function = function.add_line(CodeLine::new(1, &format!("{} => {},", pattern, i)));
+            }
+
+            function = function.add_line(CodeLine::new(0, "};"));
+        }
+
+        let mut arms: Vec<String> = enm.options.iter()
+            .map(|option| {
+                let used = self.fields.variant_fields(option);
+
+                let comparison = match used.split_first() {
+                    None => "std::cmp::Ordering::Equal".to_string(),
+                    Some((first, rest)) => {
+                        let mut comparison = format!("a{0}.cmp(b{0})", first);
+                        for i in rest {
+                            comparison.push_str(&format!(".then_with(|| a{0}.cmp(b{0}))", i));
+                        }
+                        comparison
+                    },
+                };
+
+                format!("({}, {}) => {},", pattern(option, &used, "a"), pattern(option, &used, "b"), comparison)
+            })
+            .collect();
+
+        if enm.options.len() > 1 {
+            arms.push("_ => index(self).cmp(&index(other)),".to_string());
+        }
+
+        bounded_impl(StdTrait::Ord, &enm.typ, &self.fields.enum_field_types(enm))
+            .add_function(match_lines(function, "(self, other)", arms))
+    }
+
+    fn imports(&self) -> Vec<String> {
+        trait_imports(StdTrait::Ord)
+    }
+}
+
+/// An impl of `std_trait` for `typ`, with the type parameters that appear in `field_types` bounded by the trait.
+/// Parameters only used by skipped fields, such as the `T` of a skipped `PhantomData<T>`, are left unbounded.
+fn bounded_impl(std_trait: StdTrait, typ: &Type, field_types: &[String]) -> TraitImpl {
+    let generics = typ.types.iter()
+        .map(|g| match g {
+            Generic::Type(t) if field_types.iter().any(|f| mentions(f, t.name.as_str())) => {
+                Generic::new(&format!("{}: {}", t, std_trait.name()))
+            },
+            g => g.clone(),
+        })
+        .collect();
+
+    std_trait.impl_for(typ).with_impl_generics(generics)
+}
+
+/// Whether the type parameter `param` appears in the type `typ`.
+fn mentions(typ: &str, param: &str) -> bool {
+    typ.split(|c: char| !is_ident_continue(c)).any(|word| word == param)
+}
+
+fn field_types(fields: &[&Field]) -> Vec<String> {
+    fields.iter().map(|f| f.field_type.to_string()).collect()
+}
+
+/// The import `std_trait` needs, unless it is in the prelude.
+fn trait_imports(std_trait: StdTrait) -> Vec<String> {
+    match std_trait.in_prelude() {
+        true => vec![],
+        false => vec![std_trait.path()],
+    }
+}
+
+/// Appends `match scrutinee { arms }` to `function`.
+fn match_lines(function: TraitFunction, scrutinee: &str, arms: Vec<String>) -> TraitFunction {
+    if arms.is_empty() {
+        return function.add_line(CodeLine::new(0, "match *self {}"));
+    }
+
+    let mut function = function.add_line(CodeLine::new(0, &format!("match {} {{", scrutinee)));
+
+    for arm in arms.iter() {
+        function = function.add_line(CodeLine::new(1, arm));
+    }
+
+    function.add_line(CodeLine::new(0, "}"))
+}
+
+/// The comma-separated bindings for a tuple variant, `_` for fields that are not used.
+fn bindings(option: &EnumOption, used: &[usize], prefix: &str) -> String {
+    (0..option.option_types.len())
+        .map(|i| if used.contains(&i) { format!("{}{}", prefix, i) } else { "_".to_string() })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn pattern(option: &EnumOption, used: &[usize], prefix: &str) -> String {
+    if option.option_types.is_empty() {
+        format!("Self::{}", option.name)
+    } else {
+        format!("Self::{}({})", option.name, bindings(option, used, prefix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cached() -> Struct {
+        Struct::new("Cached")
+            .add_field(Field::new("id", "u32"))
+            .add_field(Field::new("name", "String"))
+            .add_field(Field::new("cache", "Vec<u8>"))
+    }
+
+    fn shape() -> Enum {
+        Enum::new("Shape")
+            .add_option(EnumOption::new("Circle", vec!["u64", "PhantomData<u8>"]))
+            .add_option(EnumOption::new("Point", vec![]))
+    }
+
+    #[test]
+    fn debug_struct_skips_and_redacts() {
+        let i = ManualDebug::new()
+            .with_fields(FieldFilter::all().skip("cache"))
+            .redact("name")
+            .for_struct(&cached());
+
+        assert_eq!(
+r#"impl Debug for Cached {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cached")
+            .field("id", &self.id)
+            .field("name", &"<redacted>")
+            .finish_non_exhaustive()
+    }
+}
+"#,
+            i.to_string()
+        );
+    }
+
+    #[test]
+    fn debug_unit_struct() {
+        let i = ManualDebug::new().for_struct(&Struct::new("Unit"));
+
+        assert!(i.to_string().contains("        f.write_str(\"Unit\")\n"));
+    }
+
+    #[test]
+    fn debug_enum_skips_by_type() {
+        let i = ManualDebug::new()
+            .with_fields(FieldFilter::all().skip_type("PhantomData"))
+            .for_enum(&shape());
+
+        assert_eq!(
+r#"impl Debug for Shape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Circle(v0, _) => f.debug_tuple("Circle").field(v0).finish_non_exhaustive(),
+            Self::Point => f.write_str("Point"),
+        }
+    }
+}
+"#,
+            i.to_string()
+        );
+    }
+
+    #[test]
+    fn partial_eq_struct_subset() {
+        let i = ManualPartialEq::new()
+            .with_fields(FieldFilter::all().skip("cache"))
+            .for_struct(&cached());
+
+        assert_eq!(
+            "impl PartialEq for Cached {\n    fn eq(&self, other: &Self) -> bool {\n        self.id == other.id\n            && self.name == other.name\n    }\n}\n",
+            i.to_string()
+        );
+    }
+
+    #[test]
+    fn partial_eq_enum() {
+        let i = ManualPartialEq::new()
+            .with_fields(FieldFilter::all().skip("Circle.1"))
+            .for_enum(&shape());
+
+        assert_eq!(
+r#"impl PartialEq for Shape {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Circle(a0, _), Self::Circle(b0, _)) => a0 == b0,
+            (Self::Point, Self::Point) => true,
+            _ => false,
+        }
+    }
+}
+"#,
+            i.to_string()
+        );
+    }
+
+    #[test]
+    fn hash_struct_with_generics() {
+        let s = Struct::new("Handle<T>")
+            .add_field(Field::new("id", "u32"))
+            .add_field(Field::new("marker", "PhantomData<T>"));
+
+        let i = ManualHash::new()
+            .with_fields(FieldFilter::all().skip_type("PhantomData"))
+            .for_struct(&s);
+
+        assert_eq!(
+            "impl<T> Hash for Handle<T> {\n    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {\n        std::hash::Hash::hash(&self.id, state);\n    }\n}\n",
+            i.to_string()
+        );
+    }
+
+    #[test]
+    fn only_used_parameters_are_bounded() {
+        let s = Struct::new("Pair<K, V, M>")
+            .add_field(Field::new("key", "K"))
+            .add_field(Field::new("values", "Vec<V>"))
+            .add_field(Field::new("marker", "PhantomData<M>"));
+        let filter = FieldFilter::all().skip("marker");
+
+        let ord = ManualOrd::new().with_fields(filter.clone()).for_struct(&s);
+        assert!(ord.to_string().starts_with("impl<K: Ord, V: Ord, M> Ord for Pair<K, V, M> {\n"));
+        assert!(ManualOrd::partial_ord(&ord).to_string().starts_with("impl<K: Ord, V: Ord, M> PartialOrd for Pair<K, V, M> {\n"));
+
+        let e = Enum::new("Either<L, R>")
+            .add_option(EnumOption::new("Left", vec!["L"]))
+            .add_option(EnumOption::new("Right", vec!["PhantomData<R>"]));
+        let debug = ManualDebug::new().with_fields(FieldFilter::all().skip_type("PhantomData")).for_enum(&e);
+        assert!(debug.to_string().starts_with("impl<L: Debug, R> Debug for Either<L, R> {\n"));
+    }
+
+    #[test]
+    fn imports_for_traits_outside_the_prelude() {
+        assert_eq!(vec!["std::fmt::Debug".to_string()], ManualDebug::new().imports());
+        assert_eq!(vec!["std::hash::Hash".to_string()], ManualHash::new().imports());
+        assert!(ManualOrd::new().imports().is_empty());
+    }
+
+    #[test]
+    fn hash_enum() {
+        let i = ManualHash::new().for_enum(&shape());
+
+        assert_eq!(
+r#"impl Hash for Shape {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::hash::Hash::hash(&std::mem::discriminant(self), state);
+        match self {
+            Self::Circle(v0, v1) => {
+                std::hash::Hash::hash(v0, state);
+                std::hash::Hash::hash(v1, state);
+            }
+            Self::Point => {}
+        }
+    }
+}
+"#,
+            i.to_string()
+        );
+    }
+
+    #[test]
+    fn ord_struct_in_chosen_order() {
+        let i = ManualOrd::new()
+            .with_fields(FieldFilter::only(&["name", "id"]))
+            .for_struct(&cached());
+
+        assert_eq!(
+            "impl Ord for Cached {\n    fn cmp(&self, other: &Self) -> std::cmp::Ordering {\n        self.name.cmp(&other.name)\n            .then_with(|| self.id.cmp(&other.id))\n    }\n}\n",
+            i.to_string()
+        );
+    }
+
+    #[test]
+    fn ord_enum() {
+        let i = ManualOrd::new()
+            .with_fields(FieldFilter::all().skip_type("PhantomData"))
+            .for_enum(&shape());
+
+        assert_eq!(
+r#"impl Ord for Shape {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let index = |value: &Self| match value {
+            Self::Circle(..) => 0,
+            Self::Point => 1,
+        };
+        match (self, other) {
+            (Self::Circle(a0, _), Self::Circle(b0, _)) => a0.cmp(b0),
+            (Self::Point, Self::Point) => std::cmp::Ordering::Equal,
+            _ => index(self).cmp(&index(other)),
+        }
+    }
+}
+"#,
+            i.to_string()
+        );
+    }
+
+    #[test]
+    fn partial_ord_defers_to_ord() {
+        let i = ManualOrd::partial_ord(&ManualOrd::new().for_struct(&cached()));
+
+        assert_eq!(
+            "impl PartialOrd for Cached {\n    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {\n        Some(std::cmp::Ord::cmp(self, other))\n    }\n}\n",
+            i.to_string()
+        );
+    }
+
+    #[test]
+    fn debug_keyword_field() {
+        let i = ManualDebug::new().for_struct(&Struct::new("Token").add_field(Field::new("type", "u8")));
+
+        assert!(i.to_string().contains(".field(\"type\", &self.r#type)"));
+    }
+}
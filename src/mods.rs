@@ -1,51 +1,468 @@
-use std::fmt::{Display, Formatter, Error};
-use crate::{SnakeCase, Visibility, Indent};
+use std::fmt::Error;
+use std::collections::BTreeSet;
+use crate::render::display_via_render;
+use crate::{Render, Renderer, SnakeCase, Visibility, Struct, Enum, Impl, TraitImpl, Trait, Use, Imports, SymbolTable, Function, Const, Static, TypeAlias, MacroRules, MacroCall, Union, ExternBlock, CodeGenError};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Mod {
     pub name: SnakeCase,
     pub vis: Visibility,
-    pub body: String,
+    pub items: Vec<Item>,
 }
 
 impl Mod {
-    pub fn new(name: &str, body: String) -> Self {
-        Mod {
-            name: name.parse().unwrap(),
+    pub fn new(name: &str) -> Self {
+        Self::try_new(name).unwrap()
+    }
+
+    pub fn try_new(name: &str) -> Result<Self, CodeGenError> {
+        Ok(Mod {
+            name: name.parse()?,
             vis: Default::default(),
-            body
-        }
+            items: vec![],
+        })
     }
 
     pub fn with_visibility(mut self, vis: Visibility) -> Self {
         self.vis = vis;
         self
     }
+
+    pub fn add_item<I: Into<Item>>(mut self, item: I) -> Self {
+        self.items.push(item.into());
+        self
+    }
+
+    pub fn push<I: Into<Item>>(&mut self, item: I) {
+        self.items.push(item.into());
+    }
+
+    pub fn struct_mut(&mut self, name: &str) -> Option<&mut Struct> {
+        self.items.iter_mut().find_map(|item| match item {
+            Item::Struct(s) if s.typ.name.as_str() == name => Some(s),
+            _ => None,
+        })
+    }
+
+    pub fn enum_mut(&mut self, name: &str) -> Option<&mut Enum> {
+        self.items.iter_mut().find_map(|item| match item {
+            Item::Enum(e) if e.typ.name.as_str() == name => Some(e),
+            _ => None,
+        })
+    }
+
+    pub fn trait_mut(&mut self, name: &str) -> Option<&mut Trait> {
+        self.items.iter_mut().find_map(|item| match item {
+            Item::Trait(t) if t.name.as_str() == name => Some(t),
+            _ => None,
+        })
+    }
+
+    pub fn mod_mut(&mut self, name: &str) -> Option<&mut Mod> {
+        self.items.iter_mut().find_map(|item| match item {
+            Item::Mod(m) if m.name.as_str() == name => Some(m),
+            _ => None,
+        })
+    }
+
+    /// The names the module's items define or import, which need no import themselves.
+    pub fn defined_names(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+
+        for item in self.items.iter() {
+            match item {
+                Item::Struct(s) => { names.insert(s.typ.name.as_str().to_string()); },
+                Item::Enum(e) => { names.insert(e.typ.name.as_str().to_string()); },
+                Item::Trait(t) => { names.insert(t.name.as_str().to_string()); },
+                Item::Mod(m) => { names.insert(m.name.as_str().to_string()); },
+                Item::Function(func) => { names.insert(func.name.as_str().to_string()); },
+                Item::Const(c) => { names.insert(c.name.as_str().to_string()); },
+                Item::Static(s) => { names.insert(s.name.as_str().to_string()); },
+                Item::TypeAlias(t) => { names.insert(t.alias.name.as_str().to_string()); },
+                Item::MacroRules(m) => { names.insert(m.name.as_str().to_string()); },
+                Item::Union(u) => { names.insert(u.typ.name.as_str().to_string()); },
+                Item::ExternBlock(e) => names.extend(e.names().into_iter().map(String::from)),
+                Item::Use(u) => names.extend(u.names()),
+                Item::Imports(i) => names.extend(i.paths().filter_map(|p| p.rsplit("::").next()).map(String::from)),
+                Item::Impl(_) | Item::TraitImpl(_) | Item::MacroCall(_) | Item::Raw(_) => {},
+            }
+        }
+
+        names
+    }
+
+    /// The imports needed by this module's items; see `Imports::collect`.
+    pub fn collect_imports(&self, table: &SymbolTable) -> Imports {
+        Imports::collect(self, table)
+    }
+
+    /// Collects the imports of this module and all nested modules, and adds them to the top of each module.
+    /// New imports are merged into an existing `Imports` item.
+    pub fn add_imports(&mut self, table: &SymbolTable) {
+        let collected = self.collect_imports(table);
+
+        if !collected.is_empty() {
+            match self.items.iter_mut().find_map(|item| match item {
+                Item::Imports(i) => Some(i),
+                _ => None,
+            }) {
+                Some(imports) => imports.extend(&collected),
+                None => self.items.insert(0, Item::Imports(collected)),
+            }
+        }
+
+        for item in self.items.iter_mut() {
+            if let Item::Mod(m) = item {
+                m.add_imports(table);
+            }
+        }
+    }
+
+    /// The items of the module without the surrounding `mod name { ... }`, as rendered into its own file.
+    pub fn contents(&self) -> ModContents<'_> {
+        ModContents { items: &self.items, mod_declarations: false }
+    }
+
+    /// Like `contents`, but nested modules are rendered as `mod name;` declarations, for when they are
+    /// written to their own files.
+    pub fn file_contents(&self) -> ModContents<'_> {
+        ModContents { items: &self.items, mod_declarations: true }
+    }
 }
 
-impl Display for Mod {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        writeln!(f, "{}mod {} {{", self.vis, self.name).ok();
+impl Render for Mod {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        write!(r, "{}{}mod {} {{", r.indent(0), self.vis, self.name)?;
 
-        for line in self.body.lines() {
-            writeln!(f, "{}{}", Indent(1), line).ok();
+        if self.items.is_empty() {
+            r.empty_body(0, false)?;
+            return writeln!(r);
         }
 
-        writeln!(f, "}}")
+        writeln!(r)?;
+        r.indented(&self.contents(), 1)?;
+        writeln!(r, "{}}}", r.indent(0))
+    }
+}
+
+/// Renders the items of a `Mod`, separated by blank lines. Consecutive `use` and `mod` declarations are kept together.
+#[derive(Debug, Copy, Clone)]
+pub struct ModContents<'a> {
+    items: &'a [Item],
+    mod_declarations: bool,
+}
+
+impl<'a> ModContents<'a> {
+    pub(crate) fn new(items: &'a [Item]) -> Self {
+        ModContents { items, mod_declarations: false }
+    }
+}
+
+impl Render for ModContents<'_> {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        let items = self.items;
+
+        for (i, item) in items.iter().enumerate() {
+            let grouped = i != 0 && match (&items[i - 1], item) {
+                (Item::Use(_), Item::Use(_)) => true,
+                (Item::Mod(_), Item::Mod(_)) => self.mod_declarations,
+                _ => false,
+            };
+
+            if i != 0 && !grouped {
+                r.item_separator()?;
+            }
+
+            match item {
+                Item::Mod(m) if self.mod_declarations => writeln!(r, "{}{}mod {};", r.indent(0), m.vis, m.name)?,
+                _ => r.render(item)?,
+            };
+        }
+
+        Ok(())
+    }
+}
+
+/// An item that can be placed in a `Mod`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Item {
+    Struct(Struct),
+    Enum(Enum),
+    Impl(Impl),
+    TraitImpl(TraitImpl),
+    Trait(Trait),
+    Mod(Mod),
+    Function(Function),
+    Const(Const),
+    Static(Static),
+    TypeAlias(TypeAlias),
+    MacroRules(MacroRules),
+    MacroCall(MacroCall),
+    Union(Union),
+    ExternBlock(ExternBlock),
+    Use(Use),
+    Imports(Imports),
+    /// Pre-rendered code, inserted as is.
+    Raw(String),
+}
+
+impl Render for Item {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        match self {
+            Item::Struct(s) => r.render(s),
+            Item::Enum(e) => r.render(e),
+            Item::Impl(i) => r.render(i),
+            Item::TraitImpl(i) => r.render(i),
+            Item::Trait(t) => r.render(t),
+            Item::Mod(m) => r.render(m),
+            Item::Function(func) => func.render_at(r, 0),
+            Item::Const(c) => r.render(c),
+            Item::Static(s) => r.render(s),
+            Item::TypeAlias(t) => r.render(t),
+            Item::MacroRules(m) => r.render(m),
+            Item::MacroCall(m) => r.render(m),
+            Item::Union(u) => r.render(u),
+            Item::ExternBlock(e) => r.render(e),
+            Item::Use(u) => write!(r, "{}{}", r.indent(0), u),
+            Item::Imports(i) => r.lines(0, &i.to_string()),
+            Item::Raw(code) if code.ends_with('\n') => r.lines(0, code),
+            Item::Raw(code) => {
+                r.lines(0, code)?;
+                writeln!(r)
+            },
+        }
+    }
+}
+
+display_via_render!(Mod, ModContents<'_>, Item);
+
+impl From<Struct> for Item {
+    fn from(s: Struct) -> Self {
+        Item::Struct(s)
+    }
+}
+
+impl From<Enum> for Item {
+    fn from(e: Enum) -> Self {
+        Item::Enum(e)
+    }
+}
+
+impl From<Impl> for Item {
+    fn from(i: Impl) -> Self {
+        Item::Impl(i)
+    }
+}
+
+impl From<TraitImpl> for Item {
+    fn from(i: TraitImpl) -> Self {
+        Item::TraitImpl(i)
+    }
+}
+
+impl From<Trait> for Item {
+    fn from(t: Trait) -> Self {
+        Item::Trait(t)
+    }
+}
+
+impl From<Mod> for Item {
+    fn from(m: Mod) -> Self {
+        Item::Mod(m)
+    }
+}
+
+impl From<Function> for Item {
+    fn from(func: Function) -> Self {
+        Item::Function(func)
+    }
+}
+
+impl From<Const> for Item {
+    fn from(c: Const) -> Self {
+        Item::Const(c)
+    }
+}
+
+impl From<Static> for Item {
+    fn from(s: Static) -> Self {
+        Item::Static(s)
+    }
+}
+
+impl From<TypeAlias> for Item {
+    fn from(t: TypeAlias) -> Self {
+        Item::TypeAlias(t)
+    }
+}
+
+impl From<MacroRules> for Item {
+    fn from(m: MacroRules) -> Self {
+        Item::MacroRules(m)
+    }
+}
+
+impl From<MacroCall> for Item {
+    fn from(m: MacroCall) -> Self {
+        Item::MacroCall(m)
+    }
+}
+
+impl From<Union> for Item {
+    fn from(u: Union) -> Self {
+        Item::Union(u)
+    }
+}
+
+impl From<ExternBlock> for Item {
+    fn from(e: ExternBlock) -> Self {
+        Item::ExternBlock(e)
+    }
+}
+
+impl From<Use> for Item {
+    fn from(u: Use) -> Self {
+        Item::Use(u)
+    }
+}
+
+impl From<Imports> for Item {
+    fn from(i: Imports) -> Self {
+        Item::Imports(i)
+    }
+}
+
+impl From<String> for Item {
+    fn from(code: String) -> Self {
+        Item::Raw(code)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Struct, Field};
+    use crate::{Field, Derive, Function, CodeLine, Style};
 
     #[test]
     fn simple() {
         let s = Struct::new("Test").add_field(Field::new("value", "u32"));
-        let m = Mod::new("test_mod", s.to_string());
+        let m = Mod::new("test_mod").add_item(s);
 
         assert_eq!("pub mod test_mod {\n    pub struct Test {\n        pub value: u32,\n    }\n}\n", m.to_string());
     }
-}
 
+    #[test]
+    fn empty() {
+        assert_eq!("mod empty {}\n", Mod::new("empty").with_visibility(Visibility::Private).to_string());
+    }
+
+    #[test]
+    fn items_are_separated_by_blank_lines() {
+        let m = Mod::new("test_mod")
+            .add_item(Struct::new("Test"))
+            .add_item(Impl::new("Test")
+                .add_function(Function::new("new")
+                    .with_return("Self".to_string())
+                    .add_line(CodeLine::new(0, "Test"))));
+
+        assert_eq!(
+            "pub mod test_mod {\n    pub struct Test;\n\n    impl Test {\n        pub fn new() -> Self {\n            Test\n        }\n    }\n}\n",
+            m.to_string()
+        );
+    }
+
+    #[test]
+    fn nested_mods() {
+        let m = Mod::new("outer")
+            .add_item(Mod::new("inner").add_item(Struct::new("Test")));
+
+        assert_eq!("pub mod outer {\n    pub mod inner {\n        pub struct Test;\n    }\n}\n", m.to_string());
+    }
+
+    #[test]
+    fn derive_added_after_placement() {
+        let mut m = Mod::new("outer")
+            .add_item(Mod::new("inner").add_item(Struct::new("Test")));
+
+        m.mod_mut("inner")
+            .and_then(|inner| inner.struct_mut("Test"))
+            .unwrap()
+            .derives
+            .insert(Derive::Clone);
+
+        assert_eq!("pub mod outer {\n    pub mod inner {\n        #[derive(Clone)]\n        pub struct Test;\n    }\n}\n", m.to_string());
+    }
+
+    #[test]
+    fn contents_has_no_mod_wrapper() {
+        let m = Mod::new("root")
+            .add_item(Struct::new("A"))
+            .add_item("const X: u32 = 1;".to_string());
+
+        assert_eq!("pub struct A;\n\nconst X: u32 = 1;\n", m.contents().to_string());
+    }
+
+    #[test]
+    fn file_contents_declares_nested_mods() {
+        let m = Mod::new("root")
+            .add_item(Mod::new("a").add_item(Struct::new("A")))
+            .add_item(Mod::new("b").with_visibility(Visibility::Private))
+            .add_item(Struct::new("C"));
+
+        assert_eq!("pub mod a;\nmod b;\n\npub struct C;\n", m.file_contents().to_string());
+    }
+
+    #[test]
+    fn consecutive_uses_are_not_separated() {
+        let m = Mod::new("root")
+            .add_item(Use::new("std::fmt::Debug"))
+            .add_item(Use::new("std::rc::Rc"))
+            .add_item(Struct::new("A"));
+
+        assert_eq!("use std::fmt::Debug;\nuse std::rc::Rc;\n\npub struct A;\n", m.contents().to_string());
+    }
+
+    #[test]
+    fn imports_added_per_module() {
+        let mut m = Mod::new("outer")
+            .add_item(Struct::new("A").add_field(Field::new("a", "Rc<u32>")))
+            .add_item(Mod::new("inner")
+                .add_item(Struct::new("B").add_field(Field::new("b", "HashMap<u32, u32>"))));
+
+        m.add_imports(&SymbolTable::std());
+
+        assert_eq!(
+            "pub mod outer {\n    use std::rc::Rc;\n\n    pub struct A {\n        pub a: Rc<u32>,\n    }\n\n    pub mod inner {\n        use std::collections::HashMap;\n\n        pub struct B {\n            pub b: HashMap<u32, u32>,\n        }\n    }\n}\n",
+            m.to_string()
+        );
+    }
+
+    #[test]
+    fn nested_string_literals_are_not_reindented() {
+        let m = Mod::new("outer")
+            .add_item(Mod::new("inner")
+                .add_item(Impl::new("Help")
+                    .add_function(Function::new("text")
+                        .with_return("&'static str".to_string())
+                        .add_line(CodeLine::new(0, "r#\"usage:\n  help [\"topic\"]\n\"#")))
+                    .add_function(Function::new("escaped")
+                        .with_return("&'static str".to_string())
+                        .add_line(CodeLine::new(0, "\"a \\\"quoted\\\" line\nsecond line\"")))))
+            .add_item(Item::Raw("const RAW: &str = \"\nnot indented\";".to_string()));
+
+        assert_eq!(
+            "pub mod outer {\n    pub mod inner {\n        impl Help {\n            pub fn text() -> &'static str {\n                r#\"usage:\n  help [\"topic\"]\n\"#\n            }\n\n            pub fn escaped() -> &'static str {\n                \"a \\\"quoted\\\" line\nsecond line\"\n            }\n        }\n    }\n\n    const RAW: &str = \"\nnot indented\";\n}\n",
+            m.to_string()
+        );
+    }
+
+    #[test]
+    fn nested_items_follow_style() {
+        let m = Mod::new("outer").add_item(Mod::new("inner").add_item(Function::new("run").add_line(CodeLine::new(0, "go();"))));
+
+        assert_eq!(
+            "pub mod outer {\n\tpub mod inner {\n\t\tpub fn run() {\n\t\t\tgo();\n\t\t}\n\t}\n}\n",
+            Style::new().with_tabs().render(&m)
+        );
+    }
+}
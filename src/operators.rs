@@ -0,0 +1,360 @@
+use crate::*;
+use std::str::FromStr;
+
+/// Generates operator impls for newtypes over numeric types, such as `Meters(f64)`.
+///
+/// Every registered newtype gets `Add`, `Sub`, `Mul<Inner>`, `Div<Inner>`, `Neg`, the matching
+/// `*Assign` impls and `Sum`, with all reference-operand combinations. Operations between
+/// different types are declared as a table of cross ops: `"Meters / Seconds = MetersPerSecond"`.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct NewtypeOps {
+    pub newtypes: Vec<Newtype>,
+    pub cross_ops: Vec<CrossOp>,
+}
+
+impl NewtypeOps {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_newtype(mut self, typ: &str, inner: &str) -> Self {
+        self.newtypes.push(Newtype::new(typ, inner));
+        self
+    }
+
+    pub fn add_cross_op(mut self, op: &str) -> Self {
+        self.cross_ops.push(CrossOp::new(op));
+        self
+    }
+
+    pub fn add_cross_ops(mut self, ops: &[&str]) -> Self {
+        self.cross_ops.extend(ops.iter().map(|op| CrossOp::new(op)));
+        self
+    }
+
+    pub fn impls(&self) -> Vec<TraitImpl> {
+        let mut impls = vec![];
+
+        for newtype in self.newtypes.iter() {
+            impls.extend(self.newtype_impls(newtype));
+        }
+
+        for op in self.cross_ops.iter() {
+            impls.extend(self.binary_impls(op));
+        }
+
+        impls
+    }
+
+    fn newtype_impls(&self, newtype: &Newtype) -> Vec<TraitImpl> {
+        let typ = &newtype.typ;
+        let inner = &newtype.inner;
+
+        let mut impls = vec![];
+
+        for (op, rhs) in [(BinaryOp::Add, typ), (BinaryOp::Sub, typ), (BinaryOp::Mul, inner), (BinaryOp::Div, inner)].iter() {
+            impls.extend(self.binary_impls(&CrossOp {
+                op: *op,
+                lhs: typ.clone(),
+                rhs: (*rhs).clone(),
+                output: typ.clone(),
+            }));
+        }
+
+        for lhs in [typ.clone(), reference(typ)].iter() {
+            impls.push(StdTrait::Neg.impl_for(lhs)
+                .add_associated_type(TypeName::new("Output"), typ.clone())
+                .add_function(TraitFunction::new("neg")
+                    .with_parameters("self")
+                    .with_return("Self::Output")
+                    .add_line(CodeLine::new(0, &format!("{}(-self.0)", typ)))));
+        }
+
+        for (op, rhs) in [(BinaryOp::Add, typ), (BinaryOp::Sub, typ), (BinaryOp::Mul, inner), (BinaryOp::Div, inner)].iter() {
+            let operand = if *rhs == typ { "rhs.0" } else { "rhs" };
+
+            for rhs in [(*rhs).clone(), reference(rhs)].iter() {
+                impls.push(op.assign_trait().impl_for(typ)
+                    .with_trait_generics(Generics::one(&rhs.to_string()))
+                    .add_function(TraitFunction::new(&op.assign_trait().method_name())
+                        .with_parameters(&format!("&mut self, rhs: {}", rhs))
+                        .add_line(CodeLine::new(0, &format!("self.0 {}= {};", op.symbol(), operand)))));
+            }
+        }
+
+        let sum_body = format!("{}(iter.map(|v| v.0).sum())", typ);
+
+        impls.push(StdTrait::Sum.impl_for(typ)
+            .add_function(TraitFunction::new("sum")
+                .with_generics(Generics::one("I: Iterator<Item = Self>"))
+                .with_parameters("iter: I")
+                .with_return("Self")
+                .add_line(CodeLine::new(0, &sum_body))));
+
+        impls.push(StdTrait::Sum.impl_for(typ)
+            .with_impl_generics(Generics::one("'a"))
+            .with_trait_generics(Generics::one(&format!("&'a {}", typ)))
+            .add_function(TraitFunction::new("sum")
+                .with_generics(Generics::one(&format!("I: Iterator<Item = &'a {}>", typ)))
+                .with_parameters("iter: I")
+                .with_return("Self")
+                .add_line(CodeLine::new(0, &sum_body))));
+
+        impls
+    }
+
+    /// The by-value impl of `op` followed by its `&lhs op rhs`, `lhs op &rhs` and `&lhs op &rhs` variants.
+    fn binary_impls(&self, op: &CrossOp) -> Vec<TraitImpl> {
+        let lhs_operand = self.operand("self", &op.lhs);
+        let rhs_operand = self.operand("rhs", &op.rhs);
+
+        let body = if self.is_newtype(&op.output) {
+            format!("{}({} {} {})", op.output, lhs_operand, op.op.symbol(), rhs_operand)
+        } else {
+            format!("{} {} {}", lhs_operand, op.op.symbol(), rhs_operand)
+        };
+
+        let lhs_types = [op.lhs.clone(), reference(&op.lhs)];
+        let rhs_types = [op.rhs.clone(), reference(&op.rhs)];
+
+        lhs_types.iter()
+            .flat_map(|lhs| rhs_types.iter().map(move |rhs| (lhs, rhs)))
+            .map(|(lhs, rhs)| {
+                op.op.std_trait().impl_for(lhs)
+                    .with_trait_generics(Generics::one(&rhs.to_string()))
+                    .add_associated_type(TypeName::new("Output"), op.output.clone())
+                    .add_function(TraitFunction::new(&op.op.std_trait().method_name())
+                        .with_parameters(&format!("self, rhs: {}", rhs))
+                        .with_return("Self::Output")
+                        .add_line(CodeLine::new(0, &body)))
+            })
+            .collect()
+    }
+
+    fn operand(&self, name: &str, typ: &Type) -> String {
+        if self.is_newtype(typ) {
+            format!("{}.0", name)
+        } else {
+            name.to_string()
+        }
+    }
+
+    fn is_newtype(&self, typ: &Type) -> bool {
+        self.newtypes.iter().any(|n| n.typ == *typ)
+    }
+}
+
+fn reference(typ: &Type) -> Type {
+    Type::new(&format!("&{}", typ))
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Newtype {
+    pub typ: Type,
+    pub inner: Type,
+}
+
+impl Newtype {
+    pub fn new(typ: &str, inner: &str) -> Self {
+        Newtype {
+            typ: Type::new(typ),
+            inner: Type::new(inner),
+        }
+    }
+}
+
+/// A binary operation between two types, written as `"Meters * Meters = SquareMeters"`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct CrossOp {
+    pub op: BinaryOp,
+    pub lhs: Type,
+    pub rhs: Type,
+    pub output: Type,
+}
+
+impl CrossOp {
+    pub fn new(op: &str) -> Self {
+        op.parse().unwrap()
+    }
+
+    pub fn try_new(op: &str) -> Result<Self, CodeGenError> {
+        op.parse()
+    }
+}
+
+impl FromStr for CrossOp {
+    type Err = CodeGenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+
+        match parts.as_slice() {
+            [lhs, op, rhs, "=", output] => Ok(CrossOp {
+                op: op.parse()?,
+                lhs: lhs.parse()?,
+                rhs: rhs.parse()?,
+                output: output.parse()?,
+            }),
+            _ => Err(CodeGenError::syntax(InputKind::CrossOp, s, "must be of the form 'Lhs * Rhs = Output'")),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+        }
+    }
+
+    pub fn std_trait(self) -> StdTrait {
+        match self {
+            BinaryOp::Add => StdTrait::Add,
+            BinaryOp::Sub => StdTrait::Sub,
+            BinaryOp::Mul => StdTrait::Mul,
+            BinaryOp::Div => StdTrait::Div,
+            BinaryOp::Rem => StdTrait::Rem,
+        }
+    }
+
+    pub fn assign_trait(self) -> StdTrait {
+        match self {
+            BinaryOp::Add => StdTrait::AddAssign,
+            BinaryOp::Sub => StdTrait::SubAssign,
+            BinaryOp::Mul => StdTrait::MulAssign,
+            BinaryOp::Div => StdTrait::DivAssign,
+            BinaryOp::Rem => StdTrait::RemAssign,
+        }
+    }
+}
+
+impl FromStr for BinaryOp {
+    type Err = CodeGenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "+" => Ok(BinaryOp::Add),
+            "-" => Ok(BinaryOp::Sub),
+            "*" => Ok(BinaryOp::Mul),
+            "/" => Ok(BinaryOp::Div),
+            "%" => Ok(BinaryOp::Rem),
+            _ => Err(CodeGenError::syntax(InputKind::BinaryOp, s, "must be one of + - * / %")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meters() -> NewtypeOps {
+        NewtypeOps::new().add_newtype("Meters", "f64")
+    }
+
+    fn find<'a>(impls: &'a [TraitImpl], header: &str) -> &'a TraitImpl {
+        impls.iter()
+            .find(|i| i.to_string().starts_with(header))
+            .unwrap_or_else(|| panic!("missing impl: {}", header))
+    }
+
+    #[test]
+    fn newtype_add() {
+        let impls = meters().impls();
+
+        assert_eq!(
+            "impl Add<Meters> for Meters {\n    type Output = Meters;\n\n    fn add(self, rhs: Meters) -> Self::Output {\n        Meters(self.0 + rhs.0)\n    }\n}\n",
+            find(&impls, "impl Add<Meters> for Meters").to_string()
+        );
+    }
+
+    #[test]
+    fn newtype_reference_operands() {
+        let impls = meters().impls();
+
+        find(&impls, "impl Add<&Meters> for Meters ");
+        find(&impls, "impl Add<Meters> for &Meters ");
+        find(&impls, "impl Sub<&Meters> for &Meters ");
+        find(&impls, "impl Neg for &Meters ");
+    }
+
+    #[test]
+    fn newtype_scalar_mul() {
+        let impls = meters().impls();
+
+        assert_eq!(
+            "impl Mul<&f64> for &Meters {\n    type Output = Meters;\n\n    fn mul(self, rhs: &f64) -> Self::Output {\n        Meters(self.0 * rhs)\n    }\n}\n",
+            find(&impls, "impl Mul<&f64> for &Meters").to_string()
+        );
+    }
+
+    #[test]
+    fn newtype_assign() {
+        let impls = meters().impls();
+
+        assert_eq!(
+            "impl DivAssign<f64> for Meters {\n    fn div_assign(&mut self, rhs: f64) {\n        self.0 /= rhs;\n    }\n}\n",
+            find(&impls, "impl DivAssign<f64> for Meters").to_string()
+        );
+        find(&impls, "impl AddAssign<&Meters> for Meters");
+    }
+
+    #[test]
+    fn newtype_sum_of_references() {
+        let impls = meters().impls();
+
+        assert_eq!(
+            "impl<'a> Sum<&'a Meters> for Meters {\n    fn sum<I: Iterator<Item = &'a Meters>>(iter: I) -> Self {\n        Meters(iter.map(|v| v.0).sum())\n    }\n}\n",
+            find(&impls, "impl<'a> Sum<&'a Meters> for Meters").to_string()
+        );
+    }
+
+    #[test]
+    fn cross_op_between_newtypes() {
+        let impls = meters()
+            .add_newtype("Seconds", "f64")
+            .add_newtype("MetersPerSecond", "f64")
+            .add_cross_op("Meters / Seconds = MetersPerSecond")
+            .impls();
+
+        assert_eq!(
+            "impl Div<Seconds> for Meters {\n    type Output = MetersPerSecond;\n\n    fn div(self, rhs: Seconds) -> Self::Output {\n        MetersPerSecond(self.0 / rhs.0)\n    }\n}\n",
+            find(&impls, "impl Div<Seconds> for Meters").to_string()
+        );
+        find(&impls, "impl Div<&Seconds> for &Meters");
+    }
+
+    #[test]
+    fn cross_op_to_scalar() {
+        let impls = meters()
+            .add_cross_ops(&["Meters / Meters = f64", "f64 * Meters = Meters"])
+            .impls();
+
+        assert!(find(&impls, "impl Div<Meters> for Meters").to_string().contains("        self.0 / rhs.0\n"));
+        assert!(find(&impls, "impl Mul<Meters> for f64").to_string().contains("        Meters(self * rhs.0)\n"));
+    }
+
+    #[test]
+    fn impl_count() {
+        // 4 binary ops * 4 operand combinations + 2 neg + 4 assign ops * 2 + 2 sum
+        assert_eq!(28, meters().impls().len());
+    }
+
+    #[test]
+    fn invalid_cross_op() {
+        assert!("Meters * Meters".parse::<CrossOp>().is_err());
+        assert!("Meters ^ Meters = Meters".parse::<CrossOp>().is_err());
+    }
+}
@@ -0,0 +1,777 @@
+use std::fmt::{self, Display, Formatter, Error};
+use std::io;
+use crate::{CodeGenError, Generics, StrConcat};
+
+/// How an empty body is closed.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum EmptyBody {
+    /// `{}`, or `{\n}` after a `where` clause, as rustfmt does.
+    #[default]
+    Inline,
+    /// `{\n}`
+    Open,
+}
+
+/// Formatting options for rendering. The default produces the same output as `Display`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Style {
+    /// Spaces per indentation level, when not using tabs.
+    pub indent_width: u8,
+    pub tabs: bool,
+    /// Whether the last entry of a multi-line list (fields, variants, `where` predicates) has a comma.
+    pub trailing_commas: bool,
+    /// Blank lines between items in modules and files, and between functions in impl blocks.
+    pub blank_lines: u8,
+    pub empty_body: EmptyBody,
+    /// The width lists are broken at, counting indentation. Tabs count as `indent_width` columns.
+    pub max_width: usize,
+}
+
+impl Style {
+    pub const DEFAULT: Style = Style {
+        indent_width: 4,
+        tabs: false,
+        trailing_commas: true,
+        blank_lines: 1,
+        empty_body: EmptyBody::Inline,
+        max_width: 100,
+    };
+
+    pub fn new() -> Self {
+        Self::DEFAULT
+    }
+
+    pub fn with_indent_width(mut self, width: u8) -> Self {
+        self.indent_width = width;
+        self
+    }
+
+    pub fn with_tabs(mut self) -> Self {
+        self.tabs = true;
+        self
+    }
+
+    pub fn without_trailing_commas(mut self) -> Self {
+        self.trailing_commas = false;
+        self
+    }
+
+    pub fn with_blank_lines(mut self, blank_lines: u8) -> Self {
+        self.blank_lines = blank_lines;
+        self
+    }
+
+    pub fn with_empty_body(mut self, empty_body: EmptyBody) -> Self {
+        self.empty_body = empty_body;
+        self
+    }
+
+    pub fn with_max_width(mut self, max_width: usize) -> Self {
+        self.max_width = max_width;
+        self
+    }
+
+    /// The width above which the fields of a tuple variant are put one per line, even if the line fits: 60% of
+    /// `max_width`, like rustfmt's `fn_call_width` with the default `use_small_heuristics`.
+    pub fn fn_call_width(&self) -> usize {
+        self.max_width * 6 / 10
+    }
+
+    /// The width above which a method chain is put one call per line, even if the line fits: 60% of `max_width`,
+    /// like rustfmt's `chain_width`.
+    pub fn chain_width(&self) -> usize {
+        self.max_width * 6 / 10
+    }
+
+    /// Renders `item` with this style. Panics if the item is invalid, as `Display` does; see `try_render`.
+    pub fn render<T: Render + ?Sized>(&self, item: &T) -> String {
+        match self.try_render(item) {
+            Ok(out) => out,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Renders `item` with this style, or returns why it cannot be rendered.
+    pub fn try_render<T: Render + ?Sized>(&self, item: &T) -> Result<String, RenderError> {
+        let mut out = String::new();
+        self.render_into(&mut out, item)?;
+        Ok(out)
+    }
+
+    /// Renders `item` into `out` as it is produced, without building the output in memory first.
+    pub fn render_into<T: Render + ?Sized>(&self, out: &mut dyn fmt::Write, item: &T) -> Result<(), RenderError> {
+        let mut r = Renderer::new(out, self);
+        let result = item.render(&mut r);
+        r.finish(result)
+    }
+
+    /// Renders `item` into `out` as it is produced. Rendering makes many small writes, so files and sockets should
+    /// be wrapped in a `BufWriter`.
+    pub fn write_into<T: Render + ?Sized>(&self, out: &mut dyn io::Write, item: &T) -> Result<(), RenderError> {
+        let mut adapter = IoAdapter { out, error: None };
+
+        match self.render_into(&mut adapter, item) {
+            Err(RenderError::Fmt(e)) => Err(adapter.error.map_or(RenderError::Fmt(e), RenderError::Io)),
+            result => result,
+        }
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// `levels` of indentation in a `Style`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct StyledIndent {
+    levels: u8,
+    width: u8,
+    tabs: bool,
+}
+
+impl Display for StyledIndent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        for _ in 0..self.levels {
+            match self.tabs {
+                true => write!(f, "\t")?,
+                false => write!(f, "{:width$}", "", width = self.width as usize)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The output and style that `Render` implementations write to.
+pub struct Renderer<'a> {
+    out: &'a mut dyn fmt::Write,
+    pub style: &'a Style,
+    /// The nesting depth, added to every indentation.
+    depth: u8,
+    /// Why the item being rendered is invalid, see `invalid`.
+    problem: Option<CodeGenError>,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(out: &'a mut dyn fmt::Write, style: &'a Style) -> Self {
+        Renderer { out, style, depth: 0, problem: None }
+    }
+
+    /// Records that the item being rendered is invalid, returning the error to stop rendering with.
+    pub fn invalid(&mut self, problem: CodeGenError) -> Error {
+        self.problem.get_or_insert(problem);
+        Error
+    }
+
+    /// Turns the result of rendering into a `RenderError` that tells an invalid item from a failed write.
+    fn finish(self, result: fmt::Result) -> Result<(), RenderError> {
+        match (result, self.problem) {
+            (Ok(()), _) => Ok(()),
+            (Err(_), Some(problem)) => Err(RenderError::Invalid(problem)),
+            (Err(e), None) => Err(RenderError::Fmt(e)),
+        }
+    }
+
+    /// The indentation of a line `levels` deeper than the current depth, in the renderer's style.
+    pub fn indent(&self, levels: u8) -> StyledIndent {
+        StyledIndent {
+            levels: self.depth + levels,
+            width: self.style.indent_width,
+            tabs: self.style.tabs,
+        }
+    }
+
+    pub fn render<T: Render + ?Sized>(&mut self, item: &T) -> fmt::Result {
+        item.render(self)
+    }
+
+    /// Lets `write!` be used on a renderer without importing `fmt::Write`.
+    pub fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        self.out.write_fmt(args)
+    }
+
+    /// `,`, or nothing for the last entry of a list when the style has no trailing commas.
+    pub fn comma(&self, is_last: bool) -> &'static str {
+        match is_last && !self.style.trailing_commas {
+            true => "",
+            false => ",",
+        }
+    }
+
+    /// The blank lines between items.
+    pub fn item_separator(&mut self) -> fmt::Result {
+        for _ in 0..self.style.blank_lines {
+            writeln!(self)?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether `text` fits within the maximum width on a line indented by `levels`.
+    pub fn fits<D: Display>(&self, levels: u8, text: D) -> bool {
+        let indent = self.indent(levels).levels as usize * self.style.indent_width as usize;
+        let mut width = Width { width: indent, max: self.style.max_width };
+        fmt::Write::write_fmt(&mut width, format_args!("{}", text)).is_ok()
+    }
+
+    /// Writes `prefix`, `items` separated by `, ` and `suffix` on one line indented by `levels` if it fits.
+    /// Otherwise the items are written one per line, one level deeper, with `suffix` on the line after them.
+    /// Returns whether the list was broken across lines.
+    pub fn list<I, T, P, S>(&mut self, levels: u8, prefix: P, items: I, suffix: S) -> Result<bool, Error>
+    where
+        I: IntoIterator<Item = T> + Clone,
+        T: Display,
+        P: Display,
+        S: Display,
+    {
+        let indent = self.indent(levels);
+        let joined = StrConcat { iter: items.clone(), left_bound: "", right_bound: "", item_prepend: "", item_append: "", join: ", " };
+
+        if items.clone().into_iter().next().is_none() || self.fits(levels, format_args!("{}{}{}", prefix, joined, suffix)) {
+            write!(self, "{}{}{}{}", indent, prefix, joined, suffix)?;
+            return Ok(false);
+        }
+
+        self.broken_list(levels, prefix, items, suffix)
+    }
+
+    /// Like `list`, for the fields of a tuple variant. Like rustfmt, more than one field is also broken across
+    /// lines when the fields are wider than `Style::fn_call_width`.
+    pub(crate) fn tuple_list<P: Display, S: Display>(&mut self, levels: u8, prefix: P, items: &[String], suffix: S) -> Result<bool, Error> {
+        let width = items.iter().map(|i| i.chars().count()).sum::<usize>() + 2 * items.len().saturating_sub(1);
+
+        match items.len() > 1 && width > self.style.fn_call_width() {
+            true => self.broken_list(levels, prefix, items, suffix),
+            false => self.list(levels, prefix, items, suffix),
+        }
+    }
+
+    fn broken_list<I, T, P, S>(&mut self, levels: u8, prefix: P, items: I, suffix: S) -> Result<bool, Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Display,
+        P: Display,
+        S: Display,
+    {
+        let indent = self.indent(levels);
+
+        writeln!(self, "{}{}", indent, prefix)?;
+        self.vertical(levels + 1, items)?;
+        write!(self, "{}{}", indent, suffix)?;
+        Ok(true)
+    }
+
+    /// Writes `prefix`, `generics` and `suffix` on a line indented by `levels`, breaking the generics across lines
+    /// if they do not fit. Returns whether they were broken.
+    pub(crate) fn generic_header<P: Display, S: Display>(&mut self, levels: u8, prefix: P, generics: &Generics, suffix: S) -> Result<bool, Error> {
+        match generics.is_empty() {
+            true => write!(self, "{}{}{}", self.indent(levels), prefix, suffix).map(|_| false),
+            false => self.list(levels, format_args!("{}<", prefix), generics.iter(), format_args!(">{}", suffix)),
+        }
+    }
+
+    /// Like `generic_header`, followed by ` {`. As rustfmt does, generics that fit are kept on one line with `{`
+    /// on the next line when only the brace does not fit, counting the `}` an `empty` body is closed with.
+    pub(crate) fn braced_header<P: Display>(&mut self, levels: u8, prefix: P, generics: &Generics, empty: bool) -> Result<bool, Error> {
+        let closing = match (empty, self.style.empty_body) {
+            (true, EmptyBody::Inline) => " {}",
+            _ => " {",
+        };
+
+        match (self.fits(levels, format_args!("{}{}{}", prefix, generics, closing)), self.fits(levels, format_args!("{}{}", prefix, generics))) {
+            (true, _) => write!(self, "{}{}{} {{", self.indent(levels), prefix, generics).map(|_| false),
+            (false, true) => write!(self, "{}{}{}\n{}{{", self.indent(levels), prefix, generics, self.indent(levels)).map(|_| false),
+            (false, false) => self.generic_header(levels, prefix, generics, " {"),
+        }
+    }
+
+    /// Writes each of `items` on its own line indented by `levels`, followed by a comma.
+    pub fn vertical<I: IntoIterator<Item = T>, T: Display>(&mut self, levels: u8, items: I) -> fmt::Result {
+        self.entries(levels, items, false)
+    }
+
+    /// Like `vertical`, followed by the `...` of a variadic function, which cannot have a comma after it.
+    pub(crate) fn vertical_variadic<I: IntoIterator<Item = T>, T: Display>(&mut self, levels: u8, items: I) -> fmt::Result {
+        self.entries(levels, items, true)?;
+        writeln!(self, "{}...", self.indent(levels))
+    }
+
+    fn entries<I: IntoIterator<Item = T>, T: Display>(&mut self, levels: u8, items: I, more: bool) -> fmt::Result {
+        let indent = self.indent(levels);
+        let mut items = items.into_iter().peekable();
+
+        while let Some(item) = items.next() {
+            let comma = self.comma(!more && items.peek().is_none());
+            writeln!(self, "{}{}{}", indent, item, comma)?;
+        }
+
+        Ok(())
+    }
+
+    /// Closes an empty body that has been opened with `{` on a line indented by `levels`. After a `where` clause
+    /// or a header broken across lines, `multiline_header`, rustfmt puts the `}` on its own line.
+    pub fn empty_body(&mut self, levels: u8, multiline_header: bool) -> fmt::Result {
+        match (self.style.empty_body, multiline_header) {
+            (EmptyBody::Inline, false) => write!(self, "}}"),
+            _ => write!(self, "\n{}}}", self.indent(levels)),
+        }
+    }
+
+    /// Renders `item` `levels` deeper than the current depth.
+    pub fn indented<T: Render + ?Sized>(&mut self, item: &T, levels: u8) -> fmt::Result {
+        self.depth += levels;
+        let result = item.render(self);
+        self.depth -= levels;
+        result
+    }
+
+    /// Writes `text`, indenting each of its lines by `levels`. Empty lines and lines that continue a string
+    /// literal, such as the lines of a multi-line raw string, are written as they are.
+    pub fn lines(&mut self, levels: u8, text: &str) -> fmt::Result {
+        let indent = self.indent(levels);
+
+        for (line, in_literal) in literal_aware_lines(text) {
+            if in_literal || line.trim_end_matches('\n').is_empty() {
+                write!(self, "{}", line)?;
+            } else {
+                write!(self, "{}{}", indent, line)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits `text` after each newline, pairing every line with whether it starts inside a string literal.
+pub(crate) fn literal_aware_lines(text: &str) -> LiteralAwareLines<'_> {
+    LiteralAwareLines { text, position: 0, state: LiteralState::Code }
+}
+
+/// `text` with its string and char literals and comments replaced by spaces, leaving only code.
+pub(crate) fn code_only(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut code = Vec::with_capacity(bytes.len());
+    let mut state = LiteralState::Code;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let before = state;
+        let next = state.step(bytes, i).min(bytes.len());
+
+        match (before, state, next - i) {
+            (LiteralState::Code, LiteralState::Code, 1) => code.push(bytes[i]),
+            _ => code.resize(code.len() + next - i, b' '),
+        }
+
+        i = next;
+    }
+
+    // only whole literals and comments are replaced, which start and end at ASCII delimiters
+    String::from_utf8(code).unwrap()
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum LiteralState {
+    Code,
+    Str,
+    /// A raw string closed by `"` and this many `#`.
+    RawStr(usize),
+    Comment,
+    /// A `/* */` comment, nested this deep.
+    BlockComment(usize),
+}
+
+impl LiteralState {
+    /// Moves past the byte at `i`, returning the index of the next byte to look at. The delimiters are all ASCII,
+    /// so scanning bytes never splits a character.
+    fn step(&mut self, bytes: &[u8], i: usize) -> usize {
+        let c = bytes[i];
+        let next = |n: usize| bytes.get(i + n).copied();
+
+        match *self {
+            LiteralState::Code => match c {
+                b'"' => *self = LiteralState::Str,
+                b'/' if next(1) == Some(b'/') => *self = LiteralState::Comment,
+                b'/' if next(1) == Some(b'*') => {
+                    *self = LiteralState::BlockComment(1);
+                    return i + 2;
+                },
+                b'r' | b'b' if i == 0 || !is_ident_char(bytes[i - 1]) => {
+                    let prefix = match (c, next(1)) {
+                        (b'b', Some(b'r')) => 2,
+                        (b'r', _) => 1,
+                        _ => 0,
+                    };
+
+                    if prefix > 0 {
+                        let hashes = bytes[i + prefix..].iter().take_while(|c| **c == b'#').count();
+
+                        if next(prefix + hashes) == Some(b'"') {
+                            *self = LiteralState::RawStr(hashes);
+                            return i + prefix + hashes + 1;
+                        }
+                    }
+                },
+                // a char literal, rather than a lifetime
+                b'\'' if next(1) == Some(b'\\') => {
+                    return i + 1 + bytes[i + 2..].iter().take(10).position(|c| *c == b'\'').map_or(0, |p| p + 2);
+                },
+                b'\'' if next(2) == Some(b'\'') => return i + 3,
+                _ => {},
+            },
+            LiteralState::Str => match c {
+                b'\\' if next(1) != Some(b'\n') => return i + 2,
+                b'"' => *self = LiteralState::Code,
+                _ => {},
+            },
+            LiteralState::RawStr(hashes) => {
+                if c == b'"' && (1..=hashes).all(|n| next(n) == Some(b'#')) {
+                    *self = LiteralState::Code;
+                    return i + hashes + 1;
+                }
+            },
+            LiteralState::Comment => {
+                if c == b'\n' {
+                    *self = LiteralState::Code;
+                }
+            },
+            LiteralState::BlockComment(depth) => match (c, next(1)) {
+                (b'/', Some(b'*')) => {
+                    *self = LiteralState::BlockComment(depth + 1);
+                    return i + 2;
+                },
+                (b'*', Some(b'/')) => {
+                    *self = match depth {
+                        1 => LiteralState::Code,
+                        _ => LiteralState::BlockComment(depth - 1),
+                    };
+                    return i + 2;
+                },
+                _ => {},
+            },
+        }
+
+        i + 1
+    }
+}
+
+/// The iterator returned by `literal_aware_lines`.
+#[derive(Debug, Clone)]
+pub(crate) struct LiteralAwareLines<'a> {
+    text: &'a str,
+    position: usize,
+    state: LiteralState,
+}
+
+impl<'a> Iterator for LiteralAwareLines<'a> {
+    type Item = (&'a str, bool);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.position;
+        if start >= self.text.len() {
+            return None;
+        }
+
+        let in_literal = matches!(self.state, LiteralState::Str | LiteralState::RawStr(_));
+        let bytes = self.text.as_bytes();
+        let mut i = start;
+
+        while i < bytes.len() {
+            let next = self.state.step(bytes, i);
+
+            // a step over more than one byte never spans a line break, apart from an escaped one in a string
+            if let Some(end) = bytes[i..next.min(bytes.len())].iter().position(|c| *c == b'\n') {
+                self.position = i + end + 1;
+                return Some((&self.text[start..self.position], in_literal));
+            }
+
+            i = next;
+        }
+
+        self.position = self.text.len();
+        Some((&self.text[start..], in_literal))
+    }
+}
+
+/// Passes writes on to an `io::Write`, keeping the I/O error that `fmt::Error` cannot carry.
+struct IoAdapter<'a> {
+    out: &'a mut dyn io::Write,
+    error: Option<io::Error>,
+}
+
+impl fmt::Write for IoAdapter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.out.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            Error
+        })
+    }
+}
+
+/// Counts the width of what is written to it, failing once it exceeds `max`.
+struct Width {
+    width: usize,
+    max: usize,
+}
+
+impl fmt::Write for Width {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.width += s.chars().count();
+
+        match self.width <= self.max {
+            true => Ok(()),
+            false => Err(Error),
+        }
+    }
+}
+
+fn is_ident_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || !c.is_ascii()
+}
+
+impl fmt::Write for Renderer<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.out.write_str(s)
+    }
+}
+
+/// Rendering that follows a `Style`. `Display` renders with the default style.
+pub trait Render {
+    fn render(&self, r: &mut Renderer<'_>) -> fmt::Result;
+
+    /// Displays `self` with `style`.
+    fn styled<'a>(&'a self, style: &'a Style) -> Styled<'a, Self> {
+        Styled { item: self, style }
+    }
+}
+
+/// Displays an item with a `Style`, see `Render::styled`.
+pub struct Styled<'a, T: ?Sized> {
+    item: &'a T,
+    style: &'a Style,
+}
+
+impl<T: Render + ?Sized> Display for Styled<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        display(self.item, f, self.style)
+    }
+}
+
+/// Renders `item` for `Display`, which can only report that the formatter failed: an invalid item panics with
+/// the reason instead.
+pub(crate) fn display<T: Render + ?Sized>(item: &T, f: &mut Formatter<'_>, style: &Style) -> fmt::Result {
+    let mut r = Renderer::new(f, style);
+    let result = item.render(&mut r);
+
+    match r.finish(result) {
+        Ok(()) => Ok(()),
+        Err(RenderError::Invalid(problem)) => panic!("{}", problem),
+        Err(_) => Err(Error),
+    }
+}
+
+/// Why rendering failed.
+#[derive(Debug)]
+pub enum RenderError {
+    /// Writing to an `io::Write` failed.
+    Io(io::Error),
+    /// The `fmt::Write` being rendered into failed.
+    Fmt(Error),
+    /// The item cannot be rendered as valid code, such as a trait impl missing a required function.
+    Invalid(CodeGenError),
+}
+
+impl Display for RenderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            RenderError::Io(e) => write!(f, "writing the output failed: {}", e),
+            RenderError::Fmt(_) => write!(f, "writing the output failed"),
+            RenderError::Invalid(e) => write!(f, "invalid item: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RenderError::Io(e) => Some(e),
+            RenderError::Fmt(e) => Some(e),
+            RenderError::Invalid(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for RenderError {
+    fn from(e: io::Error) -> Self {
+        RenderError::Io(e)
+    }
+}
+
+/// Implements `Display` with the default style for types that implement `Render`.
+macro_rules! display_via_render {
+    ($($typ:ty),* $(,)?) => {
+        $(
+            impl std::fmt::Display for $typ {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    $crate::render::display(self, f, &$crate::Style::DEFAULT)
+                }
+            }
+        )*
+    };
+}
+
+pub(crate) use display_via_render;
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn module() -> Mod {
+        Mod::new("shapes")
+            .add_item(Struct::new("Point").add_field(Field::new("x", "f32")).add_field(Field::new("y", "f32")))
+            .add_item(Impl::new("Point")
+                .add_function(Function::new("origin")
+                    .with_return("Point".to_string())
+                    .add_line(CodeLine::new(0, "Point { x: 0.0, y: 0.0 }")))
+                .add_function(Function::new("noop")))
+    }
+
+    #[test]
+    fn default_style_matches_display() {
+        let m = module();
+
+        assert_eq!(m.to_string(), Style::default().render(&m));
+        assert_eq!(m.to_string(), m.styled(&Style::new()).to_string());
+    }
+
+    #[test]
+    fn tabs() {
+        assert_eq!(
+            "pub mod shapes {\n\tpub struct Point {\n\t\tpub x: f32,\n\t\tpub y: f32,\n\t}\n\n\timpl Point {\n\t\tpub fn origin() -> Point {\n\t\t\tPoint { x: 0.0, y: 0.0 }\n\t\t}\n\n\t\tpub fn noop() {}\n\t}\n}\n",
+            Style::new().with_tabs().render(&module())
+        );
+    }
+
+    #[test]
+    fn indent_width() {
+        let f = Function::new("run").add_line(CodeLine::new(1, "nested();"));
+
+        assert_eq!("  pub fn run() {\n      nested();\n  }\n", Style::new().with_indent_width(2).render(&f));
+    }
+
+    #[test]
+    fn without_trailing_commas() {
+        let style = Style::new().without_trailing_commas();
+        let e = Enum::new("Shape").add_option(EnumOption::new("Circle", vec!["f32"])).add_option(EnumOption::new("Empty", vec![]));
+        let i = Trait::new("Area").impl_for(&Type::new("T"))
+            .with_impl_generics(Generics::one("T"))
+            .add_where_predicate("T: Copy")
+            .add_where_predicate("T: Default");
+
+        assert_eq!("pub struct A {\n    pub x: u8,\n    pub y: u8\n}\n", style.render(&Struct::new("A").add_field(Field::new("x", "u8")).add_field(Field::new("y", "u8"))));
+        assert_eq!("pub enum Shape {\n    Circle(f32),\n    Empty\n}\n", style.render(&e));
+        assert_eq!("impl<T> Area for T\nwhere\n    T: Copy,\n    T: Default\n{\n}\n", style.render(&i));
+    }
+
+    #[test]
+    fn blank_lines() {
+        let m = Mod::new("consts")
+            .add_item(Const::new("A", "u8", "1"))
+            .add_item(Const::new("B", "u8", "2"));
+
+        assert_eq!("pub mod consts {\n    pub const A: u8 = 1;\n    pub const B: u8 = 2;\n}\n", Style::new().with_blank_lines(0).render(&m));
+        assert_eq!("pub mod consts {\n    pub const A: u8 = 1;\n\n\n    pub const B: u8 = 2;\n}\n", Style::new().with_blank_lines(2).render(&m));
+    }
+
+    #[test]
+    fn open_empty_bodies() {
+        let style = Style::new().with_empty_body(EmptyBody::Open);
+
+        assert_eq!("impl Point {\n}\n", style.render(&Impl::new("Point")));
+        assert_eq!("pub enum Never {\n}\n", style.render(&Enum::new("Never")));
+        assert_eq!("pub mod empty {\n}\n", style.render(&Mod::new("empty")));
+        assert_eq!("    pub fn noop() {\n    }\n", style.render(&Function::new("noop")));
+    }
+
+    #[test]
+    fn emitter_style() {
+        let files = Emitter::new("out").with_style(Style::new().with_tabs()).render(&module());
+
+        assert!(files[0].content.contains("\n\tpub x: f32,\n"));
+    }
+
+    #[test]
+    fn width_includes_enclosing_indentation() {
+        let s = Struct::new("Pair<TFirst, TSecond>").add_field(Field::new("first", "TFirst"));
+        let nested = Mod::new("a").add_item(Mod::new("b").add_item(s.clone()));
+        let style = Style::new().with_max_width(36);
+
+        assert_eq!("pub struct Pair<TFirst, TSecond> {\n    pub first: TFirst,\n}\n", style.render(&s));
+        assert_eq!(
+            "pub mod a {\n    pub mod b {\n        pub struct Pair<\n            TFirst,\n            TSecond,\n        > {\n            pub first: TFirst,\n        }\n    }\n}\n",
+            style.render(&nested)
+        );
+    }
+
+    #[test]
+    fn brace_that_does_not_fit_goes_on_the_next_line() {
+        let style = Style::new().with_max_width(36);
+        let s = Struct::new("Pair<TFirst, TSecond>").add_field(Field::new("first", "TFirst"));
+        let e = Enum::new("Pairs<TFirst, TSecon>");
+
+        assert_eq!("pub struct Pair<TFirst, TSecond> {\n    pub first: TFirst,\n}\n", style.render(&s));
+        assert_eq!("pub struct Pair<TFirst, TSecond>\n{\n    pub first: TFirst,\n}\n", style.with_max_width(33).render(&s));
+        assert_eq!("pub enum Pairs<TFirst, TSecon>\n{}\n", style.with_max_width(32).render(&e));
+        assert_eq!("pub enum Pairs<TFirst, TSecon> {}\n", style.with_max_width(33).render(&e));
+    }
+
+    #[test]
+    fn lines_inside_literals() {
+        use crate::render::literal_aware_lines;
+
+        let text = "let a = \"x\n\\\"y\n\";\nlet b = r##\"\"#\n\"##;\nlet c = '\"';\n// \"\nlet d: &'a str = b\"\n\";\nend";
+        let in_literal: Vec<bool> = literal_aware_lines(text).map(|(_, l)| l).collect();
+
+        assert_eq!(vec![false, true, true, false, true, false, false, false, true, false], in_literal);
+    }
+
+    #[test]
+    fn quotes_inside_block_comments() {
+        use crate::render::literal_aware_lines;
+
+        let text = "/* it's \"quoted /* nested \" */\n\" */\nlet a = \"\n\";\n/**/ let b = \"\n\";";
+        let in_literal: Vec<bool> = literal_aware_lines(text).map(|(_, l)| l).collect();
+        assert_eq!(vec![false, false, false, true, false, true], in_literal);
+
+        let raw = Item::Raw("/* it's \"quoted */\nfn x() {\n    y();\n}\n".to_string());
+        let nested = Mod::new("a").add_item(Mod::new("b").add_item(raw));
+        assert_eq!(
+            "pub mod a {\n    pub mod b {\n        /* it's \"quoted */\n        fn x() {\n            y();\n        }\n    }\n}\n",
+            nested.to_string()
+        );
+    }
+
+    #[test]
+    fn render_errors() {
+        struct Full;
+
+        impl std::fmt::Write for Full {
+            fn write_str(&mut self, _: &str) -> std::fmt::Result {
+                Err(std::fmt::Error)
+            }
+        }
+
+        let drop = StdTrait::Drop.impl_for(&Type::new("Test"));
+        let invalid = Mod::new("a").add_item(drop.clone());
+        let style = Style::new();
+
+        assert!(matches!(style.render_into(&mut Full, &Struct::new("A")), Err(RenderError::Fmt(_))));
+        assert_eq!(
+            "invalid item: impl of Drop for Test is missing function drop",
+            style.try_render(&invalid).unwrap_err().to_string()
+        );
+        assert!(matches!(style.render_into(&mut Full, &drop), Err(RenderError::Invalid(_))));
+    }
+}
@@ -0,0 +1,210 @@
+use crate::*;
+use crate::mods::ModContents;
+use crate::render::display_via_render;
+
+/// The first line of every generated file, used to tell generated files from hand-written ones.
+pub const GENERATED_MARKER: &str = "// @generated";
+
+/// The comment at the top of a generated file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Header {
+    pub generator: String,
+    /// The definition file the code was generated from.
+    pub source: Option<String>,
+    pub version: Option<String>,
+    /// Whether to include a hash of the rest of the file, to detect manual edits.
+    pub hash: bool,
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Header {
+            generator: "code_gen".to_string(),
+            source: None,
+            version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            hash: false,
+        }
+    }
+}
+
+impl Header {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_generator(mut self, generator: &str) -> Self {
+        self.generator = generator.to_string();
+        self
+    }
+
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.version = Some(version.to_string());
+        self
+    }
+
+    pub fn without_version(mut self) -> Self {
+        self.version = None;
+        self
+    }
+
+    pub fn with_hash(mut self) -> Self {
+        self.hash = true;
+        self
+    }
+
+    /// Renders the header followed by `body`.
+    pub fn render(&self, body: &str) -> String {
+        let mut out = format!("{} by {} — do not edit\n", GENERATED_MARKER, self.generator);
+
+        if let Some(source) = &self.source {
+            out.push_str(&format!("// source: {}\n", source));
+        }
+        if let Some(version) = &self.version {
+            out.push_str(&format!("// generator version: {}\n", version));
+        }
+        if self.hash {
+            out.push_str(&format!("// content hash: {:016x}\n", content_hash(body)));
+        }
+
+        out.push_str(body);
+        out
+    }
+
+    /// Whether `content` starts with a generated header.
+    pub fn is_generated(content: &str) -> bool {
+        content.starts_with(GENERATED_MARKER)
+    }
+
+    /// Whether the content hash in the header of `content` matches the rest of the file, or `None` if it has no hash.
+    pub fn verify_hash(content: &str) -> Option<bool> {
+        let mut hash = None;
+        let mut body_start = 0;
+
+        for line in content.split_inclusive('\n') {
+            if !line.starts_with("//") {
+                break;
+            }
+            if let Some(h) = line.trim_end().strip_prefix("// content hash: ") {
+                hash = Some(h.to_string());
+            }
+            body_start += line.len();
+        }
+
+        hash.map(|h| h == format!("{:016x}", content_hash(&content[body_start..])))
+    }
+}
+
+/// 64-bit FNV-1a, which unlike `DefaultHasher` is stable across Rust releases.
+fn content_hash(content: &str) -> u64 {
+    content.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// A generated source file: a header comment, inner attributes such as `#![allow(clippy::all)]` and items.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct SourceFile {
+    pub header: Header,
+    pub attributes: Vec<String>,
+    pub items: Vec<Item>,
+}
+
+impl SourceFile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_header(mut self, header: Header) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Adds an inner attribute, e.g. `allow(clippy::all)` for `#![allow(clippy::all)]`.
+    pub fn add_attribute(mut self, attribute: &str) -> Self {
+        self.attributes.push(attribute.to_string());
+        self
+    }
+
+    pub fn add_item<I: Into<Item>>(mut self, item: I) -> Self {
+        self.items.push(item.into());
+        self
+    }
+
+    /// Renders the header and attributes of this file followed by `body` instead of its items.
+    pub fn wrap<D: Display>(&self, body: D) -> String {
+        let mut out = String::from("\n");
+
+        for attribute in self.attributes.iter() {
+            out.push_str(&format!("#![{}]\n", attribute));
+        }
+
+        let body = body.to_string();
+        if !self.attributes.is_empty() && !body.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&body);
+
+        self.header.render(&out)
+    }
+}
+
+impl Render for SourceFile {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        write!(r, "{}", self.wrap(ModContents::new(&self.items).styled(r.style)))
+    }
+}
+
+display_via_render!(SourceFile);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header() {
+        let file = SourceFile::new()
+            .with_header(Header::new().with_source("defs/units.txt").with_version("1.2.3"))
+            .add_item(Struct::new("A"));
+
+        assert_eq!(
+            "// @generated by code_gen — do not edit\n// source: defs/units.txt\n// generator version: 1.2.3\n\npub struct A;\n",
+            file.to_string()
+        );
+    }
+
+    #[test]
+    fn default_version_is_crate_version() {
+        assert_eq!(Some(env!("CARGO_PKG_VERSION").to_string()), Header::new().version);
+    }
+
+    #[test]
+    fn inner_attributes() {
+        let file = SourceFile::new()
+            .with_header(Header::new().with_generator("units").without_version())
+            .add_attribute("allow(clippy::all)")
+            .add_attribute("allow(dead_code)")
+            .add_item(Struct::new("A"))
+            .add_item(Struct::new("B"));
+
+        assert_eq!(
+            "// @generated by units — do not edit\n\n#![allow(clippy::all)]\n#![allow(dead_code)]\n\npub struct A;\n\npub struct B;\n",
+            file.to_string()
+        );
+    }
+
+    #[test]
+    fn content_hash_detects_edits() {
+        let file = SourceFile::new()
+            .with_header(Header::new().with_hash())
+            .add_item(Struct::new("A"))
+            .to_string();
+
+        assert!(Header::is_generated(&file));
+        assert_eq!(Some(true), Header::verify_hash(&file));
+        assert_eq!(Some(false), Header::verify_hash(&file.replace("struct A", "struct B")));
+        assert_eq!(None, Header::verify_hash(&SourceFile::new().to_string()));
+    }
+}
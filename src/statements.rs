@@ -0,0 +1,856 @@
+use crate::*;
+use crate::render::{display_via_render, literal_aware_lines};
+
+/// A sequence of statements, rendered with indentation derived from nesting.
+///
+/// Each statement renders from column zero, with nested bodies indented one level per nesting. `Function::with_body`
+/// turns a `Body` into `CodeLine`s, so it can be mixed with lines added by `add_line`.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
+pub struct Body {
+    pub statements: Vec<Stmt>,
+}
+
+impl Body {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_statement<S: Into<Stmt>>(mut self, statement: S) -> Self {
+        self.statements.push(statement.into());
+        self
+    }
+
+    /// The rendered statements as `CodeLine`s, starting at `indent`. The statements are rendered as in a free
+    /// function, with one tab per level, so the level of each line is the number of levels the renderer indented
+    /// it by. Lines that continue a multi-line string literal stay part of the `CodeLine` the literal starts in,
+    /// so they are never re-indented.
+    pub fn lines(&self, indent: u8) -> Vec<CodeLine> {
+        let style = Style::new().with_tabs();
+        let mut rendered = String::new();
+        // rendering statements into a `String` cannot fail
+        Renderer::new(&mut rendered, &style).indented(self, indent + 1).unwrap();
+
+        let mut lines: Vec<CodeLine> = vec![];
+
+        for (line, in_literal) in literal_aware_lines(&rendered) {
+            let line = line.strip_suffix('\n').unwrap_or(line);
+
+            match (in_literal, lines.last_mut()) {
+                (true, Some(last)) => {
+                    last.text.push('\n');
+                    last.text.push_str(line);
+                },
+                _ => {
+                    let text = line.trim_start_matches('\t');
+                    let levels = (line.len() - text.len()) as u8;
+                    lines.push(CodeLine::new(levels.saturating_sub(1), text));
+                },
+            }
+        }
+
+        lines
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+}
+
+impl Render for Body {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        for statement in self.statements.iter() {
+            r.render(statement)?;
+            writeln!(r)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes `body` in braces, with its statements one level deeper: `{\n    ...\n}`, or `{}` if it is empty.
+fn braced(r: &mut Renderer<'_>, body: &Body) -> Result<(), Error> {
+    if body.is_empty() {
+        return write!(r, "{{}}");
+    }
+
+    writeln!(r, "{{")?;
+    r.indented(body, 1)?;
+    write!(r, "{}}}", r.indent(0))
+}
+
+/// Writes `code` from the current position. Its other lines are indented to the current depth, except for lines
+/// inside string literals.
+fn continued(r: &mut Renderer<'_>, code: &str) -> Result<(), Error> {
+    for (i, (line, in_literal)) in literal_aware_lines(code).enumerate() {
+        match i == 0 || in_literal || line.trim_end_matches('\n').is_empty() {
+            true => write!(r, "{}", line)?,
+            false => write!(r, "{}{}", r.indent(0), line)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes `exprs` separated by `, `.
+fn list(r: &mut Renderer<'_>, exprs: &[Expr]) -> Result<(), Error> {
+    for (i, expr) in exprs.iter().enumerate() {
+        if i > 0 {
+            write!(r, ", ")?;
+        }
+        r.render(expr)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Stmt {
+    /// `let pattern: Type = value;`
+    Let { pattern: String, typ: Option<Type>, value: Option<Expr> },
+    /// An expression followed by `;`, which is left out after block-like expressions such as `if` and `match`.
+    Expr(Expr),
+    /// An expression without `;`, which is the value of the body.
+    Tail(Expr),
+    /// `return value;`
+    Return(Option<Expr>),
+    /// `for pattern in iter { ... }`
+    For { pattern: String, iter: Expr, body: Body },
+    /// `while condition { ... }`
+    While { condition: Expr, body: Body },
+    /// `loop { ... }`
+    Loop(Body),
+    /// Code inserted as is, each line indented to the statement's level. Nested indentation is kept.
+    Raw(String),
+}
+
+impl Stmt {
+    pub fn let_binding(pattern: &str, value: Expr) -> Self {
+        Stmt::Let { pattern: pattern.to_string(), typ: None, value: Some(value) }
+    }
+
+    pub fn let_typed(pattern: &str, typ: &str, value: Expr) -> Self {
+        Self::try_let_typed(pattern, typ, value).unwrap()
+    }
+
+    pub fn try_let_typed(pattern: &str, typ: &str, value: Expr) -> Result<Self, CodeGenError> {
+        Ok(Stmt::Let { pattern: pattern.to_string(), typ: Some(typ.parse()?), value: Some(value) })
+    }
+
+    pub fn for_loop(pattern: &str, iter: Expr, body: Body) -> Self {
+        Stmt::For { pattern: pattern.to_string(), iter, body }
+    }
+
+    pub fn while_loop(condition: Expr, body: Body) -> Self {
+        Stmt::While { condition, body }
+    }
+
+    pub fn raw(code: &str) -> Self {
+        Stmt::Raw(code.to_string())
+    }
+}
+
+impl Render for Stmt {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        if let Stmt::Raw(code) = self {
+            return r.lines(0, code.trim_end_matches('\n'));
+        }
+
+        write!(r, "{}", r.indent(0))?;
+
+        match self {
+            Stmt::Let { pattern, typ, value } => {
+                write!(r, "let {}", pattern)?;
+                if let Some(typ) = typ {
+                    write!(r, ": {}", typ)?;
+                }
+                if let Some(value) = value {
+                    write!(r, " = ")?;
+                    r.render(value)?;
+                }
+                write!(r, ";")
+            },
+            Stmt::Expr(expr) if expr.is_block_like() => r.render(expr),
+            Stmt::Expr(expr) => {
+                r.render(expr)?;
+                write!(r, ";")
+            },
+            Stmt::Tail(expr) => r.render(expr),
+            Stmt::Return(Some(expr)) => {
+                write!(r, "return ")?;
+                r.render(expr)?;
+                write!(r, ";")
+            },
+            Stmt::Return(None) => write!(r, "return;"),
+            Stmt::For { pattern, iter, body } => {
+                write!(r, "for {} in ", pattern)?;
+                r.render(iter)?;
+                write!(r, " ")?;
+                braced(r, body)
+            },
+            Stmt::While { condition, body } => {
+                write!(r, "while ")?;
+                r.render(condition)?;
+                write!(r, " ")?;
+                braced(r, body)
+            },
+            Stmt::Loop(body) => {
+                write!(r, "loop ")?;
+                braced(r, body)
+            },
+            Stmt::Raw(_) => Ok(()),
+        }
+    }
+}
+
+impl From<Expr> for Stmt {
+    fn from(expr: Expr) -> Self {
+        Stmt::Expr(expr)
+    }
+}
+
+impl From<If> for Stmt {
+    fn from(i: If) -> Self {
+        Stmt::Expr(Expr::If(i))
+    }
+}
+
+impl From<Match> for Stmt {
+    fn from(m: Match) -> Self {
+        Stmt::Expr(Expr::Match(m))
+    }
+}
+
+/// A raw line, keeping its indentation relative to the statement's level.
+impl From<CodeLine> for Stmt {
+    fn from(line: CodeLine) -> Self {
+        Stmt::Raw(format!("{}{}", Indent(line.indent.0.saturating_sub(1)), line.text))
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Expr {
+    /// Expression code inserted as is, such as `a + b` or `&self.name`.
+    Raw(String),
+    /// `function(args)`
+    Call { function: String, args: Vec<Expr> },
+    /// `receiver.method(args).method(args)`
+    MethodChain(MethodChain),
+    /// `name!(args)`, `name![args]` or `name! { args }`
+    Macro { name: String, delimiter: MacroDelimiter, args: Vec<Expr> },
+    Closure(Closure),
+    StructLiteral(StructLiteral),
+    If(If),
+    Match(Match),
+    /// `{ ... }`
+    Block(Body),
+}
+
+impl Expr {
+    pub fn raw(code: &str) -> Self {
+        Expr::Raw(code.to_string())
+    }
+
+    pub fn call(function: &str, args: Vec<Expr>) -> Self {
+        Expr::Call { function: function.to_string(), args }
+    }
+
+    /// A macro call with parentheses, e.g. `format!("{}", x)`.
+    pub fn macro_call(name: &str, args: Vec<Expr>) -> Self {
+        Expr::Macro { name: name.trim_end_matches('!').to_string(), delimiter: MacroDelimiter::Paren, args }
+    }
+
+    /// A `vec![...]`.
+    pub fn vec(items: Vec<Expr>) -> Self {
+        Expr::Macro { name: "vec".to_string(), delimiter: MacroDelimiter::Bracket, args: items }
+    }
+
+    /// Whether the expression ends in a block, so needs no `;` as a statement.
+    fn is_block_like(&self) -> bool {
+        matches!(self, Expr::If(_) | Expr::Match(_) | Expr::Block(_))
+    }
+}
+
+impl From<&str> for Expr {
+    fn from(code: &str) -> Self {
+        Expr::raw(code)
+    }
+}
+
+impl From<MethodChain> for Expr {
+    fn from(chain: MethodChain) -> Self {
+        Expr::MethodChain(chain)
+    }
+}
+
+impl From<Closure> for Expr {
+    fn from(closure: Closure) -> Self {
+        Expr::Closure(closure)
+    }
+}
+
+impl From<StructLiteral> for Expr {
+    fn from(literal: StructLiteral) -> Self {
+        Expr::StructLiteral(literal)
+    }
+}
+
+impl From<If> for Expr {
+    fn from(i: If) -> Self {
+        Expr::If(i)
+    }
+}
+
+impl From<Match> for Expr {
+    fn from(m: Match) -> Self {
+        Expr::Match(m)
+    }
+}
+
+impl From<Body> for Expr {
+    fn from(body: Body) -> Self {
+        Expr::Block(body)
+    }
+}
+
+impl Render for Expr {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        match self {
+            Expr::Raw(code) => continued(r, code),
+            Expr::Call { function, args } => {
+                write!(r, "{}(", function)?;
+                list(r, args)?;
+                write!(r, ")")
+            },
+            Expr::MethodChain(chain) => r.render(chain),
+            Expr::Macro { name, delimiter, args } => {
+                let (open, close) = match delimiter {
+                    MacroDelimiter::Paren => ("(", ")"),
+                    MacroDelimiter::Bracket => ("[", "]"),
+                    MacroDelimiter::Brace => (" { ", " }"),
+                };
+                write!(r, "{}!{}", name, open)?;
+                list(r, args)?;
+                write!(r, "{}", close)
+            },
+            Expr::Closure(closure) => r.render(closure),
+            Expr::StructLiteral(literal) => r.render(literal),
+            Expr::If(i) => r.render(i),
+            Expr::Match(m) => r.render(m),
+            Expr::Block(body) => braced(r, body),
+        }
+    }
+}
+
+/// A chain of method calls on a receiver. Like rustfmt, a chain that does not fit on one line, or is wider than
+/// `Style::chain_width`, is rendered with one call per line.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct MethodChain {
+    pub receiver: Box<Expr>,
+    pub calls: Vec<(String, Vec<Expr>)>,
+}
+
+impl MethodChain {
+    pub fn new<E: Into<Expr>>(receiver: E) -> Self {
+        MethodChain {
+            receiver: Box::new(receiver.into()),
+            calls: vec![],
+        }
+    }
+
+    pub fn call(mut self, method: &str, args: Vec<Expr>) -> Self {
+        self.calls.push((method.to_string(), args));
+        self
+    }
+}
+
+impl Render for MethodChain {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        let single_line = OneLine(self).to_string();
+        let vertical = single_line.contains('\n')
+            || single_line.chars().count() > r.style.chain_width()
+            || !r.fits(0, &single_line);
+
+        if !vertical {
+            return write!(r, "{}", single_line);
+        }
+
+        r.render(self.receiver.as_ref())?;
+
+        for (method, args) in self.calls.iter() {
+            write!(r, "\n{}.{}(", r.indent(1), method)?;
+            r.indented(&Args(args), 1)?;
+            write!(r, ")")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A chain with all its calls on one line.
+struct OneLine<'a>(&'a MethodChain);
+
+impl Display for OneLine<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.0.receiver)?;
+
+        for (method, args) in self.0.calls.iter() {
+            write!(f, ".{}(", method)?;
+            for (i, arg) in args.iter().enumerate() {
+                match i {
+                    0 => write!(f, "{}", arg)?,
+                    _ => write!(f, ", {}", arg)?,
+                }
+            }
+            write!(f, ")")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The arguments of a call, see `list`.
+struct Args<'a>(&'a [Expr]);
+
+impl Render for Args<'_> {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        list(r, self.0)
+    }
+}
+
+/// `|params| body` or `move |params| { ... }`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Closure {
+    pub params: Vec<String>,
+    pub is_move: bool,
+    pub body: Box<Expr>,
+}
+
+impl Closure {
+    pub fn new<E: Into<Expr>>(params: &[&str], body: E) -> Self {
+        Closure {
+            params: params.iter().map(|p| p.to_string()).collect(),
+            is_move: false,
+            body: Box::new(body.into()),
+        }
+    }
+
+    pub fn as_move(mut self) -> Self {
+        self.is_move = true;
+        self
+    }
+}
+
+impl Render for Closure {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        if self.is_move {
+            write!(r, "move ")?;
+        }
+
+        write!(r, "|{}| ", self.params.join(", "))?;
+        r.render(self.body.as_ref())
+    }
+}
+
+/// `Path { field: value, shorthand, ..base }`. Literals with short field lists stay on one line, as rustfmt does.
+/// Fields are named in snake_case, or by their index for tuple structs: `Pair { 0: a, 1: b }`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct StructLiteral {
+    pub path: String,
+    pub fields: Vec<(String, Expr)>,
+    pub base: Option<Box<Expr>>,
+}
+
+impl StructLiteral {
+    /// The longest field list that is kept on one line.
+    pub const SINGLE_LINE_WIDTH: usize = 18;
+
+    pub fn new(path: &str) -> Self {
+        StructLiteral {
+            path: path.to_string(),
+            fields: vec![],
+            base: None,
+        }
+    }
+
+    pub fn add_field<E: Into<Expr>>(self, name: &str, value: E) -> Self {
+        self.try_add_field(name, value).unwrap()
+    }
+
+    pub fn try_add_field<E: Into<Expr>>(mut self, name: &str, value: E) -> Result<Self, CodeGenError> {
+        let name = match is_tuple_index(name) {
+            true => name.to_string(),
+            false => name.parse::<SnakeCase>()?.to_string(),
+        };
+
+        self.fields.push((name, value.into()));
+        Ok(self)
+    }
+
+    /// Adds `..base` after the fields.
+    pub fn with_base<E: Into<Expr>>(mut self, base: E) -> Self {
+        self.base = Some(Box::new(base.into()));
+        self
+    }
+}
+
+/// Whether `name` is the index of a tuple field, such as `0`, which has no leading zeros.
+fn is_tuple_index(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|b| b.is_ascii_digit()) && (name == "0" || !name.starts_with('0'))
+}
+
+/// A field of a struct literal, or its `..base`.
+enum LiteralField<'a> {
+    Field(&'a str, &'a Expr),
+    Base(&'a Expr),
+}
+
+impl Render for LiteralField<'_> {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        match self {
+            // `Self { x: x }` is written `Self { x }`, which tuple indices cannot be
+            LiteralField::Field(name, Expr::Raw(code)) if code == name && !is_tuple_index(name) => write!(r, "{}", code),
+            LiteralField::Field(name, value) => {
+                write!(r, "{}: ", name)?;
+                r.render(*value)
+            },
+            LiteralField::Base(base) => {
+                write!(r, "..")?;
+                r.render(*base)
+            },
+        }
+    }
+}
+
+display_via_render!(LiteralField<'_>);
+
+impl Render for StructLiteral {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        let fields: Vec<LiteralField<'_>> = self.fields.iter()
+            .map(|(name, value)| LiteralField::Field(name, value))
+            .chain(self.base.as_deref().map(LiteralField::Base))
+            .collect();
+
+        if fields.is_empty() {
+            return write!(r, "{} {{}}", self.path);
+        }
+
+        let single_line = fields.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+
+        if single_line.len() <= Self::SINGLE_LINE_WIDTH && !single_line.contains('\n') {
+            return write!(r, "{} {{ {} }}", self.path, single_line);
+        }
+
+        writeln!(r, "{} {{", self.path)?;
+
+        for field in fields.iter() {
+            write!(r, "{}", r.indent(1))?;
+            r.indented(field, 1)?;
+
+            // no trailing comma after `..base`
+            match field {
+                LiteralField::Base(_) => writeln!(r)?,
+                LiteralField::Field(..) => writeln!(r, ",")?,
+            }
+        }
+
+        write!(r, "{}}}", r.indent(0))
+    }
+}
+
+/// `if condition { ... } else if ... { ... } else { ... }`
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct If {
+    pub condition: Box<Expr>,
+    pub then: Body,
+    pub otherwise: Option<Box<Else>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Else {
+    If(If),
+    Body(Body),
+}
+
+impl If {
+    pub fn new<E: Into<Expr>>(condition: E, then: Body) -> Self {
+        If {
+            condition: Box::new(condition.into()),
+            then,
+            otherwise: None,
+        }
+    }
+
+    pub fn with_else(mut self, otherwise: Body) -> Self {
+        self.otherwise = Some(Box::new(Else::Body(otherwise)));
+        self
+    }
+
+    pub fn with_else_if(mut self, otherwise: If) -> Self {
+        self.otherwise = Some(Box::new(Else::If(otherwise)));
+        self
+    }
+}
+
+impl Render for If {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        write!(r, "if ")?;
+        r.render(self.condition.as_ref())?;
+        write!(r, " ")?;
+        braced(r, &self.then)?;
+
+        match self.otherwise.as_deref() {
+            Some(Else::If(i)) => {
+                write!(r, " else ")?;
+                r.render(i)
+            },
+            Some(Else::Body(body)) => {
+                write!(r, " else ")?;
+                braced(r, body)
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+/// `match scrutinee { arms }`
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Match {
+    pub scrutinee: Box<Expr>,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct MatchArm {
+    pub pattern: String,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+}
+
+impl Match {
+    pub fn new<E: Into<Expr>>(scrutinee: E) -> Self {
+        Match {
+            scrutinee: Box::new(scrutinee.into()),
+            arms: vec![],
+        }
+    }
+
+    pub fn add_arm<E: Into<Expr>>(mut self, pattern: &str, body: E) -> Self {
+        self.arms.push(MatchArm { pattern: pattern.to_string(), guard: None, body: body.into() });
+        self
+    }
+
+    pub fn add_guarded_arm<G: Into<Expr>, E: Into<Expr>>(mut self, pattern: &str, guard: G, body: E) -> Self {
+        self.arms.push(MatchArm { pattern: pattern.to_string(), guard: Some(guard.into()), body: body.into() });
+        self
+    }
+}
+
+impl Render for Match {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        write!(r, "match ")?;
+        r.render(self.scrutinee.as_ref())?;
+
+        if self.arms.is_empty() {
+            return write!(r, " {{}}");
+        }
+
+        writeln!(r, " {{")?;
+
+        for arm in self.arms.iter() {
+            write!(r, "{}", r.indent(1))?;
+            r.indented(arm, 1)?;
+            writeln!(r)?;
+        }
+
+        write!(r, "{}}}", r.indent(0))
+    }
+}
+
+impl Render for MatchArm {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        write!(r, "{}", self.pattern)?;
+
+        if let Some(guard) = &self.guard {
+            write!(r, " if ")?;
+            r.render(guard)?;
+        }
+
+        write!(r, " => ")?;
+        r.render(&self.body)?;
+
+        match &self.body {
+            // block bodies need no comma
+            Expr::Block(_) => Ok(()),
+            _ => write!(r, ","),
+        }
+    }
+}
+
+display_via_render!(Body, Stmt, Expr, MethodChain, Closure, StructLiteral, If, Match, MatchArm);
+
+impl Function {
+    /// Appends the statements of `body` to the function's lines.
+    pub fn with_body(mut self, body: Body) -> Self {
+        self.lines.extend(body.lines(0));
+        self
+    }
+}
+
+impl TraitFunction {
+    /// Appends the statements of `body` to the function's lines.
+    pub fn with_body(mut self, body: Body) -> Self {
+        self.lines.extend(body.lines(0));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_statements() {
+        let body = Body::new()
+            .add_statement(Stmt::let_binding("mut total", Expr::raw("0")))
+            .add_statement(Stmt::for_loop("item", Expr::raw("items"), Body::new()
+                .add_statement(Match::new("item")
+                    .add_arm("Item::A(n)", Body::new().add_statement(Stmt::raw("total += n;")))
+                    .add_guarded_arm("Item::B(n)", "n > 0", "total -= n")
+                    .add_arm("_", Body::new()))))
+            .add_statement(Stmt::Tail(Expr::raw("total")));
+
+        assert_eq!(
+            "let mut total = 0;\nfor item in items {\n    match item {\n        Item::A(n) => {\n            total += n;\n        }\n        Item::B(n) if n > 0 => total -= n,\n        _ => {}\n    }\n}\ntotal\n",
+            body.to_string()
+        );
+    }
+
+    #[test]
+    fn if_else_chain() {
+        let i = If::new("x > 0", Body::new().add_statement(Stmt::Tail("1".into())))
+            .with_else_if(If::new("x < 0", Body::new().add_statement(Stmt::Tail("-1".into())))
+                .with_else(Body::new().add_statement(Stmt::Tail("0".into()))));
+
+        assert_eq!("if x > 0 {\n    1\n} else if x < 0 {\n    -1\n} else {\n    0\n}", i.to_string());
+    }
+
+    #[test]
+    fn loops_and_return() {
+        let body = Body::new()
+            .add_statement(Stmt::while_loop("running".into(), Body::new().add_statement(Expr::call("step", vec![]))))
+            .add_statement(Stmt::Loop(Body::new().add_statement(Stmt::raw("break;"))))
+            .add_statement(Stmt::Return(Some(Expr::raw("Ok(())"))));
+
+        assert_eq!("while running {\n    step();\n}\nloop {\n    break;\n}\nreturn Ok(());\n", body.to_string());
+    }
+
+    #[test]
+    fn method_chain_and_closure() {
+        let short = MethodChain::new("items").call("iter", vec![]).call("sum::<u32>", vec![]);
+        assert_eq!("items.iter().sum::<u32>()", short.to_string());
+
+        let long = MethodChain::new("items")
+            .call("iter", vec![])
+            .call("filter", vec![Closure::new(&["x"], "x.enabled").into()])
+            .call("map", vec![Closure::new(&["x"], Body::new()
+                .add_statement(Stmt::let_binding("y", "x.value * 2".into()))
+                .add_statement(Stmt::Tail("y".into()))).into()])
+            .call("collect::<Vec<_>>", vec![]);
+
+        assert_eq!(
+            "items\n    .iter()\n    .filter(|x| x.enabled)\n    .map(|x| {\n        let y = x.value * 2;\n        y\n    })\n    .collect::<Vec<_>>()",
+            long.to_string()
+        );
+    }
+
+    #[test]
+    fn method_chains_break_by_width() {
+        let chain = MethodChain::new("items")
+            .call("iter", vec![])
+            .call("copied", vec![])
+            .call("rev", vec![])
+            .call("skip", vec!["1".into()])
+            .call("sum::<u32>", vec![]);
+        assert_eq!("items.iter().copied().rev().skip(1).sum::<u32>()", chain.to_string());
+
+        let body = Body::new().add_statement(Stmt::let_binding("total", chain.into()));
+        assert_eq!(
+            "let total = items\n    .iter()\n    .copied()\n    .rev()\n    .skip(1)\n    .sum::<u32>();\n",
+            Style::new().with_max_width(60).render(&body)
+        );
+    }
+
+    #[test]
+    fn statements_follow_the_style() {
+        let body = Body::new()
+            .add_statement(Stmt::for_loop("item", Expr::raw("items"), Body::new()
+                .add_statement(Match::new("item")
+                    .add_arm("Some(x)", Body::new().add_statement(Expr::call("run", vec!["x".into()])))
+                    .add_arm("None", Expr::raw("()")))));
+
+        assert_eq!(
+            "for item in items {\n\tmatch item {\n\t\tSome(x) => {\n\t\t\trun(x);\n\t\t}\n\t\tNone => (),\n\t}\n}\n",
+            Style::new().with_tabs().render(&body)
+        );
+
+        let i = Impl::new("A").add_function(Function::new("run").with_body(body));
+        assert_eq!(
+            "impl A {\n  pub fn run() {\n    for item in items {\n      match item {\n        Some(x) => {\n          run(x);\n        }\n        None => (),\n      }\n    }\n  }\n}\n",
+            Style::new().with_indent_width(2).render(&i)
+        );
+    }
+
+    #[test]
+    fn struct_literals() {
+        assert_eq!("Self { x, y }", StructLiteral::new("Self").add_field("x", "x").add_field("y", "y").to_string());
+        assert_eq!(
+            "Point {\n    x: compute_x(),\n    ..Default::default()\n}",
+            StructLiteral::new("Point").add_field("x", Expr::call("compute_x", vec![])).with_base("Default::default()").to_string()
+        );
+        assert_eq!("Pair { 0: a, 1: 1 }", StructLiteral::new("Pair").add_field("0", "a").add_field("1", "1").to_string());
+        assert!(StructLiteral::new("Pair").try_add_field("01", "a").is_err());
+    }
+
+    #[test]
+    fn macro_calls() {
+        assert_eq!("println!(\"{}\", x)", Expr::macro_call("println!", vec!["\"{}\"".into(), "x".into()]).to_string());
+        assert_eq!("vec![1, 2]", Expr::vec(vec!["1".into(), "2".into()]).to_string());
+    }
+
+    #[test]
+    fn mixed_with_code_lines() {
+        let f = Function::new("run")
+            .add_line(CodeLine::new(0, "let x = 1;"))
+            .with_body(Body::new()
+                .add_statement(If::new("x > 0", Body::new()
+                    .add_statement(CodeLine::new(0, "// raw lines keep relative indentation"))
+                    .add_statement(CodeLine::new(1, "indented();")))));
+
+        assert_eq!(
+            "    pub fn run() {\n        let x = 1;\n        if x > 0 {\n            // raw lines keep relative indentation\n                indented();\n        }\n    }\n",
+            f.to_string()
+        );
+    }
+
+    #[test]
+    fn multi_line_literals_are_kept() {
+        let lines = Body::new()
+            .add_statement(If::new("verbose", Body::new()
+                .add_statement(Stmt::let_binding("text", Expr::raw("\"first\n    second\"")))))
+            .lines(0);
+
+        let rendered: Vec<String> = lines.iter().map(ToString::to_string).collect();
+        assert_eq!(vec!["    if verbose {", "        let text = \"first\n    second\";", "    }"], rendered);
+    }
+
+    #[test]
+    fn quotes_in_block_comments_end_with_the_comment() {
+        let lines = Body::new()
+            .add_statement(Stmt::raw("/* it's \"quoted */"))
+            .add_statement(If::new("x", Body::new().add_statement(Stmt::raw("y();"))))
+            .lines(0);
+
+        let rendered: Vec<String> = lines.iter().map(ToString::to_string).collect();
+        assert_eq!(vec!["    /* it's \"quoted */", "    if x {", "        y();", "    }"], rendered);
+    }
+}
@@ -0,0 +1,441 @@
+use crate::*;
+
+/// Traits from the standard library with their real signatures, for validating and building `TraitImpl`s.
+///
+/// Only required items are listed, plus the provided methods that are commonly overridden
+/// (`PartialEq::ne`, `Iterator::size_hint`, ...), which carry their std default body.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum StdTrait {
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    Display,
+    From,
+    Into,
+    TryFrom,
+    FromStr,
+    Iterator,
+    IntoIterator,
+    Sum,
+    Product,
+    Index,
+    IndexMut,
+    Deref,
+    DerefMut,
+    AsRef,
+    Borrow,
+    Drop,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Neg,
+    Not,
+    AddAssign,
+    SubAssign,
+    MulAssign,
+    DivAssign,
+    RemAssign,
+    BitAndAssign,
+    BitOrAssign,
+    BitXorAssign,
+    ShlAssign,
+    ShrAssign,
+}
+
+impl StdTrait {
+    pub const ALL: [StdTrait; 47] = [
+        StdTrait::Default,
+        StdTrait::Clone,
+        StdTrait::Copy,
+        StdTrait::Debug,
+        StdTrait::Display,
+        StdTrait::From,
+        StdTrait::Into,
+        StdTrait::TryFrom,
+        StdTrait::FromStr,
+        StdTrait::Iterator,
+        StdTrait::IntoIterator,
+        StdTrait::Sum,
+        StdTrait::Product,
+        StdTrait::Index,
+        StdTrait::IndexMut,
+        StdTrait::Deref,
+        StdTrait::DerefMut,
+        StdTrait::AsRef,
+        StdTrait::Borrow,
+        StdTrait::Drop,
+        StdTrait::Hash,
+        StdTrait::PartialEq,
+        StdTrait::Eq,
+        StdTrait::PartialOrd,
+        StdTrait::Ord,
+        StdTrait::Add,
+        StdTrait::Sub,
+        StdTrait::Mul,
+        StdTrait::Div,
+        StdTrait::Rem,
+        StdTrait::BitAnd,
+        StdTrait::BitOr,
+        StdTrait::BitXor,
+        StdTrait::Shl,
+        StdTrait::Shr,
+        StdTrait::Neg,
+        StdTrait::Not,
+        StdTrait::AddAssign,
+        StdTrait::SubAssign,
+        StdTrait::MulAssign,
+        StdTrait::DivAssign,
+        StdTrait::RemAssign,
+        StdTrait::BitAndAssign,
+        StdTrait::BitOrAssign,
+        StdTrait::BitXorAssign,
+        StdTrait::ShlAssign,
+        StdTrait::ShrAssign,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StdTrait::Default => "Default",
+            StdTrait::Clone => "Clone",
+            StdTrait::Copy => "Copy",
+            StdTrait::Debug => "Debug",
+            StdTrait::Display => "Display",
+            StdTrait::From => "From",
+            StdTrait::Into => "Into",
+            StdTrait::TryFrom => "TryFrom",
+            StdTrait::FromStr => "FromStr",
+            StdTrait::Iterator => "Iterator",
+            StdTrait::IntoIterator => "IntoIterator",
+            StdTrait::Sum => "Sum",
+            StdTrait::Product => "Product",
+            StdTrait::Index => "Index",
+            StdTrait::IndexMut => "IndexMut",
+            StdTrait::Deref => "Deref",
+            StdTrait::DerefMut => "DerefMut",
+            StdTrait::AsRef => "AsRef",
+            StdTrait::Borrow => "Borrow",
+            StdTrait::Drop => "Drop",
+            StdTrait::Hash => "Hash",
+            StdTrait::PartialEq => "PartialEq",
+            StdTrait::Eq => "Eq",
+            StdTrait::PartialOrd => "PartialOrd",
+            StdTrait::Ord => "Ord",
+            StdTrait::Add => "Add",
+            StdTrait::Sub => "Sub",
+            StdTrait::Mul => "Mul",
+            StdTrait::Div => "Div",
+            StdTrait::Rem => "Rem",
+            StdTrait::BitAnd => "BitAnd",
+            StdTrait::BitOr => "BitOr",
+            StdTrait::BitXor => "BitXor",
+            StdTrait::Shl => "Shl",
+            StdTrait::Shr => "Shr",
+            StdTrait::Neg => "Neg",
+            StdTrait::Not => "Not",
+            StdTrait::AddAssign => "AddAssign",
+            StdTrait::SubAssign => "SubAssign",
+            StdTrait::MulAssign => "MulAssign",
+            StdTrait::DivAssign => "DivAssign",
+            StdTrait::RemAssign => "RemAssign",
+            StdTrait::BitAndAssign => "BitAndAssign",
+            StdTrait::BitOrAssign => "BitOrAssign",
+            StdTrait::BitXorAssign => "BitXorAssign",
+            StdTrait::ShlAssign => "ShlAssign",
+            StdTrait::ShrAssign => "ShrAssign",
+        }
+    }
+
+    /// The module the trait is defined in.
+    pub fn module(self) -> &'static str {
+        match self {
+            StdTrait::Default => "std::default",
+            StdTrait::Clone => "std::clone",
+            StdTrait::Copy => "std::marker",
+            StdTrait::Debug | StdTrait::Display => "std::fmt",
+            StdTrait::From | StdTrait::Into | StdTrait::TryFrom | StdTrait::AsRef => "std::convert",
+            StdTrait::FromStr => "std::str",
+            StdTrait::Iterator | StdTrait::IntoIterator | StdTrait::Sum | StdTrait::Product => "std::iter",
+            StdTrait::Borrow => "std::borrow",
+            StdTrait::Drop => "std::ops",
+            StdTrait::Hash => "std::hash",
+            StdTrait::PartialEq | StdTrait::Eq | StdTrait::PartialOrd | StdTrait::Ord => "std::cmp",
+            _ => "std::ops",
+        }
+    }
+
+    /// The full path of the trait, e.g. `std::ops::Add`.
+    pub fn path(self) -> String {
+        format!("{}::{}", self.module(), self.name())
+    }
+
+    /// Whether the trait is in the 2018 prelude and can be named without a `use`.
+    pub fn in_prelude(self) -> bool {
+        matches!(
+            self,
+            StdTrait::Default | StdTrait::Clone | StdTrait::Copy
+            | StdTrait::From | StdTrait::Into | StdTrait::AsRef
+            | StdTrait::Iterator | StdTrait::IntoIterator | StdTrait::Drop
+            | StdTrait::PartialEq | StdTrait::Eq | StdTrait::PartialOrd | StdTrait::Ord
+        )
+    }
+
+    pub fn definition(self) -> Trait {
+        let name = self.name();
+
+        match self {
+            StdTrait::Default => Trait::new(name)
+                .add_function_definition(TraitFunction::new("default")
+                    .with_return("Self")),
+            StdTrait::Clone => Trait::new(name)
+                .add_function_definition(TraitFunction::new("clone")
+                    .with_parameters("&self")
+                    .with_return("Self"))
+                .add_function_definition(TraitFunction::new("clone_from")
+                    .with_parameters("&mut self, source: &Self")
+                    .add_line(CodeLine::new(0, "*self = source.clone()"))),
+            StdTrait::Copy => Trait::new(name)
+                .with_supertraits("Clone"),
+            StdTrait::Debug | StdTrait::Display => Trait::new(name)
+                .add_function_definition(TraitFunction::new("fmt")
+                    .with_parameters("&self, f: &mut std::fmt::Formatter<'_>")
+                    .with_return("std::fmt::Result")),
+            StdTrait::From => Trait::new(name)
+                .with_generics(Generics::one("T"))
+                .add_function_definition(TraitFunction::new("from")
+                    .with_parameters("value: T")
+                    .with_return("Self")),
+            StdTrait::Into => Trait::new(name)
+                .with_generics(Generics::one("T"))
+                .add_function_definition(TraitFunction::new("into")
+                    .with_parameters("self")
+                    .with_return("T")),
+            StdTrait::TryFrom => Trait::new(name)
+                .with_generics(Generics::one("T"))
+                .add_associated_type("Error")
+                .add_function_definition(TraitFunction::new("try_from")
+                    .with_parameters("value: T")
+                    .with_return("Result<Self, Self::Error>")),
+            StdTrait::FromStr => Trait::new(name)
+                .add_associated_type("Err")
+                .add_function_definition(TraitFunction::new("from_str")
+                    .with_parameters("s: &str")
+                    .with_return("Result<Self, Self::Err>")),
+            StdTrait::Iterator => Trait::new(name)
+                .add_associated_type("Item")
+                .add_function_definition(TraitFunction::new("next")
+                    .with_parameters("&mut self")
+                    .with_return("Option<Self::Item>"))
+                .add_function_definition(TraitFunction::new("size_hint")
+                    .with_parameters("&self")
+                    .with_return("(usize, Option<usize>)")
+                    .add_line(CodeLine::new(0, "(0, None)"))),
+            StdTrait::IntoIterator => Trait::new(name)
+                .add_associated_type("Item")
+                .add_associated_type("IntoIter: Iterator<Item = Self::Item>")
+                .add_function_definition(TraitFunction::new("into_iter")
+                    .with_parameters("self")
+                    .with_return("Self::IntoIter")),
+            StdTrait::Sum | StdTrait::Product => Trait::new(name)
+                .with_generics(Generics::one("A = Self"))
+                .add_function_definition(TraitFunction::new(if self == StdTrait::Sum { "sum" } else { "product" })
+                    .with_generics(Generics::one("I: Iterator<Item = A>"))
+                    .with_parameters("iter: I")
+                    .with_return("Self")),
+            StdTrait::Index => Trait::new(name)
+                .with_generics(Generics::one("Idx: ?Sized"))
+                .add_associated_type("Output: ?Sized")
+                .add_function_definition(TraitFunction::new("index")
+                    .with_parameters("&self, index: Idx")
+                    .with_return("&Self::Output")),
+            StdTrait::IndexMut => Trait::new(name)
+                .with_generics(Generics::one("Idx: ?Sized"))
+                .with_supertraits("Index<Idx>")
+                .add_function_definition(TraitFunction::new("index_mut")
+                    .with_parameters("&mut self, index: Idx")
+                    .with_return("&mut Self::Output")),
+            StdTrait::Deref => Trait::new(name)
+                .add_associated_type("Target: ?Sized")
+                .add_function_definition(TraitFunction::new("deref")
+                    .with_parameters("&self")
+                    .with_return("&Self::Target")),
+            StdTrait::DerefMut => Trait::new(name)
+                .with_supertraits("Deref")
+                .add_function_definition(TraitFunction::new("deref_mut")
+                    .with_parameters("&mut self")
+                    .with_return("&mut Self::Target")),
+            StdTrait::AsRef => Trait::new(name)
+                .with_generics(Generics::one("T: ?Sized"))
+                .add_function_definition(TraitFunction::new("as_ref")
+                    .with_parameters("&self")
+                    .with_return("&T")),
+            StdTrait::Borrow => Trait::new(name)
+                .with_generics(Generics::one("Borrowed: ?Sized"))
+                .add_function_definition(TraitFunction::new("borrow")
+                    .with_parameters("&self")
+                    .with_return("&Borrowed")),
+            StdTrait::Drop => Trait::new(name)
+                .add_function_definition(TraitFunction::new("drop")
+                    .with_parameters("&mut self")),
+            StdTrait::Hash => Trait::new(name)
+                .add_function_definition(TraitFunction::new("hash")
+                    .with_generics(Generics::one("H: std::hash::Hasher"))
+                    .with_parameters("&self, state: &mut H")),
+            StdTrait::PartialEq => Trait::new(name)
+                .with_generics(Generics::one("Rhs: ?Sized = Self"))
+                .add_function_definition(TraitFunction::new("eq")
+                    .with_parameters("&self, other: &Rhs")
+                    .with_return("bool"))
+                .add_function_definition(TraitFunction::new("ne")
+                    .with_parameters("&self, other: &Rhs")
+                    .with_return("bool")
+                    .add_line(CodeLine::new(0, "!self.eq(other)"))),
+            StdTrait::Eq => Trait::new(name)
+                .with_supertraits("PartialEq<Self>"),
+            StdTrait::PartialOrd => Trait::new(name)
+                .with_generics(Generics::one("Rhs: ?Sized = Self"))
+                .with_supertraits("PartialEq<Rhs>")
+                .add_function_definition(TraitFunction::new("partial_cmp")
+                    .with_parameters("&self, other: &Rhs")
+                    .with_return("Option<std::cmp::Ordering>"))
+                .add_function_definition(partial_ord_comparison("lt", "Less"))
+                .add_function_definition(partial_ord_comparison("le", "Less | std::cmp::Ordering::Equal"))
+                .add_function_definition(partial_ord_comparison("gt", "Greater"))
+                .add_function_definition(partial_ord_comparison("ge", "Greater | std::cmp::Ordering::Equal")),
+            StdTrait::Ord => Trait::new(name)
+                .with_supertraits("Eq + PartialOrd<Self>")
+                .add_function_definition(TraitFunction::new("cmp")
+                    .with_parameters("&self, other: &Self")
+                    .with_return("std::cmp::Ordering")),
+            StdTrait::Neg | StdTrait::Not => Trait::new(name)
+                .add_associated_type("Output")
+                .add_function_definition(TraitFunction::new(&self.method_name())
+                    .with_parameters("self")
+                    .with_return("Self::Output")),
+            _ if self.is_assign_op() => Trait::new(name)
+                .with_generics(Generics::one("Rhs = Self"))
+                .add_function_definition(TraitFunction::new(&self.method_name())
+                    .with_parameters("&mut self, rhs: Rhs")),
+            _ => Trait::new(name)
+                .with_generics(Generics::one("Rhs = Self"))
+                .add_associated_type("Output")
+                .add_function_definition(TraitFunction::new(&self.method_name())
+                    .with_parameters("self, rhs: Rhs")
+                    .with_return("Self::Output")),
+        }
+    }
+
+    pub fn impl_for(self, typ: &Type) -> TraitImpl {
+        self.definition().impl_for(typ)
+    }
+
+    /// The required method of the trait, derived from its name: `AddAssign` -> `add_assign`.
+    pub(crate) fn method_name(self) -> String {
+        let name: SnakeCase = CamelCase::new(self.name()).into();
+        name.to_string()
+    }
+
+    fn is_assign_op(self) -> bool {
+        self.module() == "std::ops" && self.name().ends_with("Assign")
+    }
+}
+
+impl From<StdTrait> for Trait {
+    fn from(std_trait: StdTrait) -> Self {
+        std_trait.definition()
+    }
+}
+
+fn partial_ord_comparison(name: &str, pattern: &str) -> TraitFunction {
+    TraitFunction::new(name)
+        .with_parameters("&self, other: &Rhs")
+        .with_return("bool")
+        .add_line(CodeLine::new(0, &format!("matches!(self.partial_cmp(other), Some(std::cmp::Ordering::{}))", pattern)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_definitions_are_named_after_the_variant() {
+        for t in StdTrait::ALL.iter() {
+            assert_eq!(t.name(), t.definition().name.as_str());
+        }
+    }
+
+    #[test]
+    fn default_impl() {
+        let i = StdTrait::Default.impl_for(&Type::new("Test"))
+            .add_function(TraitFunction::new("default")
+                .with_return("Self")
+                .add_line(CodeLine::new(0, "Test")));
+
+        assert_eq!("impl Default for Test {\n    fn default() -> Self {\n        Test\n    }\n}\n", i.to_string());
+    }
+
+    #[test]
+    fn add_impl_with_default_rhs() {
+        let i = StdTrait::Add.impl_for(&Type::new("Meters"))
+            .add_associated_type(TypeName::new("Output"), Type::new("Self"))
+            .add_function(TraitFunction::new("add")
+                .with_parameters("self, rhs: Self")
+                .with_return("Self::Output")
+                .add_line(CodeLine::new(0, "Meters(self.0 + rhs.0)")));
+
+        assert_eq!(
+            "impl Add for Meters {\n    type Output = Self;\n\n    fn add(self, rhs: Self) -> Self::Output {\n        Meters(self.0 + rhs.0)\n    }\n}\n",
+            i.to_string()
+        );
+    }
+
+    #[test]
+    fn assign_op_definition() {
+        assert_eq!(
+            "pub trait MulAssign<Rhs = Self> {\n    fn mul_assign(&mut self, rhs: Rhs);\n}\n",
+            StdTrait::MulAssign.definition().to_string()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn drop_requires_drop_fn() {
+        let _panics = StdTrait::Drop.impl_for(&Type::new("Test")).to_string();
+    }
+
+    #[test]
+    #[should_panic]
+    fn from_requires_trait_argument() {
+        let _panics = StdTrait::From.impl_for(&Type::new("Test"))
+            .add_function(TraitFunction::new("from").add_line(CodeLine::new(0, "Test")))
+            .to_string();
+    }
+
+    #[test]
+    fn into_iterator_definition() {
+        assert_eq!(
+            "pub trait IntoIterator {\n    type Item;\n    type IntoIter: Iterator<Item = Self::Item>;\n    fn into_iter(self) -> Self::IntoIter;\n}\n",
+            StdTrait::IntoIterator.definition().to_string()
+        );
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!("std::ops::AddAssign", StdTrait::AddAssign.path());
+        assert_eq!("std::fmt::Display", StdTrait::Display.path());
+        assert!(!StdTrait::Display.in_prelude());
+        assert!(StdTrait::Clone.in_prelude());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::*;
+use crate::render::display_via_render;
 use crate::formatting::SnakeCase;
 use std::fmt::Debug;
 use std::str::FromStr;
@@ -10,16 +11,19 @@ pub struct StructType {
     pub enum_traits: Vec<TraitImpl>,
 }
 
-impl Display for StructType {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        writeln!(f, "{}", self.base).ok();
+impl Render for StructType {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        r.render(&self.base)?;
+        writeln!(r)?;
 
         if let Some(i) = &self.enum_impl {
-            writeln!(f, "{}", i).ok();
+            r.render(i)?;
+            writeln!(r)?;
         }
 
         for i in &self.enum_traits {
-            writeln!(f, "{}", i).ok();
+            r.render(i)?;
+            writeln!(r)?;
         }
 
         Ok(())
@@ -36,12 +40,16 @@ pub struct Struct {
 
 impl Struct {
     pub fn new(ty: &str) -> Self {
-        Struct {
-            typ: ty.parse().unwrap(),
+        Self::try_new(ty).unwrap()
+    }
+
+    pub fn try_new(ty: &str) -> Result<Self, CodeGenError> {
+        Ok(Struct {
+            typ: ty.parse()?,
             visibility: Visibility::Pub,
             derives: Default::default(),
             fields: Default::default()
-        }
+        })
     }
     
     pub fn with_derives(mut self, derives: Derives) -> Self {
@@ -65,21 +73,24 @@ impl Struct {
     }
 }
 
-impl Display for Struct {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        write!(f, "{}", self.derives).ok();
-
-        write!(f, "{vis}struct {typ}", vis = self.visibility, typ = self.typ).ok();
+impl Render for Struct {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        r.render(&self.derives)?;
 
         match self.fields.len() {
-            0 => writeln!(f, ";"),
+            0 => {
+                r.generic_header(0, format_args!("{}struct {}", self.visibility, self.typ.name), &self.typ.types, ";")?;
+                writeln!(r)
+            },
             _ => {
-                writeln!(f, " {{").ok();
+                r.braced_header(0, format_args!("{}struct {}", self.visibility, self.typ.name), &self.typ.types, false)?;
+                writeln!(r)?;
 
-                for field in self.fields.iter() {
-                    writeln!(f, "{}", field).ok();
+                for (i, field) in self.fields.iter().enumerate() {
+                    field.render_in_list(r, i == self.fields.len() - 1)?;
+                    writeln!(r)?;
                 }
-                writeln!(f, "}}")
+                writeln!(r, "{}}}", r.indent(0))
             } ,
         }
     }
@@ -106,6 +117,8 @@ pub struct Field {
     pub visibility: Visibility,
     pub name: SnakeCase,
     pub field_type: Type,
+    /// Attributes without the surrounding `#[...]`, such as derive helpers: `serde(skip)`.
+    pub attributes: Vec<String>,
 }
 
 impl Field {
@@ -116,40 +129,68 @@ impl Field {
             visibility: Default::default(),
             name: field_name,
             field_type: typ,
+            attributes: vec![],
         }
     }
 
     pub fn new(name: &str, field_type: &str) -> Self {
-        Field {
+        Self::try_new(name, field_type).unwrap()
+    }
+
+    pub fn try_new(name: &str, field_type: &str) -> Result<Self, CodeGenError> {
+        Ok(Field {
             visibility: Visibility::Pub,
-            name: name.parse().unwrap(),
-            field_type: Type::from_str(field_type).unwrap(),
-        }
+            name: name.parse()?,
+            field_type: Type::from_str(field_type)?,
+            attributes: vec![],
+        })
     }
 
     pub fn with_visibility(mut self, visibility: Visibility) -> Self {
         self.visibility = visibility;
         self
     }
+
+    pub fn add_attribute(mut self, attribute: &str) -> Self {
+        self.attributes.push(attribute.to_string());
+        self
+    }
 }
 
-impl Display for Field {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        write!(f,
-            "{}{}{}: {},",
-            Indent(1),
+impl Field {
+    /// Renders the field as an entry of a struct or union, with a comma unless it is the last entry and the
+    /// style has no trailing commas.
+    pub(crate) fn render_in_list(&self, r: &mut Renderer<'_>, is_last: bool) -> Result<(), Error> {
+        let indent = r.indent(1);
+
+        for attribute in self.attributes.iter() {
+            writeln!(r, "{}#[{}]", indent, attribute)?;
+        }
+
+        write!(r,
+            "{}{}{}: {}{}",
+            indent,
             self.visibility,
             self.name,
             self.field_type,
+            r.comma(is_last),
         )
     }
 }
 
-impl Into<AnonField> for Field {
-    fn into(self) -> AnonField {
+impl Render for Field {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        self.render_in_list(r, false)
+    }
+}
+
+display_via_render!(StructType, Struct, Field);
+
+impl From<Field> for AnonField {
+    fn from(field: Field) -> Self {
         AnonField {
-            visibility: self.visibility,
-            field_type: self.field_type,
+            visibility: field.visibility,
+            field_type: field.field_type,
         }
     }
 }
@@ -213,6 +254,21 @@ mod tests {
         assert_eq!("#[derive(Debug, Default)]\npub struct Test<T>;\n", s.to_string());
     }
 
+    #[test]
+    fn struct_with_helper_attributes() {
+        let mut derives = Derives::new();
+        derives.insert_with_helper(Derive::imported("serde::Serialize"), "serde(rename_all = \"camelCase\")");
+
+        let s = Struct::new("Test")
+            .with_derives(derives)
+            .add_field(Field::new("cache", "Vec<u8>").add_attribute("serde(skip)"));
+
+        assert_eq!(
+            "#[derive(Serialize)]\n#[serde(rename_all = \"camelCase\")]\npub struct Test {\n    #[serde(skip)]\n    pub cache: Vec<u8>,\n}\n",
+            s.to_string()
+        );
+    }
+
     #[test]
     fn struct_get_type_name() {
         let s = Struct::new("Id<T>");
@@ -1,4 +1,6 @@
 use crate::*;
+use crate::render::display_via_render;
+use crate::impls::{parameter_list, render_signature, Parameters, Returns};
 use std::str::FromStr;
 use std::fmt::{Display, Formatter, Error};
 
@@ -12,7 +14,7 @@ impl TraitName {
 }
 
 impl FromStr for TraitName {
-    type Err = String;
+    type Err = CodeGenError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         s.parse().map(TraitName)
@@ -30,26 +32,48 @@ pub struct Trait {
     pub name: TraitName,
     pub visibility: Visibility,
     pub generics: Generics,
-    pub associated_types: Vec<TypeName>,
+    pub supertraits: Bounds,
+    /// The associated types with their bounds. Use `associated_type_names` for the names alone.
+    pub associated_types: Vec<TypeParam>,
     pub functions: Vec<TraitFunction>,
 }
 
 impl Trait {
     pub fn new(name: &str) -> Self {
-        Trait {
-            name: name.parse().unwrap(),
+        Self::try_new(name).unwrap()
+    }
+
+    pub fn try_new(name: &str) -> Result<Self, CodeGenError> {
+        Ok(Trait {
+            name: name.parse()?,
             visibility: Visibility::Pub,
             generics: Default::default(),
+            supertraits: Default::default(),
             associated_types: Default::default(),
             functions: vec![],
-        }
+        })
     }
 
+    /// Adds an associated type, optionally with bounds: `"Idx"` or `"IntoIter: Iterator<Item = Self::Item>"`
     pub fn add_associated_type(mut self, name: &str) -> Self {
-        self.associated_types.push(TypeName::new(name));
+        self.associated_types.push(TypeParam::new(name));
         self
     }
 
+    /// The names of the associated types, without their bounds.
+    pub fn associated_type_names(&self) -> impl Iterator<Item = &TypeName> + '_ {
+        self.associated_types.iter().map(|ty| &ty.typ.name)
+    }
+
+    pub fn with_supertraits(self, supertraits: &str) -> Self {
+        self.try_with_supertraits(supertraits).unwrap()
+    }
+
+    pub fn try_with_supertraits(mut self, supertraits: &str) -> Result<Self, CodeGenError> {
+        self.supertraits = supertraits.parse()?;
+        Ok(self)
+    }
+
     pub fn add_function_definition(mut self, function_def: TraitFunction) -> Self {
         self.functions.push(function_def);
         self
@@ -64,39 +88,46 @@ impl Trait {
         TraitImpl {
             trait_def: self.clone(),
             typ: typ.clone(),
-            generics: Default::default(),
+            impl_generics: Default::default(),
+            trait_generics: Default::default(),
+            where_clause: Default::default(),
             associated_types: Default::default(),
             functions: vec![],
         }
     }
 }
 
-impl Display for Trait {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        write!(f, "{}trait {}{} {{", self.visibility, self.name, self.generics).ok();
+impl Render for Trait {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        let empty = self.associated_types.is_empty() && self.functions.is_empty();
+        let broken = match self.supertraits.is_empty() {
+            true => r.braced_header(0, format_args!("{}trait {}", self.visibility, self.name), &self.generics, empty)?,
+            false => r.generic_header(0, format_args!("{}trait {}", self.visibility, self.name), &self.generics, format_args!(": {} {{", self.supertraits))?,
+        };
 
-        if self.associated_types.is_empty() && self.functions.is_empty() {
-            return writeln!(f, "}}");
-        }
-        else {
-            writeln!(f).ok();
+        if empty {
+            r.empty_body(0, broken)?;
+            return writeln!(r);
         }
 
+        writeln!(r)?;
+
         for ty in self.associated_types.iter() {
-            writeln!(f, "{}type {};", Indent(1), ty).ok();
+            writeln!(r, "{}type {};", r.indent(1), ty)?;
         }
 
         for func in self.functions.iter() {
-            write!(f, "{}", func).ok();
+            r.render(func)?;
         }
 
-        writeln!(f, "}}")
+        writeln!(r, "{}}}", r.indent(0))
     }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct TraitFunction {
     pub name: SnakeCase,
+    pub generics: Generics,
     pub parameters: String,
     pub return_type: Option<String>,
     pub lines: Vec<CodeLine>,
@@ -104,12 +135,22 @@ pub struct TraitFunction {
 
 impl TraitFunction {
     pub fn new(name: &str) -> Self {
-        Self {
-            name: name.parse().unwrap(),
+        Self::try_new(name).unwrap()
+    }
+
+    pub fn try_new(name: &str) -> Result<Self, CodeGenError> {
+        Ok(Self {
+            name: name.parse()?,
+            generics: Generics::none(),
             parameters: String::new(),
             return_type: None,
             lines: vec![],
-        }
+        })
+    }
+
+    pub fn with_generics(mut self, generics: Generics) -> Self {
+        self.generics = generics;
+        self
     }
 
     pub fn with_parameters(mut self, params: &str) -> Self {
@@ -126,34 +167,34 @@ impl TraitFunction {
         self.lines.push(line);
         self
     }
-
-    fn get_return_type(&self) -> String {
-        match self.return_type.clone() {
-            Some(ty) => format!(" -> {}" , ty),
-            None => String::new(),
-        }
-    }
 }
 
-impl Display for TraitFunction {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        write!(
-            f,
-            "{}fn {}({}){}",
-            Indent(1),
-            self.name,
-            self.parameters,
-            self.get_return_type(),
-        ).ok();
+impl Render for TraitFunction {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        let indent = r.indent(1);
+        let end = match self.lines.is_empty() {
+            true => ";",
+            false => " {",
+        };
+
+        render_signature(
+            r,
+            1,
+            format_args!("fn {}", self.name),
+            &self.generics,
+            Parameters::new(parameter_list(&self.parameters)),
+            format_args!("{}{}", Returns(self.return_type.as_ref()), end),
+        )?;
 
         if self.lines.is_empty() {
-            writeln!(f, ";")
+            writeln!(r)
         } else {
-            writeln!(f, " {{").ok();
+            writeln!(r)?;
             for line in self.lines.iter() {
-                writeln!(f, "{}{}", Indent(1), line).ok();
+                line.render_at(r, 1)?;
+                writeln!(r)?;
             }
-            writeln!(f, "    }}")
+            writeln!(r, "{}}}", indent)
         }
     }
 }
@@ -163,7 +204,11 @@ impl Display for TraitFunction {
 pub struct TraitImpl {
     pub trait_def: Trait,
     pub typ: Type,
-    pub generics: Generics,
+    /// Parameters declared by the impl block itself: `impl<T: Display> ...`
+    pub impl_generics: Generics,
+    /// Arguments passed to the trait: `... Trait<ID> for ...`
+    pub trait_generics: Generics,
+    pub where_clause: WhereClause,
     pub associated_types: Vec<(TypeName, Type)>,
     pub functions: Vec<TraitFunction>,
 }
@@ -179,33 +224,69 @@ impl TraitImpl {
         self
     }
 
-    pub fn with_generics(mut self, generics: Generics) -> Self {
-        self.generics = generics;
+    pub fn with_impl_generics(mut self, generics: Generics) -> Self {
+        self.impl_generics = generics;
+        self
+    }
+
+    pub fn with_trait_generics(mut self, generics: Generics) -> Self {
+        self.trait_generics = generics;
         self
     }
 
-    fn panic_if_invalid(&self) {
+    /// Sets the arguments passed to the trait, as `with_trait_generics` does.
+    #[deprecated(note = "use `with_trait_generics`, and `with_impl_generics` for the parameters of the impl")]
+    pub fn with_generics(self, generics: Generics) -> Self {
+        self.with_trait_generics(generics)
+    }
+
+    pub fn with_where_clause(mut self, where_clause: WhereClause) -> Self {
+        self.where_clause = where_clause;
+        self
+    }
+
+    pub fn add_where_predicate(mut self, predicate: &str) -> Self {
+        self.where_clause.push(predicate);
+        self
+    }
+
+    /// Checks that the impl provides exactly the associated types and functions the trait requires, and a valid
+    /// number of trait arguments.
+    pub fn validate(&self) -> Result<(), CodeGenError> {
+        let name = &self.trait_def.name;
+
         // check types
-        let all_trait_types_included = self.trait_def.associated_types.iter()
-            .all(|ty| self.associated_types.iter().any(|(t1, _t2)| t1 == ty));
-        assert!(all_trait_types_included);
+        if let Some(missing) = self.trait_def.associated_type_names()
+            .find(|ty| !self.associated_types.iter().any(|(t1, _t2)| t1 == *ty)) {
+            return Err(CodeGenError::InvalidImpl(format!("impl of {} for {} is missing associated type {}", name, self.typ, missing)));
+        }
 
-        let all_included_types_are_required_by_trait = self.associated_types.iter()
-            .all(|(k, _)| self.trait_def.associated_types.contains(k));
-        assert!(all_included_types_are_required_by_trait);
+        if let Some((extra, _)) = self.associated_types.iter()
+            .find(|(k, _)| !self.trait_def.associated_type_names().any(|ty| ty == k)) {
+            return Err(CodeGenError::InvalidImpl(format!("associated type {} is not a member of trait {}", extra, name)));
+        }
 
         // check functions
-        let all_fns_are_required_by_trait = self.functions.iter()
-            .all(|f| self.fn_matches_trait_fn(f));
-        assert!(all_fns_are_required_by_trait);
+        if let Some(extra) = self.functions.iter().find(|f| !self.fn_matches_trait_fn(f)) {
+            return Err(CodeGenError::InvalidImpl(format!("function {} is not a member of trait {}", extra.name, name)));
+        }
 
-        let all_nondefault_trait_fns_are_impl = self.trait_def.functions.iter()
-            .filter(|f| f.return_type.is_some() && f.lines.is_empty())
-            .all(|f| self.trait_fn_matches_impl_fn(f));
-        assert!(all_nondefault_trait_fns_are_impl);
+        if let Some(missing) = self.trait_def.functions.iter()
+            .filter(|f| f.lines.is_empty())
+            .find(|f| !self.trait_fn_matches_impl_fn(f)) {
+            return Err(CodeGenError::InvalidImpl(format!("impl of {} for {} is missing function {}", name, self.typ, missing.name)));
+        }
 
         //check generics
-        assert_eq!(self.trait_def.generics.len(), self.generics.len());
+        let generics = &self.trait_def.generics;
+        if self.trait_generics.len() < generics.required_len() || generics.len() < self.trait_generics.len() {
+            return Err(CodeGenError::InvalidImpl(format!(
+                "trait {} takes {} to {} generic arguments, {} given",
+                name, generics.required_len(), generics.len(), self.trait_generics.len()
+            )));
+        }
+
+        Ok(())
     }
 
     fn fn_matches_trait_fn(&self, function: &TraitFunction) -> bool {
@@ -221,46 +302,93 @@ impl TraitImpl {
     }
 }
 
-impl Display for TraitImpl {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        self.panic_if_invalid();
+impl Render for TraitImpl {
+    fn render(&self, r: &mut Renderer<'_>) -> Result<(), Error> {
+        if let Err(problem) = self.validate() {
+            return Err(r.invalid(problem));
+        }
 
         let has_types = !self.associated_types.is_empty();
         let has_functions = !self.functions.is_empty();
 
-        write!(f, "impl {}{} for {} {{", self.trait_def.name, self.generics, self.typ).ok();
+        let head = ImplHead(self);
+        let opening = match self.where_clause.is_empty() {
+            true => " {",
+            false => "",
+        };
+
+        // like rustfmt, a header that does not fit is broken before `for`, with `{` on its own line
+        let broken = !r.fits(0, format_args!("{} for {}{}", head, self.typ, opening));
+
+        match broken {
+            true => write!(r, "{}{}\n{}for {}", r.indent(0), head, r.indent(1), self.typ)?,
+            false => write!(r, "{}{} for {}", r.indent(0), head, self.typ)?,
+        }
+
+        // like rustfmt, an empty impl with a single predicate keeps `where` on the header line
+        let single = match (self.where_clause.iter().as_slice(), has_types || has_functions || broken) {
+            ([predicate], false) if r.style.empty_body == EmptyBody::Inline => Some(predicate),
+            _ => None,
+        };
+
+        if let Some(predicate) = single {
+            return match r.fits(0, format_args!("{} for {} where {} {{}}", head, self.typ, predicate)) {
+                true => writeln!(r, " where {} {{}}", predicate),
+                false => writeln!(r, " where\n{}{}\n{}{{\n{}}}", r.indent(1), predicate, r.indent(0), r.indent(0)),
+            };
+        }
+
+        if !self.where_clause.is_empty() {
+            writeln!(r)?;
+            r.render(&self.where_clause)?;
+            write!(r, "{}{{", r.indent(0))?;
+        } else if broken {
+            write!(r, "\n{}{{", r.indent(0))?;
+        } else {
+            write!(r, " {{")?;
+        }
+
+        if !has_types && !has_functions {
+            r.empty_body(0, broken || !self.where_clause.is_empty())?;
+            return writeln!(r);
+        }
 
         if has_types {
-            writeln!(f).ok();
+            writeln!(r)?;
         }
 
         for (gen, conc) in self.associated_types.iter() {
-            writeln!(f, "{}type {} = {};", Indent(1), {gen}, {conc}).ok();
+            writeln!(r, "{}type {} = {};", r.indent(1), gen, conc)?;
         }
 
         if has_functions {
-            writeln!(f).ok();
+            writeln!(r)?;
         }
 
-        let functions: Vec<String> = self.functions.iter()
-            .map(ToString::to_string)
-            .collect();
-
-        let functions = StrConcat {
-            iter: functions,
-            left_bound: "",
-            right_bound: "",
-            item_prepend: "",
-            item_append: "",
-            join: "\n"
-        };
+        for (i, function) in self.functions.iter().enumerate() {
+            if i > 0 {
+                r.item_separator()?;
+            }
 
-        write!(f, "{}", functions).ok();
+            r.render(function)?;
+        }
 
-        writeln!(f, "}}")
+        writeln!(r, "{}}}", r.indent(0))
     }
 }
 
+/// The `impl<T> Trait<T>` an implementation starts with, before `for`.
+#[derive(Copy, Clone)]
+struct ImplHead<'a>(&'a TraitImpl);
+
+impl Display for ImplHead<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "impl{} {}{}", self.0.impl_generics, self.0.trait_def.name, self.0.trait_generics)
+    }
+}
+
+display_via_render!(Trait, TraitFunction, TraitImpl);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -306,6 +434,25 @@ mod tests {
         assert_eq!("pub trait Test {\n    type Idx;\n}\n", t.to_string());
     }
 
+    #[test]
+    fn define_trait_with_bounded_associated_type_and_supertraits() {
+        let t = Trait::new("Test")
+            .with_supertraits("Clone + Debug")
+            .add_associated_type("Iter: Iterator<Item = u32>");
+
+        assert_eq!("pub trait Test: Clone + Debug {\n    type Iter: Iterator<Item = u32>;\n}\n", t.to_string());
+        assert_eq!(vec![&TypeName::new("Iter")], t.associated_type_names().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn function_def_with_generics() {
+        let f = TraitFunction::new("hash")
+            .with_generics(Generics::one("H: Hasher"))
+            .with_parameters("&self, state: &mut H");
+
+        assert_eq!("    fn hash<H: Hasher>(&self, state: &mut H);\n", f.to_string());
+    }
+
     #[test]
     fn define_trait_single_fn() {
         let t = Trait::new("Test")
@@ -367,6 +514,16 @@ mod tests {
         let _should_panic = i.to_string();
     }
 
+    #[test]
+    #[allow(deprecated)]
+    fn with_generics_sets_the_trait_arguments() {
+        let t = Trait::new("Trait").with_generics(Generics::one("ID"));
+        let i = t.impl_for(&Type::new("Foo")).with_generics(Generics::one("u32"));
+
+        assert_eq!(Generics::one("u32"), i.trait_generics);
+        assert!(i.impl_generics.is_empty());
+    }
+
     #[test]
     fn implementation_with_associated_types() {
         let t = Trait::new("Trait").add_associated_type("Idx");
@@ -410,6 +567,90 @@ mod tests {
         );
     }
 
+    #[test]
+    fn blanket_implementation() {
+        let t = Trait::new("MyTrait");
+        let i = t.impl_for(&Type::new("T"))
+            .with_impl_generics(Generics::one("T: Display"));
+
+        assert_eq!("impl<T: Display> MyTrait for T {}\n", i.to_string());
+    }
+
+    #[test]
+    fn generic_trait_for_generic_type() {
+        let t = Trait::new("Trait").with_generics(Generics::one("ID"));
+        let i = t.impl_for(&Type::new("Component<ID, T>"))
+            .with_impl_generics(Generics::two("ID", "T"))
+            .with_trait_generics(Generics::one("ID"));
+
+        assert_eq!("impl<ID, T> Trait<ID> for Component<ID, T> {}\n", i.to_string());
+    }
+
+    #[test]
+    fn implementation_with_where_clause() {
+        let t = Trait::new("Trait")
+            .add_function_definition(TraitFunction::new("method"));
+
+        let i = t.impl_for(&Type::new("Wrapper<T>"))
+            .with_impl_generics(Generics::one("T"))
+            .add_where_predicate("T: Clone + Debug")
+            .add_function(TraitFunction::new("method").add_line(CodeLine::new(0, "panic!();")));
+
+        assert_eq!(
+            "impl<T> Trait for Wrapper<T>\nwhere\n    T: Clone + Debug,\n{\n    fn method() {\n        panic!();\n    }\n}\n",
+            i.to_string()
+        );
+    }
+
+    #[test]
+    fn empty_implementation_with_where_clause() {
+        let i = Trait::new("Marker")
+            .impl_for(&Type::new("Wrapper<T>"))
+            .with_impl_generics(Generics::one("T"))
+            .with_where_clause(WhereClause::new("T: Send"));
+
+        assert_eq!("impl<T> Marker for Wrapper<T> where T: Send {}\n", i.to_string());
+        assert_eq!(
+            "impl<T> Marker for Wrapper<T>\nwhere\n    T: Send,\n{\n}\n",
+            Style::new().with_empty_body(EmptyBody::Open).render(&i)
+        );
+        assert_eq!(
+            "impl<T> Marker for Wrapper<T> where\n    T: Send\n{\n}\n",
+            Style::new().with_max_width(40).render(&i)
+        );
+
+        let two = i.add_where_predicate("T: Sync");
+        assert_eq!("impl<T> Marker for Wrapper<T>\nwhere\n    T: Send,\n    T: Sync,\n{\n}\n", two.to_string());
+    }
+
+    #[test]
+    #[should_panic]
+    fn implementation_missing_fn_without_return_type() {
+        let t = Trait::new("Trait")
+            .add_function_definition(TraitFunction::new("method").with_parameters("&mut self"));
+        let i = t.impl_for(&Type::new("Struct"));
+
+        let _should_panic = i.to_string();
+    }
+
+    #[test]
+    fn implementation_may_omit_defaulted_generics() {
+        let t = Trait::new("Trait").with_generics(Generics::one("Rhs = Self"));
+        let i = t.impl_for(&Type::new("Test"));
+
+        assert_eq!("impl Trait for Test {}\n", i.to_string());
+    }
+
+    #[test]
+    #[should_panic]
+    fn implementation_with_too_many_generics_panics() {
+        let t = Trait::new("Trait").with_generics(Generics::one("Rhs = Self"));
+        let i = t.impl_for(&Type::new("Test"))
+            .with_trait_generics(Generics::two("u32", "u64"));
+
+        let _panics = i.to_string();
+    }
+
     #[test]
     #[should_panic]
     fn implementation_missing_generics_panics() {
@@ -419,4 +660,28 @@ mod tests {
 
         let _panics = i.to_string();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn long_trait_impl_header_is_broken_before_for() {
+        let t = Trait::new("Convert").with_generics(Generics::one("T"));
+        let i = t.impl_for(&Type::new("Wrapper<T>"))
+            .with_impl_generics(Generics::one("T: Clone"))
+            .with_trait_generics(Generics::one("T"));
+
+        assert_eq!("impl<T: Clone> Convert<T> for Wrapper<T> {}\n", i.to_string());
+        assert_eq!(
+            "impl<T: Clone> Convert<T>\n    for Wrapper<T>\n{\n}\n",
+            Style::new().with_max_width(40).render(&i)
+        );
+    }
+
+    #[test]
+    fn long_trait_function_is_broken() {
+        let f = TraitFunction::new("convert").with_parameters("&self, value: T, options: Options").with_return("T");
+
+        assert_eq!(
+            "    fn convert(\n        &self,\n        value: T,\n        options: Options,\n    ) -> T;\n",
+            Style::new().with_max_width(50).render(&f)
+        );
+    }
+}
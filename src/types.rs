@@ -1,4 +1,5 @@
-use crate::Generics;
+use crate::{Generics, CodeGenError, InputKind, NameRules};
+use crate::identifiers::is_ident_continue;
 use std::str::FromStr;
 use std::fmt::{Display, Formatter, Error};
 
@@ -10,24 +11,87 @@ impl TypeName {
         s.parse().unwrap()
     }
 
+    pub fn try_new(s: &str) -> Result<Self, CodeGenError> {
+        s.parse()
+    }
+
+    /// Parses `s` as a type name whose path segments are checked against `rules`, which decide how keywords are
+    /// handled.
+    pub fn parse_with(s: &str, rules: &NameRules) -> Result<Self, CodeGenError> {
+        if s.is_empty() {
+            return Err(CodeGenError::Empty { kind: InputKind::TypeName });
+        }
+
+        let rest = strip_type_prefixes(s);
+        if rest.contains(' ') {
+            return Err(CodeGenError::name(InputKind::TypeName, s, "cannot contain spaces"));
+        }
+
+        let prefixes = &s[..s.len() - rest.len()];
+        let path_end = rest.find(|c: char| !is_ident_continue(c) && c != ':' && c != '#').unwrap_or(rest.len());
+        let (path, arguments) = rest.split_at(path_end);
+
+        // tuples, arrays, slices and the never type are written as they are
+        if path.is_empty() && rest.starts_with(['(', '[', '!']) {
+            return Ok(TypeName(s.to_string()));
+        }
+
+        // the inferred type `_` and `Fn(A) -> B` style arguments
+        if path.is_empty() || !(arguments.is_empty() || arguments.starts_with('(')) {
+            return Err(CodeGenError::name(InputKind::TypeName, s, "must be a path, a reference, a pointer, a tuple, an array or a slice"));
+        }
+
+        if path == "_" {
+            return Ok(TypeName(s.to_string()));
+        }
+
+        let mut checked = String::from(prefixes);
+        for (i, segment) in path.split("::").enumerate() {
+            if i > 0 {
+                checked.push_str("::");
+            }
+
+            match segment {
+                // a leading `::`
+                "" if i == 0 && path.len() > 2 => {},
+                "crate" | "self" | "Self" | "super" if i == 0 => checked.push_str(segment),
+                "super" if checked.ends_with("super::") || checked.ends_with("self::") => checked.push_str(segment),
+                _ => checked.push_str(&rules.identifier(InputKind::TypeName, segment)?),
+            }
+        }
+        checked.push_str(arguments);
+
+        Ok(TypeName(checked))
+    }
+
     pub fn as_str(&self) -> &str {
         self.0.as_str()
     }
 }
 
 impl FromStr for TypeName {
-    type Err = String;
+    type Err = CodeGenError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.contains(' ') {
-            return Err(format!("TypeName cannot contain spaces: {}", s));
-        }
-
-        if s.is_empty() {
-            return Err("TypeName cannot be empty".to_string());
-        }
+        Self::parse_with(s, &NameRules::DEFAULT)
+    }
+}
 
-        Ok(TypeName(s.to_string()))
+/// Strips reference, pointer and trait object prefixes, which are the only places a type may contain spaces:
+/// `&'a mut T`, `*const T`, `dyn T`, `impl T`, and the `?` of a `?Sized` bound.
+fn strip_type_prefixes(mut s: &str) -> &str {
+    loop {
+        let stripped = if let Some(rest) = s.strip_prefix('&') {
+            rest
+        } else if let Some(rest) = s.strip_prefix('\'') {
+            rest.trim_start_matches(|c: char| c.is_alphanumeric() || c == '_').trim_start()
+        } else if let Some(rest) = ["mut ", "const ", "dyn ", "impl ", "*", "?"].iter().find_map(|p| s.strip_prefix(p)) {
+            rest.trim_start()
+        } else {
+            return s;
+        };
+
+        s = stripped;
     }
 }
 
@@ -37,10 +101,10 @@ impl Display for TypeName {
     }
 }
 
-impl Into<Type> for TypeName {
-    fn into(self) -> Type {
+impl From<TypeName> for Type {
+    fn from(name: TypeName) -> Self {
         Type {
-            name: self,
+            name,
             types: Default::default(),
         }
     }
@@ -56,14 +120,18 @@ impl Type {
     pub fn new(s: &str) -> Self {
         Self::from_str(s).unwrap()
     }
+
+    pub fn try_new(s: &str) -> Result<Self, CodeGenError> {
+        Self::from_str(s)
+    }
 }
 
 impl FromStr for Type {
-    type Err = String;
+    type Err = CodeGenError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Some('\'') = s.chars().nth(0) {
-            return Err(format!("Type cannot start with an apostrophe: {}", s));
+            return Err(CodeGenError::syntax(InputKind::Type, s, "cannot start with an apostrophe"));
         }
 
         match s.find('<') {
@@ -106,10 +174,31 @@ mod tests {
         assert_eq!(ty, Type { name: TypeName::new("Test"), types: Generics::two("ID", "T") })
     }
 
+    #[test]
+    fn reference_types() {
+        assert_eq!("&'a Meters", Type::new("&'a Meters").to_string());
+        assert_eq!("&mut Vec<T>", Type::new("&mut Vec<T>").to_string());
+        assert_eq!("Box<dyn Fn>", Type::new("Box<dyn Fn>").to_string());
+        assert!(Type::from_str("&'a Not Valid").is_err());
+    }
+
     #[test]
     fn nested_generics() {
         let component = Type::from_str("Component<Self, Id<Body>>").unwrap();
 
         assert_eq!("Component<Self, Id<Body>>", component.to_string());
     }
+
+    #[test]
+    fn type_names_are_paths() {
+        assert_eq!("crate::shapes::r#type", TypeName::new("crate::shapes::type").to_string());
+        assert_eq!("super::super::Point", TypeName::new("super::super::Point").to_string());
+        assert_eq!("::std::fmt::Display", TypeName::new("::std::fmt::Display").to_string());
+        assert_eq!("Fn(u32)", TypeName::new("Fn(u32)").to_string());
+        assert!(TypeName::from_str("[u8]").is_ok());
+        assert!(TypeName::from_str("Foo-Bar").is_err());
+        assert!(TypeName::from_str("1Abc").is_err());
+        assert!(TypeName::from_str("a::self").is_err());
+        assert!(TypeName::parse_with("type", &NameRules::new().rejecting_keywords()).is_err());
+    }
 }
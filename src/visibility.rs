@@ -1,18 +1,13 @@
 use crate::*;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum Visibility {
+    #[default]
     Pub,
     PubCrate,
     Private,
 }
 
-impl Default for Visibility {
-    fn default() -> Self {
-        Self::Pub
-    }
-}
-
 impl Display for Visibility {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         match self {
@@ -0,0 +1,39 @@
+//! The only test in this binary, so the counting allocator sees no allocations from tests running in parallel.
+
+use code_gen::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+mod common;
+
+/// Counts allocations, to check that streaming does not allocate per item.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn streaming_does_not_allocate_per_item() {
+    let items = 2_000;
+    let module = common::large_module(items);
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    Style::default().write_into(&mut io::sink(), &module).unwrap();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    assert!(allocations < items / 100, "{} allocations while streaming {} items", allocations, items);
+}
@@ -0,0 +1,50 @@
+use code_gen::*;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+#[path = "fixtures/shapes/build.rs"]
+#[allow(dead_code)]
+mod shapes_build;
+
+#[test]
+fn fixture_crate_includes_generated_code() {
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/shapes");
+    let out_dir = env::temp_dir().join(format!("code_gen_build_script_{}", std::process::id()));
+    fs::remove_dir_all(&out_dir).ok();
+
+    let output = shapes_build::generate(BuildOutput::new("shapes").with_out_dir(&out_dir), &fixture).unwrap();
+
+    assert_eq!(
+        "#[derive(Debug, Default, Clone)]\npub struct Point {\n    pub x: f64,\n    pub y: f64,\n}\n\n#[derive(Debug, Default, Clone)]\npub struct Size {\n    pub width: u32,\n    pub height: u32,\n}\n",
+        fs::read_to_string(out_dir.join("shapes.rs")).unwrap()
+    );
+    assert_eq!(format!("cargo:rerun-if-changed={}\n", fixture.join("shapes.txt").display()), output.rerun_if_changed());
+
+    let lib = fs::read_to_string(fixture.join("src/lib.rs")).unwrap();
+    assert!(lib.starts_with(&output.include_snippet()));
+
+    // the fixture's lib.rs compiles against the generated file
+    let status = Command::new(env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()))
+        .args(["--crate-type", "lib", "--edition", "2018", "--crate-name", "shapes", "--out-dir"])
+        .arg(&out_dir)
+        .arg(fixture.join("src/lib.rs"))
+        .env("OUT_DIR", &out_dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // regenerating with unchanged definitions leaves the file alone
+    assert!(!output.write_mod(&Mod::new("shapes")
+        .add_item(Struct::new("Point")
+            .with_derives(Derives::with_debug_default_clone())
+            .add_field(Field::new("x", "f64"))
+            .add_field(Field::new("y", "f64")))
+        .add_item(Struct::new("Size")
+            .with_derives(Derives::with_debug_default_clone())
+            .add_field(Field::new("width", "u32"))
+            .add_field(Field::new("height", "u32")))).unwrap());
+
+    fs::remove_dir_all(&out_dir).unwrap();
+}
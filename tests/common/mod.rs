@@ -0,0 +1,29 @@
+use code_gen::*;
+
+/// A module with `items` structs, each with an inherent impl and a trait impl.
+pub fn large_module(items: usize) -> Mod {
+    let mut module = Mod::new("generated");
+    let display = Trait::new("Describe")
+        .add_function_definition(TraitFunction::new("describe").with_parameters("&self").with_return("String"));
+
+    for i in 0..items {
+        let name = format!("Entity{}", i);
+
+        module = module
+            .add_item(Struct::new(&format!("{}<T>", name))
+                .with_derives(Derives::with_debug_default_clone())
+                .add_field(Field::new("id", "u64"))
+                .add_field(Field::new("value", "T")))
+            .add_item(Impl::new(&format!("{}<u32>", name))
+                .add_function(Function::new("new")
+                    .with_parameters("id: u64, value: u32, a_long_parameter_name: u32, another_long_parameter_name: u32")
+                    .with_return("Self".to_string())
+                    .add_line(CodeLine::new(0, "Self { id, value }")))
+                .add_function(Function::new("doubled").with_parameters("&self").with_return("u32".to_string()).add_line(CodeLine::new(0, "self.value * 2"))))
+            .add_item(display.impl_for(&Type::new(&format!("{}<u32>", name)))
+                .add_function(TraitFunction::new("describe").with_parameters("&self").with_return("String")
+                    .add_line(CodeLine::new(0, "format!(\"{:?}\", self)"))));
+    }
+
+    module
+}
@@ -0,0 +1,30 @@
+use code_gen::*;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+fn main() {
+    let output = generate(BuildOutput::new("shapes"), Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap();
+    output.print_rerun_if_changed();
+}
+
+/// Generates a struct for each line of `shapes.txt`, such as `Point: x f64, y f64`.
+pub fn generate(output: BuildOutput, manifest_dir: &Path) -> io::Result<BuildOutput> {
+    let definitions = manifest_dir.join("shapes.txt");
+    let mut module = Mod::new("shapes");
+
+    for line in fs::read_to_string(&definitions)?.lines().filter(|l| !l.trim().is_empty()) {
+        let (name, fields) = line.split_once(':').unwrap();
+        let mut s = Struct::new(name.trim()).with_derives(Derives::with_debug_default_clone());
+
+        for field in fields.split(',') {
+            let (field_name, field_type) = field.trim().split_once(' ').unwrap();
+            s = s.add_field(Field::new(field_name, field_type.trim()));
+        }
+
+        module.push(s);
+    }
+
+    output.write_mod(&module)?;
+    Ok(output.add_input(definitions))
+}
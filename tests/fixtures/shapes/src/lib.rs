@@ -0,0 +1,5 @@
+include!(concat!(env!("OUT_DIR"), "/shapes.rs"));
+
+pub fn area(size: &Size) -> u32 {
+    size.width * size.height
+}
@@ -0,0 +1,82 @@
+use code_gen::*;
+use std::env;
+use std::fs;
+use std::process::Command;
+
+/// Runs `rustfmt --check` on `code`, returning its diff if rustfmt would change it, or `None` if rustfmt is not
+/// installed. The code goes through a file, as rustfmt reports success for stdin even when it finds a diff.
+fn rustfmt_check(name: &str, code: &str) -> Option<Result<(), String>> {
+    let path = env::temp_dir().join(format!("code_gen_rustfmt_{}_{}.rs", std::process::id(), name));
+    fs::write(&path, code).unwrap();
+
+    let output = Command::new("rustfmt")
+        .args(["--check", "--edition", "2021", "--color", "never"])
+        .arg(&path)
+        .output();
+    fs::remove_file(&path).unwrap();
+
+    let output = output.ok()?;
+    match output.status.success() {
+        true => Some(Ok(())),
+        false => Some(Err(format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr)))),
+    }
+}
+
+fn samples() -> Mod {
+    let area = Trait::new("Area")
+        .add_function_definition(TraitFunction::new("area").with_parameters("&self").with_return("f64"));
+
+    Mod::new("samples")
+        .add_item(Struct::new("Point<T>")
+            .with_derives(Derives::with_debug_default_clone())
+            .add_field(Field::new("x", "T"))
+            .add_field(Field::new("y", "T")))
+        .add_item(Enum::new("Shape")
+            .with_derives(Derives::with_debug_clone())
+            .add_option(EnumOption::new("Triangle", vec!["Point<Coordinates3D>", "Point<Coordinates3D>", "Point<Coordinates3D>"]))
+            .add_option(EnumOption::new("Line", vec!["Point<Coordinates3D>", "Point<Coordinates3D>"]))
+            .add_option(EnumOption::new("Label", vec!["AVeryLongTypeNameThatFillsMostOfTheLineOnItsOwnAndThenSome"]))
+            .add_option(EnumOption::new("Dot", vec!["Point<f64>"]))
+            .add_option(EnumOption::new("Empty", vec![])))
+        .add_item(area.impl_for(&Type::new("Shape"))
+            .add_function(TraitFunction::new("area").with_parameters("&self").with_return("f64")
+                .add_line(CodeLine::new(0, "0.0"))))
+        .add_item(area.clone())
+        .add_item(Struct::new("Generic<TFirstParameter, TSecondParameter, TThirdParameter, TFourthParameter, TFifthXYZ>")
+            .add_field(Field::new("first", "TFirstParameter")))
+        .add_item(Struct::new("Broken<TFirstParameter, TSecondParameter, TThirdParameter, TFourthParameter, TFifthParameter>")
+            .add_field(Field::new("first", "TFirstParameter")))
+        .add_item(Enum::new("Uninhabited<TFirstParameter, TSecondParameter, TThirdParameter, TFourthParameterLongerXYZ>"))
+        .add_item(Trait::new("Marker").impl_for(&Type::new("Point<T>"))
+            .with_impl_generics(Generics::one("T"))
+            .add_where_predicate("T: Copy"))
+        .add_item(Trait::new("Marker").impl_for(&Type::new("Point<T>"))
+            .with_impl_generics(Generics::one("T"))
+            .add_where_predicate("T: Clone + PartialEq + Eq + PartialOrd + Ord + std::hash::Hash + std::fmt::Debug"))
+        .add_item(Trait::new("Marker").impl_for(&Type::new("Shape"))
+            .add_where_predicate("Point<f64>: Copy")
+            .add_where_predicate("Point<u8>: Copy"))
+        .add_item(area.impl_for(&Type::new("Point<T>"))
+            .with_impl_generics(Generics::one("T"))
+            .add_where_predicate("T: Copy")
+            .add_function(TraitFunction::new("area").with_parameters("&self").with_return("f64")
+                .add_line(CodeLine::new(0, "0.0"))))
+        .add_item(Trait::new("Marker"))
+        .add_item(Impl::new("Shape")
+            .add_function(Function::new("with_a_long_name")
+                .with_parameters("first_parameter: u32, second_parameter: u32, third_parameter: u32, fourth: u32")
+                .with_return("Self".to_string())
+                .add_line(CodeLine::new(0, "Shape::Empty"))))
+}
+
+/// Fails when rustfmt is not installed, unless `CODE_GEN_SKIP_RUSTFMT` is set.
+#[test]
+fn generated_code_is_rustfmt_clean() {
+    let code = samples().file_contents().to_string();
+
+    match rustfmt_check("samples", &code) {
+        Some(result) => assert_eq!(Ok(()), result, "rustfmt would change:\n{}", code),
+        None if env::var_os("CODE_GEN_SKIP_RUSTFMT").is_some() => eprintln!("rustfmt is not installed, skipping"),
+        None => panic!("rustfmt is not installed; install it or set CODE_GEN_SKIP_RUSTFMT to skip this test"),
+    }
+}
@@ -0,0 +1,62 @@
+use code_gen::*;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+mod common;
+
+#[test]
+fn streaming_matches_to_string() {
+    let module = common::large_module(200);
+    let rendered = module.to_string();
+
+    let mut streamed = Vec::with_capacity(rendered.len());
+    Style::default().write_into(&mut streamed, &module).unwrap();
+
+    assert_eq!(rendered.as_bytes(), streamed.as_slice());
+}
+
+/// The fastest of `runs` runs of `f`.
+fn fastest<F: FnMut()>(runs: usize, mut f: F) -> Duration {
+    (0..runs)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+/// A benchmark, run with `cargo test --release --test streaming -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn bench_write_into_against_to_string() {
+    let module = common::large_module(20_000);
+    let style = Style::default();
+
+    let to_string = fastest(5, || { module.to_string(); });
+    let write_into = fastest(5, || style.write_into(&mut io::sink(), &module).unwrap());
+
+    println!("to_string() {:?}, write_into(sink) {:?}", to_string, write_into);
+    assert!(write_into <= to_string * 2, "write_into {:?} is much slower than to_string {:?}", write_into, to_string);
+}
+
+#[test]
+fn write_errors_are_reported() {
+    struct Full;
+
+    impl Write for Full {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::WriteZero, "disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    match Style::default().write_into(&mut Full, &Struct::new("A")) {
+        Err(RenderError::Io(error)) => assert_eq!(io::ErrorKind::WriteZero, error.kind()),
+        other => panic!("expected an I/O error, got {:?}", other),
+    }
+}